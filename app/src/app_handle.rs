@@ -1,24 +1,38 @@
 // app/src/app_handle.rs
-use crate::{drawing_app::DrawingApp, rendering, utils::RenderLoopHandle};
-use std::cell::RefCell;
+use crate::{
+    commands, dom_creation::AppDom, drawing_app::DrawingApp, event_listeners::EventListener,
+    keymap_storage, rendering,
+};
+use base::KeyCombo;
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
-use web_sys::{console, MouseEvent};
+use web_sys::console;
 
 /// A handle to the application, designed to be held by JavaScript.
-/// It owns the core application logic and all event listener/render loop closures.
-/// When this handle is dropped (e.g., by JS garbage collection), the closures
-/// are dropped too, automatically cleaning up the event listeners and stopping the loop.
+/// It owns the core application logic, the generated DOM and all event
+/// listener/render loop closures. Call `destroy()` to tear the app down
+/// deterministically instead of waiting for JS garbage collection.
 #[wasm_bindgen]
 pub struct AppHandle {
     // The core application logic, shared with closures.
     pub(crate) app: Rc<DrawingApp>,
-    // Event listener closures are stored here to keep them alive.
-    pub(crate) _on_mouse_down: Closure<dyn FnMut(MouseEvent)>,
-    pub(crate) _on_clear: Closure<dyn FnMut()>,
-    // This holds the handle to the render loop closure. It's in a RefCell
-    // because we create it in `start()`, not in the constructor.
-    pub(crate) _render_loop: RefCell<Option<RenderLoopHandle>>,
+    // The elements created by `mount_app`, removed again on `destroy()`.
+    pub(crate) dom: AppDom,
+    // Event listeners are stored here to keep them alive. Dropping one
+    // unregisters it from its target.
+    pub(crate) listeners: RefCell<Vec<EventListener>>,
+    // Set once `destroy()` has run, so the app cannot be restarted afterwards.
+    pub(crate) destroyed: Cell<bool>,
+}
+
+/// A command as described to JavaScript by `getCommands`.
+#[derive(Serialize)]
+struct CommandInfo<'a> {
+    id: &'a str,
+    label: &'a str,
+    bindings: Vec<String>,
 }
 
 /// Public methods exposed to JavaScript via the `AppHandle`.
@@ -34,10 +48,55 @@ impl AppHandle {
     /// Starts the render loop.
     #[wasm_bindgen(js_name = start)]
     pub fn start(&self) -> Result<(), JsValue> {
+        if self.destroyed.get() {
+            return Err(JsValue::from_str(
+                "Cannot start an app that has been destroyed",
+            ));
+        }
         console::log_1(&"Starting render loop...".into());
-        let render_loop_handle = rendering::start_render_loop(self.app.clone())?;
-        *self._render_loop.borrow_mut() = Some(render_loop_handle);
-        Ok(())
+        rendering::start_render_loop(self.app.clone())
+    }
+
+    /// Pauses the render loop. No frames are rendered and no simulation time
+    /// passes until `resume()` is called.
+    #[wasm_bindgen(js_name = pause)]
+    pub fn pause(&self) {
+        self.app.paused_by_visibility.set(false);
+        rendering::pause_render_loop(&self.app);
+    }
+
+    /// Resumes a paused render loop. Has no effect before `start()` or after `destroy()`.
+    #[wasm_bindgen(js_name = resume)]
+    pub fn resume(&self) -> Result<(), JsValue> {
+        self.app.paused_by_visibility.set(false);
+        rendering::resume_render_loop(&self.app)
+    }
+
+    /// Returns whether the render loop is currently running.
+    #[wasm_bindgen(js_name = isRunning)]
+    pub fn is_running(&self) -> bool {
+        self.app.running.get()
+    }
+
+    /// Draws the current state once, outside the render loop. Used by the
+    /// browser benchmark harness (`static/bench.html`).
+    #[wasm_bindgen(js_name = renderFrame)]
+    pub fn render_frame(&self) -> Result<(), JsValue> {
+        self.app.render_frame()
+    }
+
+    /// Tears the application down: cancels the pending animation frame, removes
+    /// every event listener, removes the generated DOM and releases the render
+    /// loop's reference cycle. Calling it more than once is a no-op.
+    #[wasm_bindgen(js_name = destroy)]
+    pub fn destroy(&self) {
+        if self.destroyed.replace(true) {
+            return;
+        }
+        console::log_1(&"Destroying app...".into());
+        rendering::stop_render_loop(&self.app);
+        self.listeners.borrow_mut().clear();
+        self.dom.remove();
     }
 
     /// Adds a circle to the state. Exposed for testing and potential external calls.
@@ -46,6 +105,89 @@ impl AppHandle {
         self.app.add_circle_at_point(x, y);
     }
 
+    /// Enables or disables the scale-in animation for newly added circles.
+    #[wasm_bindgen(js_name = setEntranceAnimation)]
+    pub fn set_entrance_animation(&self, enabled: bool) {
+        self.app.entrance_animation.set(enabled);
+    }
+
+    /// Turns the rigid-body simulation of the circles on or off.
+    #[wasm_bindgen(js_name = setPhysicsEnabled)]
+    pub fn set_physics_enabled(&self, enabled: bool) {
+        self.app.set_physics_enabled(enabled);
+    }
+
+    /// Seeds the simulation from now on, so enabling it again replays the same
+    /// way. Meant for tests.
+    #[wasm_bindgen(js_name = setPhysicsSeed)]
+    pub fn set_physics_seed(&self, seed: u32) {
+        self.app.state.borrow_mut().physics_seed = Some(seed.into());
+    }
+
+    /// Returns whether the rigid-body simulation is running.
+    #[wasm_bindgen(js_name = isPhysicsEnabled)]
+    pub fn is_physics_enabled(&self) -> bool {
+        self.app.state.borrow().physics.is_some()
+    }
+
+    /// Runs the command with the given id, e.g. `"edit.undo"` or `"view.zoomIn"`.
+    #[wasm_bindgen(js_name = executeCommand)]
+    pub fn execute_command(&self, id: &str) -> Result<(), JsValue> {
+        commands::execute_command(&self.app, id)
+    }
+
+    /// Returns every command as `{ id, label, bindings }`, with the bindings
+    /// written like `"Mod+Shift+Z"`.
+    #[wasm_bindgen(js_name = getCommands)]
+    pub fn get_commands(&self) -> Result<JsValue, JsValue> {
+        let keymap = self.app.keymap.borrow();
+        let infos: Vec<CommandInfo> = commands::COMMANDS
+            .iter()
+            .map(|c| CommandInfo {
+                id: c.id,
+                label: c.label,
+                bindings: keymap
+                    .bindings(c.id)
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            })
+            .collect();
+        serde_wasm_bindgen::to_value(&infos).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Replaces a command's key bindings and persists them. Fails without changing
+    /// anything if a combination is invalid or already used by another command.
+    #[wasm_bindgen(js_name = rebindCommand)]
+    pub fn rebind_command(&self, id: &str, keys: Vec<String>) -> Result<(), JsValue> {
+        if commands::find_command(id).is_none() {
+            return Err(JsValue::from_str(&format!("Unknown command '{}'", id)));
+        }
+        let combos = keys
+            .iter()
+            .map(|k| k.parse::<KeyCombo>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let mut keymap = self.app.keymap.borrow_mut();
+        keymap
+            .set_bindings(id, combos)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        keymap_storage::save_keymap(&keymap)
+    }
+
+    /// Restores the default key bindings and forgets the saved ones.
+    #[wasm_bindgen(js_name = resetKeymap)]
+    pub fn reset_keymap(&self) -> Result<(), JsValue> {
+        *self.app.keymap.borrow_mut() = commands::default_keymap();
+        keymap_storage::clear_saved_keymap()
+    }
+
+    /// Returns the active tool, e.g. `"circle"` or `"eraser"`.
+    #[wasm_bindgen(js_name = getTool)]
+    pub fn get_tool(&self) -> String {
+        self.app.tool.get().to_string()
+    }
+
     /// Clears all shapes. Exposed for testing and potential external calls.
     #[wasm_bindgen(js_name = clearCanvas)]
     pub fn clear_canvas(&self) {
@@ -57,4 +199,12 @@ impl AppHandle {
     pub fn get_drawing_state(&self) -> Result<JsValue, JsValue> {
         self.app.get_drawing_state()
     }
+
+    /// Restores a state previously returned by `getDrawingState`, e.g. after the
+    /// dev server hot-swapped the WASM module. Throws if the state's schema no
+    /// longer matches, leaving the current drawing untouched.
+    #[wasm_bindgen(js_name = loadDrawingState)]
+    pub fn load_drawing_state(&self, state: JsValue) -> Result<(), JsValue> {
+        self.app.load_drawing_state(state)
+    }
 }
@@ -0,0 +1,163 @@
+// app/src/commands.rs
+use crate::{drawing_app::DrawingApp, rendering};
+use base::{KeyCombo, Keymap, Tool};
+use wasm_bindgen::prelude::*;
+
+/// A user-facing action with a stable id. Keyboard shortcuts, toolbar buttons
+/// and `AppHandle::executeCommand` all go through this table.
+pub(crate) struct Command {
+    pub(crate) id: &'static str,
+    pub(crate) label: &'static str,
+    // The key combinations bound to this command unless the user rebinds it.
+    pub(crate) default_keys: &'static [&'static str],
+    // Whether holding the keys down runs the command repeatedly.
+    pub(crate) repeatable: bool,
+    run: fn(&DrawingApp) -> Result<(), JsValue>,
+}
+
+pub(crate) const COMMANDS: &[Command] = &[
+    Command {
+        id: "canvas.clear",
+        label: "Clear Canvas",
+        default_keys: &["Mod+Shift+Backspace"],
+        repeatable: false,
+        run: |app| {
+            app.clear_canvas();
+            Ok(())
+        },
+    },
+    Command {
+        id: "edit.undo",
+        label: "Undo",
+        default_keys: &["Mod+Z"],
+        repeatable: true,
+        run: |app| {
+            app.undo();
+            Ok(())
+        },
+    },
+    Command {
+        id: "edit.redo",
+        label: "Redo",
+        default_keys: &["Mod+Shift+Z", "Mod+Y"],
+        repeatable: true,
+        run: |app| {
+            app.redo();
+            Ok(())
+        },
+    },
+    Command {
+        id: "tool.circle",
+        label: "Circle Tool",
+        default_keys: &["C"],
+        repeatable: false,
+        run: |app| {
+            app.set_tool(Tool::Circle);
+            Ok(())
+        },
+    },
+    Command {
+        id: "tool.eraser",
+        label: "Eraser Tool",
+        default_keys: &["E"],
+        repeatable: false,
+        run: |app| {
+            app.set_tool(Tool::Eraser);
+            Ok(())
+        },
+    },
+    Command {
+        id: "file.export",
+        label: "Export Drawing",
+        default_keys: &["Mod+S"],
+        repeatable: false,
+        run: |app| app.export_drawing(),
+    },
+    Command {
+        id: "view.zoomIn",
+        label: "Zoom In",
+        default_keys: &["Mod+="],
+        repeatable: true,
+        run: |app| {
+            app.update_viewport(|v| v.zoom_in());
+            Ok(())
+        },
+    },
+    Command {
+        id: "view.zoomOut",
+        label: "Zoom Out",
+        default_keys: &["Mod+-"],
+        repeatable: true,
+        run: |app| {
+            app.update_viewport(|v| v.zoom_out());
+            Ok(())
+        },
+    },
+    Command {
+        id: "view.resetZoom",
+        label: "Reset Zoom",
+        default_keys: &["Mod+0"],
+        repeatable: false,
+        run: |app| {
+            app.update_viewport(|v| v.reset());
+            Ok(())
+        },
+    },
+    Command {
+        id: "app.togglePause",
+        label: "Pause/Resume",
+        default_keys: &["Space"],
+        repeatable: false,
+        run: |app| {
+            app.paused_by_visibility.set(false);
+            if app.running.get() {
+                rendering::pause_render_loop(app);
+                Ok(())
+            } else {
+                rendering::resume_render_loop(app)
+            }
+        },
+    },
+    Command {
+        id: "sim.togglePhysics",
+        label: "Toggle Physics",
+        default_keys: &["P"],
+        repeatable: false,
+        run: |app| {
+            let enabled = app.state.borrow().physics.is_some();
+            app.set_physics_enabled(!enabled);
+            Ok(())
+        },
+    },
+];
+
+pub(crate) fn find_command(id: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.id == id)
+}
+
+/// Runs the command with the given id.
+pub(crate) fn execute_command(app: &DrawingApp, id: &str) -> Result<(), JsValue> {
+    let command =
+        find_command(id).ok_or_else(|| JsValue::from_str(&format!("Unknown command '{}'", id)))?;
+    (command.run)(app)
+}
+
+/// Builds the keymap from each command's `default_keys`. Every command gets an
+/// entry, even without keys, so user rebindings for it are accepted.
+pub(crate) fn default_keymap() -> Keymap {
+    let mut keymap = Keymap::new();
+    for command in COMMANDS {
+        let combos = command
+            .default_keys
+            .iter()
+            .map(|keys| {
+                keys.parse::<KeyCombo>()
+                    .expect("default key combos are valid")
+            })
+            .collect();
+        keymap
+            .set_bindings(command.id, combos)
+            .expect("default key combos do not conflict");
+    }
+    keymap
+}
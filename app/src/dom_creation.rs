@@ -6,11 +6,25 @@ use web_sys::{Document, Element, HtmlButtonElement, HtmlCanvasElement};
 /// The ID of the container element in `index.html` where the app will be mounted.
 const CONTAINER_ID: &str = "main-app-container";
 
+/// The DOM elements created by the app inside its container.
+pub(crate) struct AppDom {
+    pub(crate) toolbar: Element,
+    pub(crate) canvas_container: Element,
+    pub(crate) canvas: HtmlCanvasElement,
+    pub(crate) clear_button: HtmlButtonElement,
+}
+
+impl AppDom {
+    /// Removes every element created by `create_app_dom` from the document.
+    pub(crate) fn remove(&self) {
+        self.toolbar.remove();
+        self.canvas_container.remove();
+    }
+}
+
 /// Creates the application's DOM structure inside the main container.
-/// Returns the created canvas and clear button elements.
-pub(crate) fn create_app_dom(
-    document: &Document,
-) -> Result<(HtmlCanvasElement, HtmlButtonElement), JsValue> {
+/// Returns the created elements so they can be wired up and later removed.
+pub(crate) fn create_app_dom(document: &Document) -> Result<AppDom, JsValue> {
     let container = document.get_element_by_id(CONTAINER_ID).ok_or_else(|| {
         JsValue::from_str(&format!("Container element '{}' not found", CONTAINER_ID))
     })?;
@@ -39,5 +53,10 @@ pub(crate) fn create_app_dom(
     container.append_child(&toolbar)?;
     container.append_child(&canvas_container)?;
 
-    Ok((canvas, clear_button))
+    Ok(AppDom {
+        toolbar,
+        canvas_container,
+        canvas,
+        clear_button,
+    })
 }
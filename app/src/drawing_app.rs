@@ -1,9 +1,16 @@
 // app/src/drawing_app.rs
-use base::DrawingState;
-use std::cell::RefCell;
+use crate::{keymap_storage, utils::RenderLoopClosure};
+use base::{Animation, DrawingState, FixedTimestep, History, Keymap, Platform, Tool, Viewport};
+use std::cell::{Cell, RefCell};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{console, CanvasRenderingContext2d, HtmlCanvasElement};
+use web_sys::{
+    console, Blob, BlobPropertyBag, CanvasRenderingContext2d, HtmlAnchorElement, HtmlCanvasElement,
+    Url,
+};
+
+/// How long the optional entrance animation for new circles lasts, in seconds.
+const ENTRANCE_ANIMATION_SECONDS: f64 = 0.3;
 
 /// The internal struct containing the application's state and core logic.
 /// It is not exposed directly to wasm-bindgen.
@@ -11,8 +18,29 @@ pub struct DrawingApp {
     pub(crate) state: RefCell<DrawingState>,
     pub(crate) canvas: HtmlCanvasElement,
     pub(crate) context: RefCell<Option<CanvasRenderingContext2d>>,
-    // We can store the animation frame ID to be able to cancel it if needed.
-    pub(crate) _animation_frame_id: RefCell<Option<i32>>,
+    // Converts requestAnimationFrame timestamps into fixed simulation steps.
+    pub(crate) timestep: RefCell<FixedTimestep>,
+    // The render loop closure, created by `rendering::start_render_loop`.
+    pub(crate) render_loop: RefCell<Option<RenderLoopClosure>>,
+    // The pending animation frame ID, kept so the render loop can be cancelled.
+    pub(crate) animation_frame_id: RefCell<Option<i32>>,
+    // Whether the render loop is currently scheduling frames.
+    pub(crate) running: Cell<bool>,
+    // Set when the loop was paused because the page became hidden, so it is
+    // only resumed automatically if the user did not pause it explicitly.
+    pub(crate) paused_by_visibility: Cell<bool>,
+    // Whether newly added circles scale in instead of appearing at full size.
+    pub(crate) entrance_animation: Cell<bool>,
+    // Undo/redo snapshots of the shapes.
+    pub(crate) history: RefCell<History>,
+    // The zoom applied when rendering and when mapping clicks to the drawing.
+    pub(crate) viewport: Cell<Viewport>,
+    // What a click on the canvas does.
+    pub(crate) tool: Cell<Tool>,
+    // Keyboard shortcuts, with the user's persisted rebindings applied.
+    pub(crate) keymap: RefCell<Keymap>,
+    // Decides whether `Mod` in the keymap means Cmd or Ctrl.
+    pub(crate) platform: Platform,
 }
 
 /// Implementation of the core application logic. These methods are not
@@ -24,7 +52,17 @@ impl DrawingApp {
             state: RefCell::new(DrawingState::new()),
             canvas,
             context: RefCell::new(None),
-            _animation_frame_id: RefCell::new(None),
+            timestep: RefCell::new(FixedTimestep::default()),
+            render_loop: RefCell::new(None),
+            animation_frame_id: RefCell::new(None),
+            running: Cell::new(false),
+            paused_by_visibility: Cell::new(false),
+            entrance_animation: Cell::new(false),
+            history: RefCell::new(History::default()),
+            viewport: Cell::new(Viewport::default()),
+            tool: Cell::new(Tool::default()),
+            keymap: RefCell::new(keymap_storage::load_keymap()),
+            platform: keymap_storage::detect_platform(),
         }
     }
 
@@ -52,6 +90,23 @@ impl DrawingApp {
         }
     }
 
+    /// Runs as many fixed-size simulation steps as the elapsed time since the
+    /// previous frame allows. `timestamp` is the requestAnimationFrame time in ms.
+    pub fn update(&self, timestamp: f64) {
+        let mut timestep = self.timestep.borrow_mut();
+        let steps = timestep.tick(timestamp);
+        let dt = timestep.step();
+        let mut state = self.state.borrow_mut();
+        let (width, height) = self
+            .viewport
+            .get()
+            .to_world(self.canvas.width() as f64, self.canvas.height() as f64);
+        state.set_physics_bounds(width, height);
+        for _ in 0..steps {
+            state.update(dt);
+        }
+    }
+
     pub fn render_frame(&self) -> Result<(), JsValue> {
         let context_opt = self.context.borrow();
         let context = context_opt
@@ -59,6 +114,7 @@ impl DrawingApp {
             .ok_or_else(|| JsValue::from_str("Rendering context not initialized"))?;
 
         // Clear the canvas
+        context.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)?;
         context.clear_rect(
             0.0,
             0.0,
@@ -66,6 +122,10 @@ impl DrawingApp {
             self.canvas.height() as f64,
         );
 
+        // Shapes are in drawing coordinates; scale them to the current zoom.
+        let zoom = self.viewport.get().zoom();
+        context.set_transform(zoom, 0.0, 0.0, zoom, 0.0, 0.0)?;
+
         // Draw all shapes from the base state
         let state = self.state.borrow();
         for shape in &state.shapes {
@@ -77,25 +137,120 @@ impl DrawingApp {
                 0.0,
                 2.0 * std::f64::consts::PI,
             )?;
+            context.set_global_alpha(shape.opacity);
             context.set_fill_style_str(&shape.color);
             context.fill();
         }
+        context.set_global_alpha(1.0);
 
         Ok(())
     }
 
+    /// Applies the current tool at a point given in canvas pixels.
+    pub fn click_at(&self, canvas_x: f64, canvas_y: f64) {
+        let (x, y) = self.viewport.get().to_world(canvas_x, canvas_y);
+        match self.tool.get() {
+            Tool::Circle => self.add_circle_at_point(x, y),
+            Tool::Eraser => self.erase_at_point(x, y),
+        }
+    }
+
     pub fn add_circle_at_point(&self, x: f64, y: f64) {
         console::log_1(&format!("Adding circle at ({}, {})", x, y).into());
-        self.state.borrow_mut().add_shape(x, y);
+        let mut state = self.state.borrow_mut();
+        self.history.borrow_mut().record(&state);
+        state.add_shape(x, y);
+        if self.entrance_animation.get() {
+            let index = state.shapes.len() - 1;
+            let radius = state.shapes[index].radius;
+            state.animate(Animation::scale_in(
+                index,
+                radius,
+                ENTRANCE_ANIMATION_SECONDS,
+            ));
+        }
+    }
+
+    pub fn set_physics_enabled(&self, enabled: bool) {
+        console::log_1(
+            &format!("Physics {}.", if enabled { "enabled" } else { "disabled" }).into(),
+        );
+        self.state.borrow_mut().set_physics_enabled(enabled);
+    }
+
+    /// Removes the topmost shape under the point, if there is one.
+    pub fn erase_at_point(&self, x: f64, y: f64) {
+        let mut state = self.state.borrow_mut();
+        if let Some(index) = state.shape_at(x, y) {
+            console::log_1(&format!("Erasing shape {} at ({}, {})", index, x, y).into());
+            self.history.borrow_mut().record(&state);
+            state.remove_shape(index);
+        }
     }
 
     pub fn clear_canvas(&self) {
         console::log_1(&"Clearing all shapes.".into());
-        self.state.borrow_mut().clear_shapes();
+        let mut state = self.state.borrow_mut();
+        if !state.shapes.is_empty() {
+            self.history.borrow_mut().record(&state);
+        }
+        state.clear_shapes();
+    }
+
+    pub fn undo(&self) -> bool {
+        self.history.borrow_mut().undo(&mut self.state.borrow_mut())
+    }
+
+    pub fn redo(&self) -> bool {
+        self.history.borrow_mut().redo(&mut self.state.borrow_mut())
+    }
+
+    pub fn set_tool(&self, tool: Tool) {
+        console::log_1(&format!("Switching to the {} tool.", tool).into());
+        self.tool.set(tool);
+    }
+
+    /// Applies `change` to the viewport.
+    pub fn update_viewport(&self, change: impl FnOnce(&mut Viewport)) {
+        let mut viewport = self.viewport.get();
+        change(&mut viewport);
+        self.viewport.set(viewport);
+    }
+
+    /// Downloads the current drawing as a JSON file.
+    pub fn export_drawing(&self) -> Result<(), JsValue> {
+        let json = js_sys::JSON::stringify(&self.get_drawing_state()?)?;
+        let parts = js_sys::Array::of1(&json);
+        let options = BlobPropertyBag::new();
+        options.set_type("application/json");
+        let blob = Blob::new_with_str_sequence_and_options(&parts, &options)?;
+        let url = Url::create_object_url_with_blob(&blob)?;
+
+        let document = self
+            .canvas
+            .owner_document()
+            .ok_or_else(|| JsValue::from_str("Canvas is not attached to a document"))?;
+        let link = document
+            .create_element("a")?
+            .dyn_into::<HtmlAnchorElement>()?;
+        link.set_href(&url);
+        link.set_download("drawing.json");
+        link.click();
+        Url::revoke_object_url(&url)
     }
 
     pub fn get_drawing_state(&self) -> Result<JsValue, JsValue> {
         serde_wasm_bindgen::to_value(&*self.state.borrow())
             .map_err(|e| JsValue::from_str(&e.to_string()))
     }
+
+    /// Replaces the shapes with a state produced by `get_drawing_state`. Fails
+    /// without changing anything if the value does not match the current schema.
+    pub fn load_drawing_state(&self, value: JsValue) -> Result<(), JsValue> {
+        let loaded: DrawingState = serde_wasm_bindgen::from_value(value)
+            .map_err(|e| JsValue::from_str(&format!("Invalid drawing state: {}", e)))?;
+        self.state.borrow_mut().replace_shapes(loaded.shapes);
+        self.history.borrow_mut().clear();
+        Ok(())
+    }
 }
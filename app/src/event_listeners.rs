@@ -1,41 +1,154 @@
 // app/src/event_listeners.rs
-use crate::drawing_app::DrawingApp;
+use crate::{commands, dom_creation::AppDom, drawing_app::DrawingApp, rendering};
+use base::KeyPress;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{HtmlButtonElement, HtmlCanvasElement, MouseEvent};
+use web_sys::{console, Document, Event, EventTarget, HtmlElement, KeyboardEvent, MouseEvent};
 
-// Type alias for the tuple of event listener closures to improve readability.
-type EventListenerClosures = (Closure<dyn FnMut(MouseEvent)>, Closure<dyn FnMut()>);
+/// A DOM event listener registration that owns its closure.
+/// Dropping it removes the listener from its target before the closure is freed,
+/// so the browser never calls into a dropped closure.
+pub(crate) struct EventListener {
+    target: EventTarget,
+    event_type: &'static str,
+    callback: Closure<dyn FnMut(Event)>,
+}
+
+impl EventListener {
+    /// Registers `callback` for `event_type` on `target`.
+    pub(crate) fn new<F>(
+        target: &EventTarget,
+        event_type: &'static str,
+        callback: F,
+    ) -> Result<Self, JsValue>
+    where
+        F: FnMut(Event) + 'static,
+    {
+        let callback = Closure::wrap(Box::new(callback) as Box<dyn FnMut(Event)>);
+        target.add_event_listener_with_callback(event_type, callback.as_ref().unchecked_ref())?;
+        Ok(Self {
+            target: target.clone(),
+            event_type,
+            callback,
+        })
+    }
+}
+
+impl Drop for EventListener {
+    fn drop(&mut self) {
+        // Removing a listener only fails for invalid arguments, which cannot happen here.
+        let _ = self.target.remove_event_listener_with_callback(
+            self.event_type,
+            self.callback.as_ref().unchecked_ref(),
+        );
+    }
+}
 
-/// Sets up the mousedown and click event listeners for the canvas and clear button.
-/// Returns the closures to be stored in the AppHandle, ensuring they are not dropped.
+/// Sets up the mousedown and click event listeners for the canvas and clear button,
+/// the keydown listener for keyboard shortcuts, and the visibilitychange listener
+/// that pauses the render loop while the page is hidden.
+/// Returns the listeners to be stored in the AppHandle, ensuring they are not dropped.
 pub(crate) fn setup_event_listeners(
     app: Rc<DrawingApp>,
-    canvas: &HtmlCanvasElement,
-    clear_button: &HtmlButtonElement,
-) -> Result<EventListenerClosures, JsValue> {
+    document: &Document,
+    dom: &AppDom,
+) -> Result<Vec<EventListener>, JsValue> {
     // --- Mouse Down Listener ---
     let mouse_app_clone = app.clone();
-    let on_mouse_down = Closure::wrap(Box::new(move |event: MouseEvent| {
+    let on_mouse_down = EventListener::new(&dom.canvas, "mousedown", move |event: Event| {
+        let event = event.unchecked_into::<MouseEvent>();
         let rect = mouse_app_clone.canvas.get_bounding_client_rect();
         // Scale mouse coordinates from CSS pixels to canvas buffer pixels.
         let scale_x = mouse_app_clone.canvas.width() as f64 / rect.width();
         let scale_y = mouse_app_clone.canvas.height() as f64 / rect.height();
         let x = (event.client_x() as f64 - rect.left()) * scale_x;
         let y = (event.client_y() as f64 - rect.top()) * scale_y;
-        mouse_app_clone.add_circle_at_point(x, y);
-    }) as Box<dyn FnMut(_)>);
-
-    canvas.add_event_listener_with_callback("mousedown", on_mouse_down.as_ref().unchecked_ref())?;
+        mouse_app_clone.click_at(x, y);
+    })?;
 
     // --- Clear Button Listener ---
-    let clear_app_clone = app;
-    let on_clear = Closure::wrap(Box::new(move || {
-        clear_app_clone.clear_canvas();
-    }) as Box<dyn FnMut()>);
+    let clear_app_clone = app.clone();
+    let on_clear = EventListener::new(&dom.clear_button, "click", move |_event: Event| {
+        if let Err(e) = commands::execute_command(&clear_app_clone, "canvas.clear") {
+            console::error_1(&e);
+        }
+    })?;
+
+    // --- Keyboard Shortcut Listener ---
+    let key_app_clone = app.clone();
+    let on_key_down = EventListener::new(document, "keydown", move |event: Event| {
+        let event = event.unchecked_into::<KeyboardEvent>();
+        if is_editable_target(&event) {
+            return;
+        }
+        let press = KeyPress {
+            key: event.key(),
+            ctrl: event.ctrl_key(),
+            alt: event.alt_key(),
+            shift: event.shift_key(),
+            meta: event.meta_key(),
+        };
+        let command = key_app_clone
+            .keymap
+            .borrow()
+            .command_for(&press, key_app_clone.platform)
+            .and_then(commands::find_command);
+        let Some(command) = command else {
+            return;
+        };
+        // The shortcut is ours, so keep the browser from acting on it too (e.g. Ctrl+S).
+        event.prevent_default();
+        if event.repeat() && !command.repeatable {
+            return;
+        }
+        if let Err(e) = commands::execute_command(&key_app_clone, command.id) {
+            console::error_1(&e);
+        }
+    })?;
 
-    clear_button.add_event_listener_with_callback("click", on_clear.as_ref().unchecked_ref())?;
+    // --- Visibility Listener ---
+    let visibility_app_clone = app;
+    let visibility_document = document.clone();
+    let on_visibility_change =
+        EventListener::new(document, "visibilitychange", move |_event: Event| {
+            let app = &visibility_app_clone;
+            if visibility_document.hidden() {
+                if app.running.get() {
+                    rendering::pause_render_loop(app);
+                    app.paused_by_visibility.set(true);
+                }
+            } else if app.paused_by_visibility.replace(false) {
+                if let Err(e) = rendering::resume_render_loop(app) {
+                    console::error_1(&e);
+                }
+            }
+        })?;
+
+    Ok(vec![
+        on_mouse_down,
+        on_clear,
+        on_key_down,
+        on_visibility_change,
+    ])
+}
 
-    Ok((on_mouse_down, on_clear))
+/// Whether the key event is aimed at a control that handles it itself: a text
+/// field or select, where keys must type normally, or a button, which Space
+/// and Enter press.
+fn is_editable_target(event: &KeyboardEvent) -> bool {
+    let Some(element) = event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlElement>().ok())
+    else {
+        return false;
+    };
+    if element.is_content_editable() {
+        return true;
+    }
+    match element.tag_name().as_str() {
+        "INPUT" | "TEXTAREA" | "SELECT" => true,
+        "BUTTON" => matches!(event.key().as_str(), " " | "Enter"),
+        _ => false,
+    }
 }
@@ -0,0 +1,62 @@
+// app/src/keymap_storage.rs
+use crate::commands::default_keymap;
+use base::{Keymap, Platform};
+use wasm_bindgen::prelude::*;
+use web_sys::{console, Storage};
+
+/// The localStorage key holding the user's rebindings.
+const KEYMAP_STORAGE_KEY: &str = "app.keymap";
+
+fn local_storage() -> Option<Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+/// Detects whether `Mod` shortcuts should use Cmd (Apple platforms) or Ctrl.
+pub(crate) fn detect_platform() -> Platform {
+    let navigator = web_sys::window()
+        .expect("no global `window` exists")
+        .navigator();
+    let platform = navigator
+        .platform()
+        .or_else(|_| navigator.user_agent())
+        .unwrap_or_default();
+    Platform::detect(&platform)
+}
+
+/// Returns the default keymap with the user's saved rebindings applied.
+/// Saved bindings that no longer parse or now conflict are skipped with a warning.
+pub(crate) fn load_keymap() -> Keymap {
+    let mut keymap = default_keymap();
+    let Some(saved) = local_storage().and_then(|s| s.get_item(KEYMAP_STORAGE_KEY).ok().flatten())
+    else {
+        return keymap;
+    };
+    match serde_json::from_str::<Keymap>(&saved) {
+        Ok(overrides) => {
+            for error in keymap.apply_overrides(&overrides) {
+                console::warn_1(&format!("Ignoring saved key binding: {}", error).into());
+            }
+        }
+        Err(e) => console::warn_1(&format!("Ignoring saved keymap: {}", e).into()),
+    }
+    keymap
+}
+
+/// Saves the bindings that differ from the defaults, so commands added later
+/// still get their default keys.
+pub(crate) fn save_keymap(keymap: &Keymap) -> Result<(), JsValue> {
+    let Some(storage) = local_storage() else {
+        return Err(JsValue::from_str("localStorage is not available"));
+    };
+    let overrides = keymap.overrides(&default_keymap());
+    let json = serde_json::to_string(&overrides).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    storage.set_item(KEYMAP_STORAGE_KEY, &json)
+}
+
+/// Forgets all saved rebindings.
+pub(crate) fn clear_saved_keymap() -> Result<(), JsValue> {
+    match local_storage() {
+        Some(storage) => storage.remove_item(KEYMAP_STORAGE_KEY),
+        None => Ok(()),
+    }
+}
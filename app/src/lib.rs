@@ -2,9 +2,11 @@
 
 // Module declarations
 pub mod app_handle;
+mod commands;
 mod dom_creation;
 mod drawing_app;
 mod event_listeners;
+mod keymap_storage;
 pub mod mount;
 mod rendering;
 mod utils;
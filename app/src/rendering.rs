@@ -1,38 +1,76 @@
 // app/src/rendering.rs
 use crate::{
     drawing_app::DrawingApp,
-    utils::{request_animation_frame, RenderLoopHandle},
+    utils::{cancel_animation_frame, request_animation_frame},
 };
-use std::cell::RefCell;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use web_sys::console;
 
-/// Creates the render loop closure and kicks off the first animation frame.
-/// Returns the handle to the closure so it can be stored and kept alive.
-pub(crate) fn start_render_loop(app: Rc<DrawingApp>) -> Result<RenderLoopHandle, JsValue> {
-    // This is a common pattern for creating a recursive closure for requestAnimationFrame.
-    // 1. Create a `Rc<RefCell<Option<Closure>>>` that will hold the closure.
-    let f: RenderLoopHandle = Rc::new(RefCell::new(None));
-    let g = f.clone();
-
-    // 2. Create the closure, which captures the `App` state and the `Rc` of itself.
-    let app_clone = app;
-    *g.borrow_mut() = Some(Closure::wrap(Box::new(move |_timestamp: f64| {
+/// Creates the render loop closure, stores it on the app and kicks off the
+/// first animation frame. Any loop that is already running is stopped first.
+pub(crate) fn start_render_loop(app: Rc<DrawingApp>) -> Result<(), JsValue> {
+    stop_render_loop(&app);
+
+    // The closure is stored on the app it captures, which forms a reference
+    // cycle. `stop_render_loop` breaks it by dropping the closure.
+    let app_clone = app.clone();
+    let closure = Closure::wrap(Box::new(move |timestamp: f64| {
+        // Each frame matches the canvas to its layout, runs the fixed-timestep
+        // update phase against the new size, then renders.
         app_clone.resize_canvas();
+        app_clone.update(timestamp);
         if let Err(e) = app_clone.render_frame() {
             console::error_1(&e);
         }
 
-        // Schedule the next frame. The closure is re-used.
-        // The unwrap is safe because `f` is never set to `None` after this block.
-        let handle = request_animation_frame(f.borrow().as_ref().unwrap()).unwrap();
-        *app_clone._animation_frame_id.borrow_mut() = Some(handle);
-    }) as Box<dyn FnMut(f64)>));
+        // Schedule the next frame unless the loop was paused in the meantime.
+        if app_clone.running.get() {
+            if let Err(e) = schedule_next_frame(&app_clone) {
+                console::error_1(&e);
+            }
+        }
+    }) as Box<dyn FnMut(f64)>);
+
+    *app.render_loop.borrow_mut() = Some(closure);
+    resume_render_loop(&app)
+}
 
-    // 3. Kick off the first frame.
-    request_animation_frame(g.borrow().as_ref().unwrap())?;
+/// Stops scheduling frames while keeping the render loop closure alive.
+pub(crate) fn pause_render_loop(app: &DrawingApp) {
+    if !app.running.replace(false) {
+        return;
+    }
+    if let Some(handle) = app.animation_frame_id.borrow_mut().take() {
+        if let Err(e) = cancel_animation_frame(handle) {
+            console::error_1(&e);
+        }
+    }
+}
+
+/// Resumes a paused render loop. The timestep is reset so the time spent
+/// paused is not simulated. Does nothing if the loop was never started.
+pub(crate) fn resume_render_loop(app: &DrawingApp) -> Result<(), JsValue> {
+    if app.running.get() || app.render_loop.borrow().is_none() {
+        return Ok(());
+    }
+    app.timestep.borrow_mut().reset();
+    schedule_next_frame(app)?;
+    app.running.set(true);
+    Ok(())
+}
+
+/// Cancels the pending animation frame and drops the render loop closure.
+/// Dropping the closure releases the `Rc` it holds to the app, breaking the cycle.
+pub(crate) fn stop_render_loop(app: &DrawingApp) {
+    pause_render_loop(app);
+    app.render_loop.borrow_mut().take();
+}
 
-    // 4. Return the handle to the AppHandle to keep it and the closure alive.
-    Ok(g)
+fn schedule_next_frame(app: &DrawingApp) -> Result<(), JsValue> {
+    if let Some(closure) = app.render_loop.borrow().as_ref() {
+        let handle = request_animation_frame(closure)?;
+        *app.animation_frame_id.borrow_mut() = Some(handle);
+    }
+    Ok(())
 }
@@ -1,8 +1,10 @@
 #![cfg(target_arch = "wasm32")]
 
 use app::{mount_app, AppHandle};
-use base::DrawingState;
+use base::{DrawingState, Platform};
+use wasm_bindgen::JsCast;
 use wasm_bindgen_test::*;
+use web_sys::{HtmlCanvasElement, KeyboardEvent, KeyboardEventInit, MouseEvent};
 
 // Configure wasm-bindgen-test to run in a browser environment.
 wasm_bindgen_test_configure!(run_in_browser);
@@ -20,6 +22,10 @@ fn setup_dom_and_app() -> AppHandle {
     if let Some(el) = document.get_element_by_id(CONTAINER_ID) {
         el.remove();
     }
+    // Start every test with the default key bindings.
+    if let Some(storage) = window.local_storage().unwrap() {
+        storage.remove_item("app.keymap").unwrap();
+    }
 
     let container = document.create_element("div").unwrap();
     container.set_id(CONTAINER_ID);
@@ -30,6 +36,72 @@ fn setup_dom_and_app() -> AppHandle {
     mount_app().expect("App mounting should succeed")
 }
 
+// Helper to resolve once the browser has run its next animation frame.
+async fn next_animation_frame() {
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        web_sys::window()
+            .unwrap()
+            .request_animation_frame(&resolve)
+            .unwrap();
+    });
+    wasm_bindgen_futures::JsFuture::from(promise).await.unwrap();
+}
+
+// Helper to fetch the app's canvas from the document.
+fn get_canvas() -> HtmlCanvasElement {
+    web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .get_element_by_id("drawing-canvas")
+        .expect("Canvas should be in the DOM")
+        .dyn_into::<HtmlCanvasElement>()
+        .unwrap()
+}
+
+// Helper to dispatch a keydown event on the document.
+// Presses `key` on the document, holding `Mod` (Cmd on macOS, Ctrl elsewhere)
+// if `with_mod` is set.
+fn press_key(key: &str, with_mod: bool) {
+    let platform = web_sys::window().unwrap().navigator().platform().unwrap();
+    let init = KeyboardEventInit::new();
+    init.set_key(key);
+    match Platform::detect(&platform) {
+        Platform::Mac => init.set_meta_key(with_mod),
+        Platform::Other => init.set_ctrl_key(with_mod),
+    }
+    init.set_cancelable(true);
+    let event = KeyboardEvent::new_with_keyboard_event_init_dict("keydown", &init).unwrap();
+    web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .dispatch_event(&event)
+        .unwrap();
+}
+
+#[derive(serde::Deserialize)]
+struct CommandInfo {
+    id: String,
+    bindings: Vec<String>,
+}
+
+fn get_bindings(app: &AppHandle, id: &str) -> Vec<String> {
+    let commands: Vec<CommandInfo> =
+        serde_wasm_bindgen::from_value(app.get_commands().unwrap()).unwrap();
+    commands
+        .into_iter()
+        .find(|c| c.id == id)
+        .expect("Command should be listed")
+        .bindings
+}
+
+fn get_shape_count(app: &AppHandle) -> usize {
+    let state: DrawingState =
+        serde_wasm_bindgen::from_value(app.get_drawing_state().unwrap()).unwrap();
+    state.shapes.len()
+}
+
 #[wasm_bindgen_test]
 fn test_app_creation_and_initial_state() {
     let app = setup_dom_and_app();
@@ -93,3 +165,306 @@ fn test_clear_canvas_modifies_state() {
         "Shape count should be 0 after clearing canvas"
     );
 }
+
+#[wasm_bindgen_test]
+fn test_destroy_removes_generated_dom() {
+    let app = setup_dom_and_app();
+    let document = web_sys::window().unwrap().document().unwrap();
+    assert!(document.get_element_by_id("drawing-canvas").is_some());
+    assert!(document.get_element_by_id("clear-btn").is_some());
+
+    app.destroy();
+
+    assert!(document.get_element_by_id("drawing-canvas").is_none());
+    assert!(document.get_element_by_id("clear-btn").is_none());
+    let container = document.get_element_by_id("main-app-container").unwrap();
+    assert_eq!(container.child_element_count(), 0);
+
+    // A second call must be a harmless no-op.
+    app.destroy();
+    assert!(app.start().is_err(), "A destroyed app should not restart");
+}
+
+#[wasm_bindgen_test]
+fn test_destroy_removes_event_listeners() {
+    let app = setup_dom_and_app();
+    let canvas = get_canvas();
+    let mouse_down = || MouseEvent::new("mousedown").unwrap();
+
+    canvas.dispatch_event(&mouse_down()).unwrap();
+    assert_eq!(get_shape_count(&app), 1, "The listener should be active");
+
+    app.destroy();
+
+    // The element reference outlives the DOM removal; dispatching on it must
+    // no longer reach the app.
+    canvas.dispatch_event(&mouse_down()).unwrap();
+    assert_eq!(get_shape_count(&app), 1, "The listener should be removed");
+}
+
+#[wasm_bindgen_test]
+async fn test_destroy_stops_render_loop() {
+    let app = setup_dom_and_app();
+    let canvas = get_canvas();
+    canvas.style().set_property("width", "123px").unwrap();
+
+    app.initialize_renderer().unwrap();
+    app.start().unwrap();
+    next_animation_frame().await;
+    next_animation_frame().await;
+    // The loop resizes the drawing buffer to the CSS size every frame.
+    assert_eq!(canvas.width(), 123, "The render loop should be running");
+
+    app.destroy();
+    canvas.style().set_property("width", "77px").unwrap();
+    next_animation_frame().await;
+    next_animation_frame().await;
+    assert_eq!(canvas.width(), 123, "No frame should run after destroy");
+}
+
+#[wasm_bindgen_test]
+async fn test_repeated_mount_destroy_cycles_do_not_leak() {
+    let mut canvases = Vec::new();
+    for _ in 0..5 {
+        let app = setup_dom_and_app();
+        let canvas = get_canvas();
+        app.initialize_renderer().unwrap();
+        app.start().unwrap();
+        next_animation_frame().await;
+        let width_before_destroy = canvas.width();
+        app.destroy();
+        canvases.push((app, canvas, width_before_destroy));
+    }
+
+    let document = web_sys::window().unwrap().document().unwrap();
+    let container = document.get_element_by_id("main-app-container").unwrap();
+    assert_eq!(container.child_element_count(), 0);
+
+    // Detached canvases report a zero-sized layout, so a leaked render loop
+    // would shrink their drawing buffers to zero.
+    next_animation_frame().await;
+    next_animation_frame().await;
+    for (app, canvas, width_before_destroy) in &canvases {
+        assert_eq!(canvas.width(), *width_before_destroy);
+        canvas
+            .dispatch_event(&MouseEvent::new("mousedown").unwrap())
+            .unwrap();
+        assert_eq!(get_shape_count(app), 0);
+    }
+}
+
+#[wasm_bindgen_test]
+async fn test_pause_and_resume_render_loop() {
+    let app = setup_dom_and_app();
+    let canvas = get_canvas();
+    assert!(!app.is_running(), "The loop should not run before start()");
+
+    app.initialize_renderer().unwrap();
+    app.start().unwrap();
+    assert!(app.is_running());
+
+    app.pause();
+    assert!(!app.is_running());
+    canvas.style().set_property("width", "64px").unwrap();
+    next_animation_frame().await;
+    next_animation_frame().await;
+    assert_ne!(canvas.width(), 64, "No frame should run while paused");
+
+    app.resume().unwrap();
+    assert!(app.is_running());
+    next_animation_frame().await;
+    next_animation_frame().await;
+    assert_eq!(canvas.width(), 64, "Frames should run again after resume");
+
+    app.destroy();
+    assert!(!app.is_running());
+    app.resume().unwrap();
+    assert!(!app.is_running(), "A destroyed app should not resume");
+}
+
+#[wasm_bindgen_test]
+fn test_entrance_animation_starts_new_circles_small() {
+    let app = setup_dom_and_app();
+    app.set_entrance_animation(true);
+    app.add_circle_at_point(40.0, 40.0);
+
+    let state: DrawingState =
+        serde_wasm_bindgen::from_value(app.get_drawing_state().unwrap()).unwrap();
+    assert_eq!(state.shapes.len(), 1);
+    assert_eq!(
+        state.shapes[0].radius, 0.0,
+        "The circle should scale in from zero"
+    );
+    assert_eq!(state.shapes[0].opacity, 0.0);
+}
+
+#[wasm_bindgen_test]
+fn test_undo_and_redo_commands() {
+    let app = setup_dom_and_app();
+    app.add_circle_at_point(10.0, 10.0);
+    app.add_circle_at_point(20.0, 20.0);
+    app.clear_canvas();
+
+    app.execute_command("edit.undo").unwrap();
+    assert_eq!(
+        get_shape_count(&app),
+        2,
+        "Undo should restore the cleared shapes"
+    );
+    app.execute_command("edit.undo").unwrap();
+    assert_eq!(get_shape_count(&app), 1);
+    app.execute_command("edit.redo").unwrap();
+    assert_eq!(get_shape_count(&app), 2);
+
+    assert!(app.execute_command("no.such.command").is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_eraser_tool_removes_clicked_shape() {
+    let app = setup_dom_and_app();
+    let canvas = get_canvas();
+    canvas.style().set_property("width", "300px").unwrap();
+    canvas.style().set_property("height", "150px").unwrap();
+    app.add_circle_at_point(0.0, 0.0);
+
+    app.execute_command("tool.eraser").unwrap();
+    assert_eq!(app.get_tool(), "eraser");
+    // Click the canvas origin, where the circle is centred.
+    let rect = canvas.get_bounding_client_rect();
+    let init = web_sys::MouseEventInit::new();
+    init.set_client_x(rect.left() as i32);
+    init.set_client_y(rect.top() as i32);
+    let event = MouseEvent::new_with_mouse_event_init_dict("mousedown", &init).unwrap();
+    canvas.dispatch_event(&event).unwrap();
+    assert_eq!(
+        get_shape_count(&app),
+        0,
+        "The eraser should remove the shape"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_keyboard_shortcut_runs_command() {
+    let app = setup_dom_and_app();
+    assert!(!app.is_physics_enabled());
+    press_key("p", false);
+    assert!(app.is_physics_enabled(), "'P' toggles physics by default");
+
+    app.add_circle_at_point(10.0, 10.0);
+    press_key("z", true);
+    assert_eq!(get_shape_count(&app), 0, "Mod+Z should undo");
+}
+
+#[wasm_bindgen_test]
+fn test_space_on_a_focused_control_does_not_toggle_pause() {
+    let app = setup_dom_and_app();
+    app.initialize_renderer().unwrap();
+    app.start().unwrap();
+    assert!(app.is_running());
+    let document = web_sys::window().unwrap().document().unwrap();
+    let button = document.get_element_by_id("clear-btn").unwrap();
+    let select = document.create_element("select").unwrap();
+    button.parent_node().unwrap().append_child(&select).unwrap();
+
+    let key_down = |key: &str| {
+        let init = KeyboardEventInit::new();
+        init.set_key(key);
+        init.set_bubbles(true);
+        init.set_cancelable(true);
+        KeyboardEvent::new_with_keyboard_event_init_dict("keydown", &init).unwrap()
+    };
+    for target in [&button, &select] {
+        target.dispatch_event(&key_down(" ")).unwrap();
+        assert!(
+            app.is_running(),
+            "Space on a <{}> should not pause",
+            target.tag_name()
+        );
+    }
+
+    // Other shortcuts still work while the button has focus.
+    button.dispatch_event(&key_down("p")).unwrap();
+    assert!(app.is_physics_enabled());
+
+    // Anywhere else, Space pauses.
+    get_canvas().dispatch_event(&key_down(" ")).unwrap();
+    assert!(!app.is_running(), "Space on the canvas should pause");
+    app.destroy();
+}
+
+#[wasm_bindgen_test]
+fn test_rebinding_detects_conflicts_and_persists() {
+    let app = setup_dom_and_app();
+    assert_eq!(get_bindings(&app, "tool.eraser"), ["E"]);
+
+    let conflict = app.rebind_command("tool.eraser", vec!["P".to_string()]);
+    assert!(conflict.is_err(), "'P' is already bound to physics");
+    assert!(app
+        .rebind_command("tool.eraser", vec!["Hyper+E".to_string()])
+        .is_err());
+    assert_eq!(
+        get_bindings(&app, "tool.eraser"),
+        ["E"],
+        "Failed rebinds change nothing"
+    );
+
+    app.rebind_command("tool.eraser", vec!["X".to_string()])
+        .unwrap();
+    press_key("x", false);
+    assert_eq!(app.get_tool(), "eraser");
+    app.destroy();
+
+    // A freshly mounted app picks up the saved binding.
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let container = document.get_element_by_id("main-app-container").unwrap();
+    assert_eq!(container.child_element_count(), 0);
+    let remounted = mount_app().unwrap();
+    assert_eq!(get_bindings(&remounted, "tool.eraser"), ["X"]);
+
+    remounted.reset_keymap().unwrap();
+    assert_eq!(get_bindings(&remounted, "tool.eraser"), ["E"]);
+    let saved = window
+        .local_storage()
+        .unwrap()
+        .unwrap()
+        .get_item("app.keymap")
+        .unwrap();
+    assert_eq!(saved, None);
+}
+
+#[wasm_bindgen_test]
+fn test_drawing_state_survives_remount() {
+    let app = setup_dom_and_app();
+    app.add_circle_at_point(10.0, 10.0);
+    app.add_circle_at_point(20.0, 20.0);
+    let saved = app.get_drawing_state().unwrap();
+    app.destroy();
+
+    let remounted = setup_dom_and_app();
+    remounted.load_drawing_state(saved.clone()).unwrap();
+    let expected: DrawingState = serde_wasm_bindgen::from_value(saved).unwrap();
+    let restored: DrawingState =
+        serde_wasm_bindgen::from_value(remounted.get_drawing_state().unwrap()).unwrap();
+    assert_eq!(restored, expected);
+    remounted.execute_command("edit.undo").unwrap();
+    assert_eq!(
+        get_shape_count(&remounted),
+        2,
+        "Loading a state should not be undoable"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_load_drawing_state_rejects_unknown_schema() {
+    let app = setup_dom_and_app();
+    app.add_circle_at_point(10.0, 10.0);
+
+    let incompatible = js_sys::JSON::parse(r#"{"shapes":[{"x":1}]}"#).unwrap();
+    assert!(app.load_drawing_state(incompatible).is_err());
+    assert_eq!(
+        get_shape_count(&app),
+        1,
+        "A failed load should leave the drawing untouched"
+    );
+}
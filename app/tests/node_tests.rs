@@ -0,0 +1,79 @@
+#![cfg(target_arch = "wasm32")]
+//! App tests that need no DOM. Without `run_in_browser` they run in Node,
+//! e.g. with `cli test --node`.
+
+use base::state::{DrawingState, Shape};
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::*;
+
+fn shape(x: f64, y: f64, opacity: f64) -> Shape {
+    Shape {
+        x,
+        y,
+        radius: 12.5,
+        color: "rgb(10, 20, 30)".to_string(),
+        opacity,
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_drawing_state_round_trips_through_js_values() {
+    let mut state = DrawingState::new();
+    state.replace_shapes(vec![shape(1.5, -2.25, 0.5), shape(1e6, 0.1, 1.0)]);
+
+    // The shape `getDrawingState` hands to JavaScript.
+    let value = serde_wasm_bindgen::to_value(&state).unwrap();
+    let shapes = Reflect::get(&value, &"shapes".into()).unwrap();
+    let shapes: Array = shapes.into();
+    assert_eq!(shapes.length(), 2);
+    let first = shapes.get(0);
+    assert_eq!(
+        Reflect::get(&first, &"y".into()).unwrap().as_f64(),
+        Some(-2.25)
+    );
+    assert_eq!(
+        Reflect::get(&first, &"color".into()).unwrap().as_string(),
+        Some("rgb(10, 20, 30)".to_string())
+    );
+
+    let restored: DrawingState = serde_wasm_bindgen::from_value(value).unwrap();
+    assert_eq!(restored.shapes, state.shapes);
+}
+
+#[wasm_bindgen_test]
+fn test_drawing_state_from_js_defaults_a_missing_opacity() {
+    let shape = Object::new();
+    for (key, value) in [
+        ("x", JsValue::from(3.0)),
+        ("y", JsValue::from(4.0)),
+        ("radius", JsValue::from(5.0)),
+        ("color", JsValue::from("red")),
+    ] {
+        Reflect::set(&shape, &key.into(), &value).unwrap();
+    }
+    let state = Object::new();
+    Reflect::set(&state, &"shapes".into(), &Array::of1(&shape)).unwrap();
+
+    let loaded: DrawingState = serde_wasm_bindgen::from_value(state.into()).unwrap();
+    assert_eq!(loaded.shapes.len(), 1);
+    assert_eq!(loaded.shapes[0].opacity, 1.0);
+}
+
+#[wasm_bindgen_test]
+fn test_drawing_state_from_js_rejects_a_mismatched_schema() {
+    let state = Object::new();
+    Reflect::set(&state, &"shapes".into(), &"not a list".into()).unwrap();
+    assert!(serde_wasm_bindgen::from_value::<DrawingState>(state.into()).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_random_shapes_work_without_a_browser() {
+    // New shapes draw their color and radius from the JS crypto API.
+    let mut state = DrawingState::new();
+    for i in 0..10 {
+        state.add_shape(f64::from(i) * 50.0, 0.0);
+    }
+    assert_eq!(state.shapes.len(), 10);
+    assert!(state.shapes.iter().all(|shape| shape.radius > 0.0));
+}
@@ -0,0 +1,73 @@
+// base/benches/state_benches.rs
+//! How adding, saving and hit-testing shapes scale with the size of a drawing.
+//! Run with `cargo run --bin cli -- bench`, which compares against a baseline.
+use base::state::DrawingState;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::hint::black_box;
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+/// A drawing of `count` shapes spread over a large canvas, the same every run.
+fn drawing(count: usize) -> DrawingState {
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut state = DrawingState::new();
+    for _ in 0..count {
+        let (x, y) = (rng.gen_range(0.0..4000.0), rng.gen_range(0.0..4000.0));
+        state.add_shape_with_rng(x, y, &mut rng);
+    }
+    state
+}
+
+fn add_shapes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("add_shapes");
+    group.sample_size(20);
+    for count in SIZES {
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            b.iter(|| drawing(black_box(count)))
+        });
+    }
+    group.finish();
+}
+
+fn serialization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialize");
+    group.sample_size(20);
+    for count in SIZES {
+        let state = drawing(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &state, |b, state| {
+            b.iter(|| serde_json::to_string(black_box(state)).unwrap())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("deserialize");
+    group.sample_size(20);
+    for count in SIZES {
+        let json = serde_json::to_string(&drawing(count)).unwrap();
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &json, |b, json| {
+            b.iter(|| serde_json::from_str::<DrawingState>(black_box(json)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn hit_testing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("shape_at");
+    for count in SIZES {
+        let state = drawing(count);
+        group.throughput(Throughput::Elements(count as u64));
+        // A miss checks every shape, the worst case for each click.
+        group.bench_with_input(BenchmarkId::from_parameter(count), &state, |b, state| {
+            b.iter(|| state.shape_at(black_box(-500.0), black_box(-500.0)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, add_shapes, serialization, hit_testing);
+criterion_main!(benches);
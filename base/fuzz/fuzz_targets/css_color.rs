@@ -0,0 +1,12 @@
+// base/fuzz/fuzz_targets/css_color.rs
+//! Shape colors are stored as CSS strings and parsed back for animation.
+#![no_main]
+
+use base::color::Rgb;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    if let Some(color) = Rgb::parse(text) {
+        assert_eq!(Rgb::parse(&color.to_string()), Some(color));
+    }
+});
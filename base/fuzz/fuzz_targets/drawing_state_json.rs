@@ -0,0 +1,21 @@
+// base/fuzz/fuzz_targets/drawing_state_json.rs
+//! Saved drawings are loaded from untrusted JSON. Any input either fails to
+//! parse or gives a state that saves again and loads back unchanged.
+#![no_main]
+
+use base::state::DrawingState;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(state) = serde_json::from_slice::<DrawingState>(data) else {
+        return;
+    };
+    let json = serde_json::to_string(&state).expect("A loaded state should save");
+    let restored: DrawingState =
+        serde_json::from_str(&json).expect("A saved state should load");
+    assert_eq!(restored.shapes.len(), state.shapes.len());
+    assert_eq!(
+        serde_json::to_string(&restored).expect("A loaded state should save"),
+        json
+    );
+});
@@ -0,0 +1,17 @@
+// base/fuzz/fuzz_targets/key_combo.rs
+//! A combo that parses displays as a string that parses to the same combo.
+#![no_main]
+
+use base::keymap::KeyCombo;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    let Ok(combo) = text.parse::<KeyCombo>() else {
+        return;
+    };
+    let shown = combo.to_string();
+    let reparsed: KeyCombo = shown
+        .parse()
+        .unwrap_or_else(|e| panic!("'{}' from '{}' does not parse: {}", shown, text, e));
+    assert_eq!(reparsed, combo, "'{}' from '{}'", shown, text);
+});
@@ -0,0 +1,17 @@
+// base/fuzz/fuzz_targets/keymap_json.rs
+//! User key binding overrides are JSON objects of command id to combos.
+#![no_main]
+
+use base::keymap::Keymap;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(keymap) = serde_json::from_slice::<Keymap>(data) else {
+        return;
+    };
+    let json = serde_json::to_string(&keymap).expect("A loaded keymap should save");
+    let restored: Keymap = serde_json::from_str(&json).expect("A saved keymap should load");
+    assert_eq!(restored, keymap);
+    // Conflict detection must cope with whatever was loaded.
+    let _ = keymap.conflicts();
+});
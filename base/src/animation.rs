@@ -0,0 +1,390 @@
+// base/src/animation.rs
+use crate::color::Rgb;
+use crate::easing::Easing;
+use crate::state::Shape;
+
+/// A shape property that can be animated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Property {
+    X,
+    Y,
+    Radius,
+    Opacity,
+    Color,
+}
+
+/// A value a property can take. `Color` tracks expect `Value::Color`; all
+/// other properties expect `Value::Number`. Mismatched values are ignored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Color(Rgb),
+}
+
+impl Value {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => Value::Number(a + (b - a) * t),
+            (Value::Color(a), Value::Color(b)) => Value::Color(a.lerp(b, t)),
+            // Mismatched keyframes cannot be blended; snap at the midpoint.
+            (a, b) => {
+                if t < 0.5 {
+                    a
+                } else {
+                    b
+                }
+            }
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<Rgb> for Value {
+    fn from(value: Rgb) -> Self {
+        Value::Color(value)
+    }
+}
+
+/// A property value at a point in time, relative to the start of the animation.
+/// `easing` shapes the segment that ends at this keyframe.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    pub time: f64,
+    pub value: Value,
+    pub easing: Easing,
+}
+
+/// The keyframes for a single property, kept sorted by time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track {
+    pub property: Property,
+    keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+    pub fn new(property: Property) -> Self {
+        Self {
+            property,
+            keyframes: Vec::new(),
+        }
+    }
+
+    /// Tweens `property` from `from` to `to` over `duration` seconds.
+    pub fn tween(
+        property: Property,
+        from: impl Into<Value>,
+        to: impl Into<Value>,
+        duration: f64,
+        easing: Easing,
+    ) -> Self {
+        Self::new(property)
+            .keyframe(0.0, from, Easing::Linear)
+            .keyframe(duration, to, easing)
+    }
+
+    /// Adds a keyframe at `time` seconds. Keyframes may be added in any order.
+    pub fn keyframe(mut self, time: f64, value: impl Into<Value>, easing: Easing) -> Self {
+        let keyframe = Keyframe {
+            time: time.max(0.0),
+            value: value.into(),
+            easing,
+        };
+        let index = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+        self.keyframes.insert(index, keyframe);
+        self
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// The time of the last keyframe.
+    pub fn duration(&self) -> f64 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// The interpolated value at `time` seconds. Before the first keyframe the
+    /// first value is held, after the last keyframe the last value is held.
+    pub fn sample(&self, time: f64) -> Option<Value> {
+        let first = self.keyframes.first()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+        let next_index = self.keyframes.partition_point(|k| k.time <= time);
+        let Some(next) = self.keyframes.get(next_index) else {
+            return self.keyframes.last().map(|k| k.value);
+        };
+        let prev = &self.keyframes[next_index - 1];
+        let progress = (time - prev.time) / (next.time - prev.time);
+        Some(prev.value.lerp(next.value, next.easing.apply(progress)))
+    }
+}
+
+/// How many times an animation plays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Repeat {
+    #[default]
+    Once,
+    Times(u32),
+    Forever,
+}
+
+/// A set of property tracks applied to one shape, identified by its index.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animation {
+    pub target: usize,
+    pub tracks: Vec<Track>,
+    pub delay: f64,
+    pub repeat: Repeat,
+    /// Plays every other iteration backwards, for ping-pong loops.
+    pub alternate: bool,
+}
+
+impl Animation {
+    pub fn new(target: usize) -> Self {
+        Self {
+            target,
+            tracks: Vec::new(),
+            delay: 0.0,
+            repeat: Repeat::Once,
+            alternate: false,
+        }
+    }
+
+    /// A preset that grows a shape from nothing to `radius` while fading it in.
+    pub fn scale_in(target: usize, radius: f64, duration: f64) -> Self {
+        Self::new(target)
+            .track(Track::tween(
+                Property::Radius,
+                0.0,
+                radius,
+                duration,
+                Easing::EaseOutBack,
+            ))
+            .track(Track::tween(
+                Property::Opacity,
+                0.0,
+                1.0,
+                duration,
+                Easing::EaseOutQuad,
+            ))
+    }
+
+    pub fn track(mut self, track: Track) -> Self {
+        self.tracks.push(track);
+        self
+    }
+
+    pub fn delay(mut self, delay: f64) -> Self {
+        self.delay = delay.max(0.0);
+        self
+    }
+
+    pub fn repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn alternate(mut self, alternate: bool) -> Self {
+        self.alternate = alternate;
+        self
+    }
+
+    /// The length of a single iteration: the latest keyframe across all tracks.
+    pub fn duration(&self) -> f64 {
+        self.tracks.iter().map(Track::duration).fold(0.0, f64::max)
+    }
+
+    /// The number of iterations, or `None` if the animation repeats forever.
+    fn iterations(&self) -> Option<u32> {
+        match self.repeat {
+            Repeat::Once => Some(1),
+            Repeat::Times(n) => Some(n.max(1)),
+            Repeat::Forever => None,
+        }
+    }
+
+    /// The time from start (including the delay) until the animation finishes,
+    /// or `None` if it repeats forever.
+    pub fn total_duration(&self) -> Option<f64> {
+        self.iterations()
+            .map(|n| self.delay + self.duration() * n as f64)
+    }
+
+    /// Maps time since start to time within the current iteration, or `None`
+    /// if the delay has not elapsed yet. Finished animations hold their final pose.
+    fn local_time(&self, elapsed: f64) -> Option<f64> {
+        let active = elapsed - self.delay;
+        if active < 0.0 {
+            return None;
+        }
+        let duration = self.duration();
+        if duration <= 0.0 {
+            return Some(0.0);
+        }
+
+        let (iteration, offset) = match self.iterations() {
+            Some(n) if active >= duration * n as f64 => (n - 1, duration),
+            _ => {
+                let iteration = (active / duration).floor();
+                (iteration as u32, active - iteration * duration)
+            }
+        };
+
+        if self.alternate && iteration % 2 == 1 {
+            Some(duration - offset)
+        } else {
+            Some(offset)
+        }
+    }
+
+    fn apply(&self, elapsed: f64, shape: &mut Shape) {
+        let Some(time) = self.local_time(elapsed) else {
+            return;
+        };
+        for track in &self.tracks {
+            if let Some(value) = track.sample(time) {
+                apply_value(shape, track.property, value);
+            }
+        }
+    }
+}
+
+fn apply_value(shape: &mut Shape, property: Property, value: Value) {
+    match (property, value) {
+        (Property::X, Value::Number(v)) => shape.x = v,
+        (Property::Y, Value::Number(v)) => shape.y = v,
+        (Property::Radius, Value::Number(v)) => shape.radius = v.max(0.0),
+        (Property::Opacity, Value::Number(v)) => shape.opacity = v.clamp(0.0, 1.0),
+        (Property::Color, Value::Color(c)) => shape.color = c.to_string(),
+        _ => {}
+    }
+}
+
+/// Identifies an animation started by an `Animator`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AnimationId(u64);
+
+#[derive(Clone, Debug, PartialEq)]
+struct Playing {
+    id: AnimationId,
+    start: f64,
+    animation: Animation,
+}
+
+/// Plays animations against a deterministic clock that only moves when
+/// `advance` is called, so the same sequence of steps always produces the same result.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Animator {
+    time: f64,
+    playing: Vec<Playing>,
+    next_id: u64,
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The animator's clock, in seconds since it was created.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Starts `animation` at the current time.
+    pub fn play(&mut self, animation: Animation) -> AnimationId {
+        self.play_at(self.time, animation)
+    }
+
+    /// Starts `animations` one after another. An animation that repeats
+    /// forever never finishes, so anything queued after it never starts.
+    pub fn play_sequence(
+        &mut self,
+        animations: impl IntoIterator<Item = Animation>,
+    ) -> Vec<AnimationId> {
+        let mut start = self.time;
+        animations
+            .into_iter()
+            .map(|animation| {
+                let next_start = animation
+                    .total_duration()
+                    .map_or(f64::INFINITY, |total| start + total);
+                let id = self.play_at(start, animation);
+                start = next_start;
+                id
+            })
+            .collect()
+    }
+
+    fn play_at(&mut self, start: f64, animation: Animation) -> AnimationId {
+        let id = AnimationId(self.next_id);
+        self.next_id += 1;
+        self.playing.push(Playing {
+            id,
+            start,
+            animation,
+        });
+        id
+    }
+
+    /// Stops an animation, leaving its shape as it currently is.
+    /// Returns whether the animation was still playing.
+    pub fn cancel(&mut self, id: AnimationId) -> bool {
+        let before = self.playing.len();
+        self.playing.retain(|p| p.id != id);
+        self.playing.len() != before
+    }
+
+    pub fn is_playing(&self, id: AnimationId) -> bool {
+        self.playing.iter().any(|p| p.id == id)
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.playing.is_empty()
+    }
+
+    /// Stops all animations.
+    pub fn clear(&mut self) {
+        self.playing.clear();
+    }
+
+    /// Stops the animations of the shape at `index` and retargets animations of
+    /// later shapes, for when that shape is removed from the list.
+    pub fn remove_target(&mut self, index: usize) {
+        self.playing.retain(|p| p.animation.target != index);
+        for playing in &mut self.playing {
+            if playing.animation.target > index {
+                playing.animation.target -= 1;
+            }
+        }
+    }
+
+    /// Moves the clock forward by `dt` seconds and writes the animated values
+    /// into `shapes`. Finished animations apply their final values once and are
+    /// then removed, as are animations whose target shape no longer exists.
+    pub fn advance(&mut self, dt: f64, shapes: &mut [Shape]) {
+        if dt.is_finite() && dt > 0.0 {
+            self.time += dt;
+        }
+        let now = self.time;
+        self.playing.retain(|playing| {
+            let Some(shape) = shapes.get_mut(playing.animation.target) else {
+                return false;
+            };
+            if now < playing.start {
+                return true;
+            }
+            let elapsed = now - playing.start;
+            playing.animation.apply(elapsed, shape);
+            playing
+                .animation
+                .total_duration()
+                .is_none_or(|total| elapsed < total)
+        });
+    }
+}
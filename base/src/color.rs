@@ -0,0 +1,46 @@
+// base/src/color.rs
+use std::fmt;
+
+/// An opaque RGB color, stored on shapes in CSS `rgb(r, g, b)` notation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Parses a color in the `rgb(r, g, b)` notation produced by `Display`.
+    /// Returns `None` for any other format.
+    pub fn parse(css: &str) -> Option<Self> {
+        let inner = css.trim().strip_prefix("rgb(")?.strip_suffix(')')?;
+        let mut channels = inner.split(',').map(|c| c.trim().parse::<u8>());
+        let rgb = Self::new(
+            channels.next()?.ok()?,
+            channels.next()?.ok()?,
+            channels.next()?.ok()?,
+        );
+        channels.next().is_none().then_some(rgb)
+    }
+
+    /// Linearly interpolates each channel towards `other`. `t` is clamped to `[0, 1]`.
+    pub fn lerp(self, other: Self, t: f64) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        Self::new(
+            channel(self.r, other.r),
+            channel(self.g, other.g),
+            channel(self.b, other.b),
+        )
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rgb({}, {}, {})", self.r, self.g, self.b)
+    }
+}
@@ -0,0 +1,70 @@
+// base/src/history.rs
+use crate::state::{DrawingState, Shape};
+
+/// The default number of undo steps kept.
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// Undo/redo stacks of shape snapshots. Record the state *before* each change;
+/// `undo` then restores it and moves the current shapes onto the redo stack.
+#[derive(Clone, Debug, PartialEq)]
+pub struct History {
+    undo: Vec<Vec<Shape>>,
+    redo: Vec<Vec<Shape>>,
+    limit: usize,
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            limit: limit.max(1),
+        }
+    }
+
+    /// Saves the current shapes as an undo step and forgets the redo stack.
+    pub fn record(&mut self, state: &DrawingState) {
+        if self.undo.len() == self.limit {
+            self.undo.remove(0);
+        }
+        self.undo.push(state.shapes.clone());
+        self.redo.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Restores the most recent undo step. Returns whether there was one.
+    pub fn undo(&mut self, state: &mut DrawingState) -> bool {
+        let Some(shapes) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(state.replace_shapes(shapes));
+        true
+    }
+
+    /// Re-applies the most recently undone step. Returns whether there was one.
+    pub fn redo(&mut self, state: &mut DrawingState) -> bool {
+        let Some(shapes) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(state.replace_shapes(shapes));
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_LIMIT)
+    }
+}
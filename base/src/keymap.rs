@@ -0,0 +1,363 @@
+// base/src/keymap.rs
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// The platform a keymap is matched on. It decides what the `Mod` modifier means:
+/// Cmd on macOS and iOS, Ctrl everywhere else.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    Mac,
+    Other,
+}
+
+impl Platform {
+    /// Detects the platform from a `navigator.platform` or user agent string.
+    pub fn detect(platform: &str) -> Self {
+        if ["Mac", "iPhone", "iPad", "iPod"]
+            .iter()
+            .any(|p| platform.contains(p))
+        {
+            Platform::Mac
+        } else {
+            Platform::Other
+        }
+    }
+
+    const ALL: [Platform; 2] = [Platform::Mac, Platform::Other];
+}
+
+/// Returns the canonical form of a key name: single characters are lowercased,
+/// a literal space becomes `Space`, and named keys keep their spelling.
+fn normalize_key(key: &str) -> String {
+    match key {
+        " " => "Space".to_string(),
+        k if k.chars().count() == 1 => k.to_lowercase(),
+        k => k.to_string(),
+    }
+}
+
+/// A key with modifiers, written like `Mod+Shift+Z`. `Mod` is the platform's
+/// primary modifier; `Ctrl`, `Alt`, `Shift` and `Meta` are matched literally.
+#[derive(Clone, Debug, Default)]
+pub struct KeyCombo {
+    pub key: String,
+    pub primary: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+}
+
+/// The modifiers a combo needs on a specific platform.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Modifiers {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    meta: bool,
+}
+
+impl KeyCombo {
+    fn modifiers(&self, platform: Platform) -> Modifiers {
+        Modifiers {
+            ctrl: self.ctrl || (self.primary && platform == Platform::Other),
+            alt: self.alt,
+            shift: self.shift,
+            meta: self.meta || (self.primary && platform == Platform::Mac),
+        }
+    }
+
+    fn same_key(&self, key: &str) -> bool {
+        self.key.eq_ignore_ascii_case(key)
+    }
+
+    /// Whether this combo is triggered by `press` on `platform`.
+    pub fn matches(&self, press: &KeyPress, platform: Platform) -> bool {
+        let expected = self.modifiers(platform);
+        let actual = Modifiers {
+            ctrl: press.ctrl,
+            alt: press.alt,
+            shift: press.shift,
+            meta: press.meta,
+        };
+        expected == actual && self.same_key(&normalize_key(&press.key))
+    }
+
+    /// Whether both combos are triggered by the same key press on any platform.
+    /// `Mod+Z` and `Ctrl+Z` conflict, because they are the same on Windows and Linux.
+    pub fn conflicts_with(&self, other: &KeyCombo) -> bool {
+        self.same_key(&other.key)
+            && Platform::ALL
+                .iter()
+                .any(|&p| self.modifiers(p) == other.modifiers(p))
+    }
+}
+
+impl PartialEq for KeyCombo {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_key(&other.key)
+            && self.primary == other.primary
+            && self.ctrl == other.ctrl
+            && self.alt == other.alt
+            && self.shift == other.shift
+            && self.meta == other.meta
+    }
+}
+
+impl FromStr for KeyCombo {
+    type Err = KeymapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || KeymapError::InvalidCombo(s.to_string());
+        // A trailing "+" is the plus key itself, e.g. "Mod++".
+        let (modifiers, key) = match s.strip_suffix("++") {
+            Some(rest) => (rest, "+"),
+            None if s == "+" => ("", "+"),
+            None => s.rsplit_once('+').unwrap_or(("", s)),
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut combo = KeyCombo {
+            key: normalize_key(key),
+            ..KeyCombo::default()
+        };
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            let flag = match modifier.trim().to_ascii_lowercase().as_str() {
+                "mod" | "primary" => &mut combo.primary,
+                "ctrl" | "control" => &mut combo.ctrl,
+                "alt" | "option" => &mut combo.alt,
+                "shift" => &mut combo.shift,
+                "meta" | "cmd" | "command" | "super" => &mut combo.meta,
+                _ => return Err(invalid()),
+            };
+            if *flag {
+                return Err(invalid());
+            }
+            *flag = true;
+        }
+        Ok(combo)
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.primary, "Mod"),
+            (self.ctrl, "Ctrl"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+            (self.meta, "Meta"),
+        ];
+        for (_, name) in modifiers.iter().filter(|(on, _)| *on) {
+            write!(f, "{}+", name)?;
+        }
+        // Letters show in upper case, unless that parses as another key, as
+        // "ß" does as "SS".
+        let upper = self.key.to_uppercase();
+        if self.key.chars().count() == 1
+            && upper.chars().count() == 1
+            && upper.to_lowercase() == self.key
+        {
+            write!(f, "{}", upper)
+        } else {
+            write!(f, "{}", self.key)
+        }
+    }
+}
+
+impl Serialize for KeyCombo {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyCombo {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// A key press as reported by the platform, e.g. from a DOM `KeyboardEvent`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyPress {
+    pub key: String,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+}
+
+/// Errors produced when parsing or changing key bindings.
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeymapError {
+    /// The text is not a valid key combination.
+    InvalidCombo(String),
+    /// The combination is already bound to another command.
+    Conflict { combo: KeyCombo, command: String },
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapError::InvalidCombo(s) => write!(f, "Invalid key combination '{}'", s),
+            KeymapError::Conflict { combo, command } => {
+                write!(f, "'{}' is already bound to '{}'", combo, command)
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeymapError {}
+
+/// Two commands that are triggered by the same key press.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    pub combo: KeyCombo,
+    pub commands: (String, String),
+}
+
+/// Maps command ids to the key combinations that trigger them.
+/// It serializes as a JSON object of command id to combo strings.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Keymap {
+    bindings: BTreeMap<String, Vec<KeyCombo>>,
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The command ids that have an entry, in sorted order.
+    pub fn commands(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(String::as_str)
+    }
+
+    /// The combos bound to `command`.
+    pub fn bindings(&self, command: &str) -> &[KeyCombo] {
+        self.bindings.get(command).map_or(&[], Vec::as_slice)
+    }
+
+    /// The command that `combo` would conflict with, other than `command` itself.
+    fn conflicting_command(&self, command: &str, combo: &KeyCombo) -> Option<&str> {
+        self.bindings
+            .iter()
+            .filter(|(other, _)| other.as_str() != command)
+            .find(|(_, combos)| combos.iter().any(|c| c.conflicts_with(combo)))
+            .map(|(other, _)| other.as_str())
+    }
+
+    /// Adds `combo` to `command`'s bindings, unless another command already uses it.
+    pub fn bind(&mut self, command: &str, combo: KeyCombo) -> Result<(), KeymapError> {
+        if let Some(other) = self.conflicting_command(command, &combo) {
+            return Err(KeymapError::Conflict {
+                combo,
+                command: other.to_string(),
+            });
+        }
+        let combos = self.bindings.entry(command.to_string()).or_default();
+        if !combos.contains(&combo) {
+            combos.push(combo);
+        }
+        Ok(())
+    }
+
+    /// Replaces all of `command`'s bindings. Nothing changes if any combo conflicts.
+    /// An empty list leaves the command unbound but keeps its entry.
+    pub fn set_bindings(
+        &mut self,
+        command: &str,
+        combos: Vec<KeyCombo>,
+    ) -> Result<(), KeymapError> {
+        for combo in &combos {
+            if let Some(other) = self.conflicting_command(command, combo) {
+                return Err(KeymapError::Conflict {
+                    combo: combo.clone(),
+                    command: other.to_string(),
+                });
+            }
+        }
+        let mut unique: Vec<KeyCombo> = Vec::with_capacity(combos.len());
+        for combo in combos {
+            if !unique.contains(&combo) {
+                unique.push(combo);
+            }
+        }
+        self.bindings.insert(command.to_string(), unique);
+        Ok(())
+    }
+
+    /// Removes one binding. Returns whether it was bound.
+    pub fn unbind(&mut self, command: &str, combo: &KeyCombo) -> bool {
+        let Some(combos) = self.bindings.get_mut(command) else {
+            return false;
+        };
+        let before = combos.len();
+        combos.retain(|c| c != combo);
+        combos.len() != before
+    }
+
+    /// The command triggered by `press` on `platform`, if any.
+    pub fn command_for(&self, press: &KeyPress, platform: Platform) -> Option<&str> {
+        self.bindings
+            .iter()
+            .find(|(_, combos)| combos.iter().any(|c| c.matches(press, platform)))
+            .map(|(command, _)| command.as_str())
+    }
+
+    /// Every pair of commands that share a key press. `bind` and `set_bindings`
+    /// prevent new conflicts; this finds them in deserialized keymaps.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let entries: Vec<(&String, &KeyCombo)> = self
+            .bindings
+            .iter()
+            .flat_map(|(command, combos)| combos.iter().map(move |c| (command, c)))
+            .collect();
+        let mut conflicts = Vec::new();
+        for (i, (command_a, combo_a)) in entries.iter().enumerate() {
+            for (command_b, combo_b) in &entries[i + 1..] {
+                if command_a != command_b && combo_a.conflicts_with(combo_b) {
+                    conflicts.push(Conflict {
+                        combo: (*combo_a).clone(),
+                        commands: ((*command_a).clone(), (*command_b).clone()),
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Replaces the bindings of every command in `overrides` that this keymap
+    /// already knows. Overrides for unknown commands are ignored, and overrides
+    /// that would conflict are skipped and returned as errors.
+    pub fn apply_overrides(&mut self, overrides: &Keymap) -> Vec<KeymapError> {
+        let mut errors = Vec::new();
+        for (command, combos) in &overrides.bindings {
+            if !self.bindings.contains_key(command) {
+                continue;
+            }
+            if let Err(e) = self.set_bindings(command, combos.clone()) {
+                errors.push(e);
+            }
+        }
+        errors
+    }
+
+    /// The entries that differ from `defaults`, i.e. what a user has rebound.
+    pub fn overrides(&self, defaults: &Keymap) -> Keymap {
+        let bindings = self
+            .bindings
+            .iter()
+            .filter(|(command, combos)| defaults.bindings.get(*command) != Some(combos))
+            .map(|(command, combos)| (command.clone(), combos.clone()))
+            .collect();
+        Keymap { bindings }
+    }
+}
@@ -3,8 +3,24 @@
 // It contains data structures, algorithms, and the main application logic.
 
 // Module declarations
+pub mod animation;
+pub mod color;
+pub mod easing;
+pub mod history;
+pub mod keymap;
+pub mod physics;
 pub mod state;
+pub mod timestep;
+pub mod tool;
+pub mod viewport;
 // ... other domain-specific modules
 
 // Re-export key types for easier access from other crates
+pub use animation::{Animation, Animator};
+pub use history::History;
+pub use keymap::{KeyCombo, KeyPress, Keymap, KeymapError, Platform};
+pub use physics::{PhysicsConfig, PhysicsWorld};
 pub use state::DrawingState;
+pub use timestep::FixedTimestep;
+pub use tool::Tool;
+pub use viewport::Viewport;
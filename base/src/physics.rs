@@ -0,0 +1,240 @@
+// base/src/physics.rs
+use crate::state::Shape;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+/// Shapes smaller than this are treated as this size when computing mass,
+/// so a circle that is scaling in from zero still has a finite mass.
+const MIN_BODY_RADIUS: f64 = 1.0;
+
+/// Tunable parameters for the simulation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhysicsConfig {
+    /// Downward acceleration, in canvas pixels per second squared.
+    pub gravity: f64,
+    /// Fraction of the normal velocity kept after a collision, in `[0, 1]`.
+    pub restitution: f64,
+    /// Coulomb friction coefficient applied along contact surfaces.
+    pub friction: f64,
+    /// New bodies start with a random horizontal speed up to this value.
+    pub max_initial_speed: f64,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            gravity: 980.0,
+            restitution: 0.8,
+            friction: 0.2,
+            max_initial_speed: 0.0,
+        }
+    }
+}
+
+/// The velocity of the shape with the same index.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Body {
+    pub vx: f64,
+    pub vy: f64,
+}
+
+/// The walls of the simulation: `[0, width] x [0, height]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Treats circles as rigid bodies. Positions live on the shapes themselves;
+/// the world only tracks velocities, one `Body` per shape index.
+#[derive(Clone, Debug)]
+pub struct PhysicsWorld {
+    pub config: PhysicsConfig,
+    pub bounds: Option<Bounds>,
+    bodies: Vec<Body>,
+    rng: StdRng,
+}
+
+/// The random number generator is left out so worlds compare by simulation state.
+impl PartialEq for PhysicsWorld {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config && self.bounds == other.bounds && self.bodies == other.bodies
+    }
+}
+
+impl PhysicsWorld {
+    /// Creates a world whose random initial velocities are seeded from entropy.
+    pub fn new(config: PhysicsConfig) -> Self {
+        Self::with_seed(config, thread_rng().gen())
+    }
+
+    /// Creates a world that replays identically for the same seed and inputs.
+    pub fn with_seed(config: PhysicsConfig, seed: u64) -> Self {
+        Self {
+            config,
+            bounds: None,
+            bodies: Vec::new(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn bodies(&self) -> &[Body] {
+        &self.bodies
+    }
+
+    /// Mutable access to the velocities, e.g. to launch a shape.
+    /// Call `sync` first so there is one body per shape.
+    pub fn bodies_mut(&mut self) -> &mut [Body] {
+        &mut self.bodies
+    }
+
+    /// Removes the body of the shape at `index`, for when that shape is removed.
+    pub fn remove_body(&mut self, index: usize) {
+        if index < self.bodies.len() {
+            self.bodies.remove(index);
+        }
+    }
+
+    /// Brings every body to rest. New bodies are created on the next `sync`.
+    pub fn reset_bodies(&mut self) {
+        self.bodies.clear();
+    }
+
+    /// Matches the body list to `shapes`: bodies of removed shapes are dropped
+    /// and new shapes get a body with a random initial velocity.
+    pub fn sync(&mut self, shapes: &[Shape]) {
+        self.bodies.truncate(shapes.len());
+        while self.bodies.len() < shapes.len() {
+            let max = self.config.max_initial_speed;
+            let vx = if max > 0.0 {
+                self.rng.gen_range(-max..=max)
+            } else {
+                0.0
+            };
+            self.bodies.push(Body { vx, vy: 0.0 });
+        }
+    }
+
+    /// Advances the simulation by `dt` seconds: integrates gravity and velocity,
+    /// then resolves circle-circle and circle-wall collisions.
+    pub fn step(&mut self, dt: f64, shapes: &mut [Shape]) {
+        self.sync(shapes);
+        if !(dt.is_finite() && dt > 0.0) {
+            return;
+        }
+
+        // Semi-implicit Euler: update velocity first, then position.
+        for (shape, body) in shapes.iter_mut().zip(&mut self.bodies) {
+            body.vy += self.config.gravity * dt;
+            shape.x += body.vx * dt;
+            shape.y += body.vy * dt;
+        }
+
+        for i in 0..shapes.len() {
+            for j in (i + 1)..shapes.len() {
+                self.resolve_circle_pair(i, j, shapes);
+            }
+        }
+
+        if let Some(bounds) = self.bounds {
+            for (shape, body) in shapes.iter_mut().zip(&mut self.bodies) {
+                resolve_walls(&self.config, bounds, shape, body);
+            }
+        }
+    }
+
+    fn resolve_circle_pair(&mut self, i: usize, j: usize, shapes: &mut [Shape]) {
+        let (a, b) = (&shapes[i], &shapes[j]);
+        let dx = b.x - a.x;
+        let dy = b.y - a.y;
+        let distance = dx.hypot(dy);
+        let min_distance = a.radius + b.radius;
+        if distance >= min_distance {
+            return;
+        }
+
+        // Coincident centres have no defined normal; push them apart horizontally.
+        let (nx, ny) = if distance > 0.0 {
+            (dx / distance, dy / distance)
+        } else {
+            (1.0, 0.0)
+        };
+        let inv_mass_a = inverse_mass(a);
+        let inv_mass_b = inverse_mass(b);
+        let inv_mass_sum = inv_mass_a + inv_mass_b;
+
+        // Separate the circles in proportion to their inverse masses.
+        let penetration = min_distance - distance;
+        let (share_a, share_b) = (inv_mass_a / inv_mass_sum, inv_mass_b / inv_mass_sum);
+        shapes[i].x -= nx * penetration * share_a;
+        shapes[i].y -= ny * penetration * share_a;
+        shapes[j].x += nx * penetration * share_b;
+        shapes[j].y += ny * penetration * share_b;
+
+        let (body_a, body_b) = (self.bodies[i], self.bodies[j]);
+        let rvx = body_b.vx - body_a.vx;
+        let rvy = body_b.vy - body_a.vy;
+        let normal_speed = rvx * nx + rvy * ny;
+        if normal_speed > 0.0 {
+            // Already separating.
+            return;
+        }
+
+        let jn = -(1.0 + self.config.restitution) * normal_speed / inv_mass_sum;
+        let (mut ix, mut iy) = (jn * nx, jn * ny);
+
+        // Friction acts along the tangent, limited by the Coulomb cone.
+        let (tx, ty) = (rvx - normal_speed * nx, rvy - normal_speed * ny);
+        let tangent_speed = tx.hypot(ty);
+        if tangent_speed > f64::EPSILON {
+            let jt = (tangent_speed / inv_mass_sum).min(self.config.friction * jn);
+            ix -= jt * tx / tangent_speed;
+            iy -= jt * ty / tangent_speed;
+        }
+
+        self.bodies[i].vx -= ix * inv_mass_a;
+        self.bodies[i].vy -= iy * inv_mass_a;
+        self.bodies[j].vx += ix * inv_mass_b;
+        self.bodies[j].vy += iy * inv_mass_b;
+    }
+}
+
+/// Mass is proportional to area.
+pub fn mass(shape: &Shape) -> f64 {
+    let radius = shape.radius.max(MIN_BODY_RADIUS);
+    std::f64::consts::PI * radius * radius
+}
+
+fn inverse_mass(shape: &Shape) -> f64 {
+    1.0 / mass(shape)
+}
+
+fn resolve_walls(config: &PhysicsConfig, bounds: Bounds, shape: &mut Shape, body: &mut Body) {
+    let r = shape.radius;
+    if shape.x - r < 0.0 {
+        shape.x = r;
+        bounce(config, &mut body.vx, &mut body.vy, -1.0);
+    } else if shape.x + r > bounds.width {
+        shape.x = bounds.width - r;
+        bounce(config, &mut body.vx, &mut body.vy, 1.0);
+    }
+    if shape.y - r < 0.0 {
+        shape.y = r;
+        bounce(config, &mut body.vy, &mut body.vx, -1.0);
+    } else if shape.y + r > bounds.height {
+        shape.y = bounds.height - r;
+        bounce(config, &mut body.vy, &mut body.vx, 1.0);
+    }
+}
+
+/// Reflects `normal` velocity that points into a wall on side `direction`
+/// (`-1` or `1`) and applies Coulomb friction to the `tangent` velocity.
+fn bounce(config: &PhysicsConfig, normal: &mut f64, tangent: &mut f64, direction: f64) {
+    let into_wall = *normal * direction;
+    if into_wall <= 0.0 {
+        return;
+    }
+    *normal = -*normal * config.restitution;
+    let friction = config.friction * (1.0 + config.restitution) * into_wall;
+    *tangent -= tangent.signum() * friction.min(tangent.abs());
+}
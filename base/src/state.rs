@@ -1,4 +1,7 @@
 // base/src/state.rs
+use crate::animation::{Animation, AnimationId, Animator};
+use crate::color::Rgb;
+use crate::physics::{Bounds, PhysicsConfig, PhysicsWorld};
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -13,6 +16,13 @@ pub struct Shape {
     pub y: f64,
     pub radius: f64,
     pub color: String,
+    /// Opacity in `[0, 1]`. Missing in older serialized states, where it defaults to opaque.
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+}
+
+fn default_opacity() -> f64 {
+    1.0
 }
 
 /// Manual implementation of PartialEq to handle floating-point comparisons.
@@ -22,17 +32,36 @@ impl PartialEq for Shape {
         (self.x - other.x).abs() < FLOAT_COMPARISON_EPSILON
             && (self.y - other.y).abs() < FLOAT_COMPARISON_EPSILON
             && (self.radius - other.radius).abs() < FLOAT_COMPARISON_EPSILON
+            && (self.opacity - other.opacity).abs() < FLOAT_COMPARISON_EPSILON
             // Compare the string field directly.
             && self.color == other.color
     }
 }
 
 /// Represents the entire state of the drawing application.
-/// This can still derive PartialEq, as it will now use the custom
-/// implementation for the `Shape` struct within its `shapes` vector.
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+/// PartialEq is implemented manually, so that only the shapes are compared.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct DrawingState {
     pub shapes: Vec<Shape>,
+    /// Runs shape animations. It is transient and not part of the serialized state.
+    #[serde(skip)]
+    pub animator: Animator,
+    /// Moves the shapes as rigid bodies while enabled. Transient, like the animator.
+    #[serde(skip)]
+    pub physics: Option<PhysicsWorld>,
+    /// Seeds the physics world each time it is enabled, so that runs can be
+    /// replayed. Without it the world is seeded from entropy.
+    #[serde(skip)]
+    pub physics_seed: Option<u64>,
+}
+
+/// Two states are equal when their shapes are. The transient animator and
+/// physics world only say how the shapes move next, so they are left out,
+/// along with the physics seed.
+impl PartialEq for DrawingState {
+    fn eq(&self, other: &Self) -> bool {
+        self.shapes == other.shapes
+    }
 }
 
 impl DrawingState {
@@ -42,23 +71,102 @@ impl DrawingState {
 
     /// Adds a new shape with a random color and radius at the given coordinates.
     pub fn add_shape(&mut self, x: f64, y: f64) {
-        let mut rng = thread_rng();
+        self.add_shape_with_rng(x, y, &mut thread_rng());
+    }
+
+    /// Like `add_shape`, but draws the color and radius from `rng`,
+    /// so seeded generators produce reproducible scenes.
+    pub fn add_shape_with_rng<R: Rng + ?Sized>(&mut self, x: f64, y: f64, rng: &mut R) {
         let radius = rng.gen_range(10.0..=50.0);
         let r: u8 = rng.gen_range(100..=255);
         let g: u8 = rng.gen_range(100..=255);
         let b: u8 = rng.gen_range(100..=255);
-        let color = format!("rgb({}, {}, {})", r, g, b);
+        let color = Rgb::new(r, g, b).to_string();
 
         self.shapes.push(Shape {
             x,
             y,
             radius,
             color,
+            opacity: 1.0,
         });
     }
 
-    /// Removes all shapes from the state.
+    /// Returns the index of the topmost shape containing the point, if any.
+    /// Later shapes are drawn on top, so they are checked first.
+    pub fn shape_at(&self, x: f64, y: f64) -> Option<usize> {
+        self.shapes.iter().rposition(|shape| {
+            let dx = x - shape.x;
+            let dy = y - shape.y;
+            dx * dx + dy * dy <= shape.radius * shape.radius
+        })
+    }
+
+    /// Removes the shape at `index` along with its animations and physics body.
+    pub fn remove_shape(&mut self, index: usize) -> Option<Shape> {
+        if index >= self.shapes.len() {
+            return None;
+        }
+        self.animator.remove_target(index);
+        if let Some(physics) = &mut self.physics {
+            physics.remove_body(index);
+        }
+        Some(self.shapes.remove(index))
+    }
+
+    /// Swaps in a new list of shapes, e.g. an undo snapshot, and returns the old one.
+    /// Running animations are stopped and physics bodies start again at rest.
+    pub fn replace_shapes(&mut self, shapes: Vec<Shape>) -> Vec<Shape> {
+        self.animator.clear();
+        if let Some(physics) = &mut self.physics {
+            physics.reset_bodies();
+        }
+        std::mem::replace(&mut self.shapes, shapes)
+    }
+
+    /// Starts an animation and immediately applies its starting values,
+    /// so the shape never renders in its un-animated state first.
+    pub fn animate(&mut self, animation: Animation) -> AnimationId {
+        let id = self.animator.play(animation);
+        self.animator.advance(0.0, &mut self.shapes);
+        id
+    }
+
+    /// Turns the physics simulation on or off. Enabling it starts every shape at rest
+    /// (apart from `PhysicsConfig::max_initial_speed`), seeded by `physics_seed`;
+    /// disabling it discards all velocities.
+    pub fn set_physics_enabled(&mut self, enabled: bool) {
+        match (enabled, self.physics.is_some()) {
+            (true, false) => {
+                let config = PhysicsConfig::default();
+                self.physics = Some(match self.physics_seed {
+                    Some(seed) => PhysicsWorld::with_seed(config, seed),
+                    None => PhysicsWorld::new(config),
+                });
+            }
+            (false, true) => self.physics = None,
+            _ => {}
+        }
+    }
+
+    /// Sets the walls of the physics simulation, if it is enabled.
+    pub fn set_physics_bounds(&mut self, width: f64, height: f64) {
+        if let Some(physics) = &mut self.physics {
+            physics.bounds = Some(Bounds { width, height });
+        }
+    }
+
+    /// Advances all time-dependent state by one fixed simulation step of `dt` seconds.
+    pub fn update(&mut self, dt: f64) {
+        self.animator.advance(dt, &mut self.shapes);
+        if let Some(physics) = &mut self.physics {
+            physics.step(dt, &mut self.shapes);
+        }
+    }
+
+    /// Removes all shapes from the state, stopping their animations.
     pub fn clear_shapes(&mut self) {
         self.shapes.clear();
+        self.animator.clear();
     }
 }
@@ -0,0 +1,106 @@
+// base/src/timestep.rs
+
+/// The default simulation step: 60 updates per second.
+pub const DEFAULT_STEP_SECONDS: f64 = 1.0 / 60.0;
+
+/// The default cap on steps per tick, so a long stall (e.g. a backgrounded tab)
+/// does not trigger a burst of catch-up updates.
+pub const DEFAULT_MAX_STEPS_PER_TICK: u32 = 5;
+
+/// Tolerance so that frame times which are an exact multiple of the step
+/// are not lost to floating-point rounding.
+const STEP_EPSILON: f64 = 1e-9;
+
+/// Converts variable frame times into a whole number of fixed-size simulation steps.
+///
+/// Time is fed in either as elapsed seconds (`advance`) or as monotonically
+/// increasing millisecond timestamps such as those passed to
+/// `requestAnimationFrame` callbacks (`tick`). Leftover time is carried over
+/// to the next call, so the simulation runs at the same rate regardless of
+/// the frame rate and can be driven deterministically from tests.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixedTimestep {
+    step: f64,
+    max_steps_per_tick: u32,
+    accumulator: f64,
+    last_timestamp: Option<f64>,
+}
+
+impl FixedTimestep {
+    /// Creates a timestep that produces one update per `step` seconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step` is not a positive, finite number.
+    pub fn new(step: f64) -> Self {
+        assert!(
+            step.is_finite() && step > 0.0,
+            "Timestep must be positive and finite, got {}",
+            step
+        );
+        Self {
+            step,
+            max_steps_per_tick: DEFAULT_MAX_STEPS_PER_TICK,
+            accumulator: 0.0,
+            last_timestamp: None,
+        }
+    }
+
+    /// Sets the maximum number of steps a single call may produce.
+    /// Time beyond that is discarded rather than simulated later.
+    pub fn with_max_steps_per_tick(mut self, max_steps_per_tick: u32) -> Self {
+        self.max_steps_per_tick = max_steps_per_tick.max(1);
+        self
+    }
+
+    /// The duration of one simulation step, in seconds.
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// How far the accumulated time is into the next step, in `[0, 1)`.
+    /// Useful for interpolating between the last two simulation states.
+    pub fn alpha(&self) -> f64 {
+        self.accumulator / self.step
+    }
+
+    /// Adds `elapsed` seconds and returns how many fixed steps should run.
+    /// Negative or non-finite durations are ignored.
+    pub fn advance(&mut self, elapsed: f64) -> u32 {
+        if elapsed.is_finite() && elapsed > 0.0 {
+            self.accumulator += elapsed;
+        }
+
+        let available = ((self.accumulator + STEP_EPSILON) / self.step).floor() as u64;
+        if available > u64::from(self.max_steps_per_tick) {
+            self.accumulator = 0.0;
+            return self.max_steps_per_tick;
+        }
+
+        self.accumulator = (self.accumulator - available as f64 * self.step).max(0.0);
+        available as u32
+    }
+
+    /// Feeds a millisecond timestamp and returns how many fixed steps should run.
+    /// The first timestamp after creation or `reset` only establishes a baseline.
+    pub fn tick(&mut self, timestamp_ms: f64) -> u32 {
+        let elapsed = self
+            .last_timestamp
+            .map_or(0.0, |last| (timestamp_ms - last) / 1000.0);
+        self.last_timestamp = Some(timestamp_ms);
+        self.advance(elapsed)
+    }
+
+    /// Forgets the last timestamp and any leftover time, e.g. after a pause,
+    /// so the paused duration is not simulated.
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.last_timestamp = None;
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(DEFAULT_STEP_SECONDS)
+    }
+}
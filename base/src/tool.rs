@@ -0,0 +1,40 @@
+// base/src/tool.rs
+use std::fmt;
+use std::str::FromStr;
+
+/// What a click on the canvas does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tool {
+    /// Adds a circle at the clicked point.
+    #[default]
+    Circle,
+    /// Removes the topmost shape under the clicked point.
+    Eraser,
+}
+
+impl Tool {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tool::Circle => "circle",
+            Tool::Eraser => "eraser",
+        }
+    }
+}
+
+impl FromStr for Tool {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "circle" => Ok(Tool::Circle),
+            "eraser" => Ok(Tool::Eraser),
+            _ => Err(format!("Unknown tool '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Tool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
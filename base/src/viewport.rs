@@ -0,0 +1,53 @@
+// base/src/viewport.rs
+
+pub const MIN_ZOOM: f64 = 0.25;
+pub const MAX_ZOOM: f64 = 8.0;
+/// The factor applied by a single zoom in or out step.
+pub const ZOOM_STEP: f64 = 1.25;
+
+/// Maps between canvas pixels and drawing coordinates. Zoom is anchored at
+/// the canvas origin, so a drawing point `p` is shown at `p * zoom`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    zoom: f64,
+}
+
+impl Viewport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    /// Sets the zoom, clamped to `[MIN_ZOOM, MAX_ZOOM]`.
+    pub fn set_zoom(&mut self, zoom: f64) {
+        if zoom.is_finite() {
+            self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        }
+    }
+
+    pub fn zoom_in(&mut self) {
+        self.set_zoom(self.zoom * ZOOM_STEP);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.set_zoom(self.zoom / ZOOM_STEP);
+    }
+
+    pub fn reset(&mut self) {
+        self.zoom = 1.0;
+    }
+
+    /// Converts a point in canvas pixels to drawing coordinates.
+    pub fn to_world(&self, x: f64, y: f64) -> (f64, f64) {
+        (x / self.zoom, y / self.zoom)
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self { zoom: 1.0 }
+    }
+}
@@ -0,0 +1,271 @@
+// base/tests/animation_tests.rs
+use base::animation::{Animation, Animator, Property, Repeat, Track, Value};
+use base::color::Rgb;
+use base::easing::Easing;
+use base::state::{DrawingState, Shape};
+
+const EPSILON: f64 = 1e-9;
+
+fn shape() -> Shape {
+    Shape {
+        x: 0.0,
+        y: 0.0,
+        radius: 10.0,
+        color: "rgb(0, 0, 0)".to_string(),
+        opacity: 1.0,
+    }
+}
+
+fn number(value: Option<Value>) -> f64 {
+    match value {
+        Some(Value::Number(v)) => v,
+        other => panic!("Expected a number, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_easing_curves_start_at_zero_and_end_at_one() {
+    let curves = [
+        Easing::Linear,
+        Easing::EaseInQuad,
+        Easing::EaseOutQuad,
+        Easing::EaseInOutQuad,
+        Easing::EaseInCubic,
+        Easing::EaseOutCubic,
+        Easing::EaseInOutCubic,
+        Easing::EaseOutBack,
+    ];
+    for easing in curves {
+        assert!(easing.apply(0.0).abs() < EPSILON, "{:?} at 0", easing);
+        assert!(
+            (easing.apply(1.0) - 1.0).abs() < EPSILON,
+            "{:?} at 1",
+            easing
+        );
+        assert!(
+            (easing.apply(2.0) - 1.0).abs() < EPSILON,
+            "{:?} is clamped",
+            easing
+        );
+    }
+    assert!(Easing::EaseInQuad.apply(0.5) < 0.5);
+    assert!(Easing::EaseOutQuad.apply(0.5) > 0.5);
+    assert!(
+        Easing::EaseOutBack.apply(0.8) > 1.0,
+        "EaseOutBack overshoots"
+    );
+}
+
+#[test]
+fn test_track_samples_keyframes() {
+    // Keyframes are added out of order on purpose.
+    let track = Track::new(Property::X)
+        .keyframe(2.0, 0.0, Easing::Linear)
+        .keyframe(0.0, 0.0, Easing::Linear)
+        .keyframe(1.0, 100.0, Easing::Linear);
+
+    assert_eq!(track.duration(), 2.0);
+    assert!((number(track.sample(-1.0)) - 0.0).abs() < EPSILON);
+    assert!((number(track.sample(0.5)) - 50.0).abs() < EPSILON);
+    assert!((number(track.sample(1.0)) - 100.0).abs() < EPSILON);
+    assert!((number(track.sample(1.5)) - 50.0).abs() < EPSILON);
+    assert!((number(track.sample(5.0)) - 0.0).abs() < EPSILON);
+    assert_eq!(Track::new(Property::X).sample(0.0), None);
+}
+
+#[test]
+fn test_tween_position_radius_color_and_opacity() {
+    let mut shapes = vec![shape()];
+    let mut animator = Animator::new();
+    animator.play(
+        Animation::new(0)
+            .track(Track::tween(Property::X, 0.0, 10.0, 1.0, Easing::Linear))
+            .track(Track::tween(Property::Y, 0.0, -10.0, 1.0, Easing::Linear))
+            .track(Track::tween(
+                Property::Radius,
+                10.0,
+                20.0,
+                1.0,
+                Easing::Linear,
+            ))
+            .track(Track::tween(
+                Property::Opacity,
+                1.0,
+                0.0,
+                1.0,
+                Easing::Linear,
+            ))
+            .track(Track::tween(
+                Property::Color,
+                Rgb::new(0, 0, 0),
+                Rgb::new(200, 100, 50),
+                1.0,
+                Easing::Linear,
+            )),
+    );
+
+    animator.advance(0.5, &mut shapes);
+    let s = &shapes[0];
+    assert!((s.x - 5.0).abs() < EPSILON);
+    assert!((s.y + 5.0).abs() < EPSILON);
+    assert!((s.radius - 15.0).abs() < EPSILON);
+    assert!((s.opacity - 0.5).abs() < EPSILON);
+    assert_eq!(s.color, "rgb(100, 50, 25)");
+
+    animator.advance(0.5, &mut shapes);
+    assert_eq!(shapes[0].color, "rgb(200, 100, 50)");
+    assert!(shapes[0].opacity.abs() < EPSILON);
+    assert!(!animator.is_animating(), "Finished animations are removed");
+}
+
+#[test]
+fn test_repeat_and_alternate() {
+    let mut shapes = vec![shape()];
+    let mut animator = Animator::new();
+    let id = animator.play(
+        Animation::new(0)
+            .track(Track::tween(Property::X, 0.0, 10.0, 1.0, Easing::Linear))
+            .repeat(Repeat::Times(3))
+            .alternate(true),
+    );
+
+    animator.advance(0.25, &mut shapes);
+    assert!((shapes[0].x - 2.5).abs() < EPSILON);
+    animator.advance(1.0, &mut shapes);
+    assert!(
+        (shapes[0].x - 7.5).abs() < EPSILON,
+        "Second pass runs backwards"
+    );
+    animator.advance(1.0, &mut shapes);
+    assert!(
+        (shapes[0].x - 2.5).abs() < EPSILON,
+        "Third pass runs forwards"
+    );
+    assert!(animator.is_playing(id));
+
+    animator.advance(5.0, &mut shapes);
+    assert!(
+        (shapes[0].x - 10.0).abs() < EPSILON,
+        "Ends on the final pose"
+    );
+    assert!(!animator.is_playing(id));
+}
+
+#[test]
+fn test_forever_loops_until_cancelled() {
+    let mut shapes = vec![shape()];
+    let mut animator = Animator::new();
+    let id = animator.play(
+        Animation::new(0)
+            .track(Track::tween(Property::X, 0.0, 10.0, 1.0, Easing::Linear))
+            .repeat(Repeat::Forever),
+    );
+
+    animator.advance(100.5, &mut shapes);
+    assert!((shapes[0].x - 5.0).abs() < 1e-6);
+    assert!(animator.cancel(id));
+    assert!(!animator.cancel(id));
+
+    animator.advance(0.25, &mut shapes);
+    assert!(
+        (shapes[0].x - 5.0).abs() < 1e-6,
+        "Cancelled animations stop in place"
+    );
+}
+
+#[test]
+fn test_delay_and_sequence() {
+    let mut shapes = vec![shape()];
+    let mut animator = Animator::new();
+    let move_x = Animation::new(0)
+        .track(Track::tween(Property::X, 0.0, 10.0, 1.0, Easing::Linear))
+        .delay(0.5);
+    let move_y = Animation::new(0).track(Track::tween(Property::Y, 0.0, 10.0, 1.0, Easing::Linear));
+    let ids = animator.play_sequence([move_x, move_y]);
+
+    animator.advance(0.25, &mut shapes);
+    assert_eq!(shapes[0], shape(), "Nothing moves during the delay");
+
+    animator.advance(0.75, &mut shapes);
+    assert!((shapes[0].x - 5.0).abs() < EPSILON);
+    assert_eq!(shapes[0].y, 0.0, "The second animation waits for the first");
+
+    animator.advance(1.0, &mut shapes);
+    assert!((shapes[0].x - 10.0).abs() < EPSILON);
+    assert!((shapes[0].y - 5.0).abs() < EPSILON);
+    assert!(!animator.is_playing(ids[0]));
+    assert!(animator.is_playing(ids[1]));
+}
+
+#[test]
+fn test_animations_for_missing_shapes_are_dropped() {
+    let mut animator = Animator::new();
+    animator.play(Animation::scale_in(3, 10.0, 1.0));
+    animator.advance(0.1, &mut [shape()]);
+    assert!(!animator.is_animating());
+}
+
+#[test]
+fn test_state_scale_in_is_deterministic() {
+    let run = || {
+        let mut state = DrawingState::new();
+        state.add_shape(10.0, 20.0);
+        let radius = 20.0;
+        state.animate(Animation::scale_in(0, radius, 0.3));
+        assert_eq!(state.shapes[0].radius, 0.0, "Starting values apply at once");
+        assert_eq!(state.shapes[0].opacity, 0.0);
+
+        let mut radii = Vec::new();
+        for _ in 0..30 {
+            state.update(1.0 / 60.0);
+            radii.push(state.shapes[0].radius);
+        }
+        assert!((state.shapes[0].radius - radius).abs() < EPSILON);
+        assert!((state.shapes[0].opacity - 1.0).abs() < EPSILON);
+        assert!(!state.animator.is_animating());
+        radii
+    };
+    assert_eq!(run(), run());
+}
+
+#[test]
+fn test_clear_shapes_stops_animations() {
+    let mut state = DrawingState::new();
+    state.add_shape(10.0, 20.0);
+    state.animate(Animation::scale_in(0, 20.0, 1.0));
+    state.clear_shapes();
+    assert!(!state.animator.is_animating());
+}
+
+#[test]
+fn test_running_animations_do_not_affect_state_equality() {
+    let mut animated = DrawingState::new();
+    animated.shapes.push(shape());
+    animated.animate(Animation::scale_in(0, 10.0, 1.0));
+    assert!(animated.animator.is_animating());
+
+    let still = DrawingState {
+        shapes: animated.shapes.clone(),
+        ..DrawingState::default()
+    };
+    assert_eq!(animated, still);
+    animated.update(0.5);
+    assert_ne!(animated, still, "Shapes that moved apart are not equal");
+}
+
+#[test]
+fn test_shape_without_opacity_deserializes_as_opaque() {
+    let json = r#"{"shapes":[{"x":1.0,"y":2.0,"radius":3.0,"color":"rgb(1, 2, 3)"}]}"#;
+    let state: DrawingState = serde_json::from_str(json).expect("Deserialization should succeed");
+    assert_eq!(state.shapes[0].opacity, 1.0);
+}
+
+#[test]
+fn test_rgb_parse_and_display() {
+    let color = Rgb::parse("rgb(12, 34, 255)").expect("Should parse");
+    assert_eq!(color, Rgb::new(12, 34, 255));
+    assert_eq!(color.to_string(), "rgb(12, 34, 255)");
+    assert_eq!(Rgb::parse("rgb(1, 2)"), None);
+    assert_eq!(Rgb::parse("rgb(1, 2, 3, 4)"), None);
+    assert_eq!(Rgb::parse("#ffffff"), None);
+}
@@ -1,5 +1,8 @@
 // base/tests/base_tests.rs
+use base::history::History;
 use base::state::DrawingState;
+use base::tool::Tool;
+use base::viewport::{Viewport, MAX_ZOOM, MIN_ZOOM};
 
 #[test]
 fn test_drawing_state_new() {
@@ -60,3 +63,99 @@ fn test_drawing_state_serialization() {
         "State should be equal after a serialization round-trip"
     );
 }
+
+#[test]
+fn test_shape_at_returns_topmost_hit() {
+    let mut state = DrawingState::new();
+    state.add_shape(100.0, 100.0);
+    state.add_shape(105.0, 100.0);
+    state.shapes[0].radius = 20.0;
+    state.shapes[1].radius = 20.0;
+
+    assert_eq!(
+        state.shape_at(105.0, 100.0),
+        Some(1),
+        "Later shapes are on top"
+    );
+    assert_eq!(state.shape_at(82.0, 100.0), Some(0));
+    assert_eq!(state.shape_at(300.0, 300.0), None);
+}
+
+#[test]
+fn test_remove_shape() {
+    let mut state = DrawingState::new();
+    state.add_shape(1.0, 1.0);
+    state.add_shape(2.0, 2.0);
+
+    let removed = state.remove_shape(0).expect("Shape should be removed");
+    assert_eq!(removed.x, 1.0);
+    assert_eq!(state.shapes.len(), 1);
+    assert_eq!(state.shapes[0].x, 2.0);
+    assert!(state.remove_shape(5).is_none());
+}
+
+#[test]
+fn test_history_undo_and_redo() {
+    let mut state = DrawingState::new();
+    let mut history = History::default();
+    assert!(!history.undo(&mut state));
+
+    history.record(&state);
+    state.add_shape(1.0, 1.0);
+    history.record(&state);
+    state.clear_shapes();
+
+    assert!(history.undo(&mut state));
+    assert_eq!(state.shapes.len(), 1, "Undo restores the cleared shape");
+    assert!(history.undo(&mut state));
+    assert!(state.shapes.is_empty());
+    assert!(!history.can_undo());
+
+    assert!(history.redo(&mut state));
+    assert_eq!(state.shapes.len(), 1);
+
+    // A new change forgets the redo stack.
+    history.record(&state);
+    state.add_shape(2.0, 2.0);
+    assert!(!history.can_redo());
+}
+
+#[test]
+fn test_history_limit() {
+    let mut state = DrawingState::new();
+    let mut history = History::new(2);
+    for i in 0..5 {
+        history.record(&state);
+        state.add_shape(i as f64, 0.0);
+    }
+    assert!(history.undo(&mut state));
+    assert!(history.undo(&mut state));
+    assert!(!history.undo(&mut state));
+    assert_eq!(state.shapes.len(), 3);
+}
+
+#[test]
+fn test_viewport_zoom_is_clamped() {
+    let mut viewport = Viewport::new();
+    viewport.zoom_in();
+    assert_eq!(viewport.to_world(125.0, 250.0), (100.0, 200.0));
+
+    for _ in 0..50 {
+        viewport.zoom_in();
+    }
+    assert_eq!(viewport.zoom(), MAX_ZOOM);
+    for _ in 0..50 {
+        viewport.zoom_out();
+    }
+    assert_eq!(viewport.zoom(), MIN_ZOOM);
+    viewport.reset();
+    assert_eq!(viewport.zoom(), 1.0);
+}
+
+#[test]
+fn test_tool_names() {
+    for tool in [Tool::Circle, Tool::Eraser] {
+        assert_eq!(tool.as_str().parse::<Tool>(), Ok(tool));
+    }
+    assert!("lasso".parse::<Tool>().is_err());
+}
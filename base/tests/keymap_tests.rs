@@ -0,0 +1,186 @@
+// base/tests/keymap_tests.rs
+use base::keymap::{KeyCombo, KeyPress, Keymap, KeymapError, Platform};
+
+fn combo(s: &str) -> KeyCombo {
+    s.parse().expect("Combo should parse")
+}
+
+fn press(key: &str, ctrl: bool, shift: bool, meta: bool) -> KeyPress {
+    KeyPress {
+        key: key.to_string(),
+        ctrl,
+        alt: false,
+        shift,
+        meta,
+    }
+}
+
+fn sample_keymap() -> Keymap {
+    let mut keymap = Keymap::new();
+    keymap.bind("edit.undo", combo("Mod+Z")).unwrap();
+    keymap.bind("edit.redo", combo("Mod+Shift+Z")).unwrap();
+    keymap.bind("tool.eraser", combo("E")).unwrap();
+    keymap.bind("app.togglePause", combo("Space")).unwrap();
+    keymap
+}
+
+#[test]
+fn test_parse_and_display_round_trip() {
+    assert_eq!(combo("mod+shift+z").to_string(), "Mod+Shift+Z");
+    assert_eq!(combo("Ctrl+Alt+Delete").to_string(), "Ctrl+Alt+Delete");
+    assert_eq!(combo("Cmd+=").to_string(), "Meta+=");
+    assert_eq!(combo("Mod++").to_string(), "Mod++");
+    // Upper-casing "ß" gives "SS", a different key.
+    assert_eq!(combo("Alt+ß").to_string(), "Alt+ß");
+    assert_eq!(combo("Space"), combo("space"));
+    assert_eq!(combo("Shift+Mod+Z"), combo("Mod+Shift+Z"));
+}
+
+#[test]
+fn test_invalid_combos_are_rejected() {
+    for text in ["", "Mod+", "Hyper+Z", "Shift+Shift+Z"] {
+        assert_eq!(
+            text.parse::<KeyCombo>(),
+            Err(KeymapError::InvalidCombo(text.to_string())),
+            "'{}' should not parse",
+            text
+        );
+    }
+}
+
+#[test]
+fn test_mod_is_platform_aware() {
+    let keymap = sample_keymap();
+    let ctrl_z = press("z", true, false, false);
+    let cmd_z = press("z", false, false, true);
+
+    assert_eq!(
+        keymap.command_for(&ctrl_z, Platform::Other),
+        Some("edit.undo")
+    );
+    assert_eq!(keymap.command_for(&cmd_z, Platform::Other), None);
+    assert_eq!(keymap.command_for(&cmd_z, Platform::Mac), Some("edit.undo"));
+    assert_eq!(keymap.command_for(&ctrl_z, Platform::Mac), None);
+
+    assert_eq!(Platform::detect("MacIntel"), Platform::Mac);
+    assert_eq!(Platform::detect("iPhone"), Platform::Mac);
+    assert_eq!(Platform::detect("Win32"), Platform::Other);
+    assert_eq!(Platform::detect("Linux x86_64"), Platform::Other);
+}
+
+#[test]
+fn test_matching_requires_exact_modifiers() {
+    let keymap = sample_keymap();
+    // Browsers report the shifted letter in upper case.
+    let ctrl_shift_z = press("Z", true, true, false);
+    assert_eq!(
+        keymap.command_for(&ctrl_shift_z, Platform::Other),
+        Some("edit.redo")
+    );
+    assert_eq!(
+        keymap.command_for(&press("e", false, false, false), Platform::Other),
+        Some("tool.eraser")
+    );
+    assert_eq!(
+        keymap.command_for(&press("e", true, false, false), Platform::Other),
+        None
+    );
+    assert_eq!(
+        keymap.command_for(&press(" ", false, false, false), Platform::Other),
+        Some("app.togglePause")
+    );
+}
+
+#[test]
+fn test_bind_detects_conflicts() {
+    let mut keymap = sample_keymap();
+    assert_eq!(
+        keymap.bind("tool.circle", combo("E")),
+        Err(KeymapError::Conflict {
+            combo: combo("E"),
+            command: "tool.eraser".to_string()
+        })
+    );
+    // "Ctrl+Z" is the same press as "Mod+Z" outside of macOS.
+    assert!(matches!(
+        keymap.bind("view.zoomOut", combo("Ctrl+Z")),
+        Err(KeymapError::Conflict { .. })
+    ));
+    // Binding the same combo to the same command again is a no-op.
+    keymap.bind("tool.eraser", combo("E")).unwrap();
+    assert_eq!(keymap.bindings("tool.eraser").len(), 1);
+    assert!(keymap.conflicts().is_empty());
+}
+
+#[test]
+fn test_set_bindings_rebinds_atomically() {
+    let mut keymap = sample_keymap();
+    let result = keymap.set_bindings("tool.eraser", vec![combo("X"), combo("Mod+Z")]);
+    assert!(result.is_err());
+    assert_eq!(
+        keymap.bindings("tool.eraser"),
+        [combo("E")],
+        "Nothing should change"
+    );
+
+    keymap
+        .set_bindings("tool.eraser", vec![combo("X"), combo("X")])
+        .unwrap();
+    assert_eq!(keymap.bindings("tool.eraser"), [combo("X")]);
+    assert_eq!(
+        keymap.command_for(&press("e", false, false, false), Platform::Other),
+        None
+    );
+
+    assert!(keymap.unbind("tool.eraser", &combo("X")));
+    assert!(!keymap.unbind("tool.eraser", &combo("X")));
+    assert!(keymap.bindings("tool.eraser").is_empty());
+}
+
+#[test]
+fn test_conflicts_found_in_deserialized_keymaps() {
+    let json = r#"{"a": ["Mod+K"], "b": ["Ctrl+K"], "c": ["Meta+K"]}"#;
+    let keymap: Keymap = serde_json::from_str(json).unwrap();
+    let pairs: Vec<_> = keymap.conflicts().into_iter().map(|c| c.commands).collect();
+    assert_eq!(
+        pairs,
+        [
+            ("a".to_string(), "b".to_string()),
+            ("a".to_string(), "c".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_overrides_persist_and_reapply() {
+    let defaults = sample_keymap();
+    let mut user = defaults.clone();
+    user.set_bindings("tool.eraser", vec![combo("X")]).unwrap();
+
+    let overrides = user.overrides(&defaults);
+    assert_eq!(overrides.commands().collect::<Vec<_>>(), ["tool.eraser"]);
+
+    let json = serde_json::to_string(&overrides).unwrap();
+    assert_eq!(json, r#"{"tool.eraser":["X"]}"#);
+
+    let saved: Keymap = serde_json::from_str(&json).unwrap();
+    let mut restored = defaults.clone();
+    assert!(restored.apply_overrides(&saved).is_empty());
+    assert_eq!(restored, user);
+}
+
+#[test]
+fn test_apply_overrides_skips_unknown_and_conflicting_entries() {
+    let mut keymap = sample_keymap();
+    let saved: Keymap =
+        serde_json::from_str(r#"{"removed.command": ["Q"], "tool.eraser": ["Space"]}"#).unwrap();
+    let errors = keymap.apply_overrides(&saved);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(keymap, sample_keymap());
+}
+
+#[test]
+fn test_invalid_saved_combo_fails_to_deserialize() {
+    let result = serde_json::from_str::<Keymap>(r#"{"tool.eraser": ["Hyper+E"]}"#);
+    assert!(result.is_err());
+}
@@ -0,0 +1,266 @@
+// base/tests/physics_tests.rs
+use base::physics::{mass, Body, Bounds, PhysicsConfig, PhysicsWorld};
+use base::state::{DrawingState, Shape};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const DT: f64 = 1.0 / 60.0;
+
+fn circle(x: f64, y: f64, radius: f64) -> Shape {
+    Shape {
+        x,
+        y,
+        radius,
+        color: "rgb(0, 0, 0)".to_string(),
+        opacity: 1.0,
+    }
+}
+
+fn weightless(restitution: f64, friction: f64) -> PhysicsConfig {
+    PhysicsConfig {
+        gravity: 0.0,
+        restitution,
+        friction,
+        max_initial_speed: 0.0,
+    }
+}
+
+/// Creates a world for `shapes` with the given starting velocities.
+fn launch(config: PhysicsConfig, shapes: &[Shape], velocities: &[(f64, f64)]) -> PhysicsWorld {
+    let mut world = PhysicsWorld::with_seed(config, 0);
+    world.sync(shapes);
+    for (body, &(vx, vy)) in world.bodies_mut().iter_mut().zip(velocities) {
+        *body = Body { vx, vy };
+    }
+    world
+}
+
+fn kinetic_energy(world: &PhysicsWorld, shapes: &[Shape]) -> f64 {
+    shapes
+        .iter()
+        .zip(world.bodies())
+        .map(|(s, b)| 0.5 * mass(s) * (b.vx * b.vx + b.vy * b.vy))
+        .sum()
+}
+
+/// Kinetic plus potential energy, with potential measured from the floor.
+fn total_energy(world: &PhysicsWorld, shapes: &[Shape], floor: f64) -> f64 {
+    let potential: f64 = shapes
+        .iter()
+        .map(|s| mass(s) * world.config.gravity * (floor - s.y))
+        .sum();
+    kinetic_energy(world, shapes) + potential
+}
+
+fn momentum(world: &PhysicsWorld, shapes: &[Shape]) -> (f64, f64) {
+    shapes
+        .iter()
+        .zip(world.bodies())
+        .fold((0.0, 0.0), |(px, py), (s, b)| {
+            (px + mass(s) * b.vx, py + mass(s) * b.vy)
+        })
+}
+
+/// A seeded scene with random radii and initial velocities.
+fn seeded_scene(seed: u64) -> (PhysicsWorld, Vec<Shape>) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut state = DrawingState::new();
+    for i in 0..12 {
+        let x = 60.0 + 75.0 * (i % 6) as f64;
+        let y = 60.0 + 110.0 * (i / 6) as f64;
+        state.add_shape_with_rng(x, y, &mut rng);
+    }
+    let config = PhysicsConfig {
+        max_initial_speed: 300.0,
+        ..PhysicsConfig::default()
+    };
+    let mut world = PhysicsWorld::with_seed(config, seed);
+    world.bounds = Some(Bounds {
+        width: 500.0,
+        height: 400.0,
+    });
+    (world, state.shapes)
+}
+
+#[test]
+fn test_gravity_accelerates_downwards() {
+    let mut world = PhysicsWorld::with_seed(PhysicsConfig::default(), 1);
+    let mut shapes = vec![circle(100.0, 100.0, 10.0)];
+    for _ in 0..60 {
+        world.step(DT, &mut shapes);
+    }
+    assert!((world.bodies()[0].vy - 980.0).abs() < 1e-6);
+    assert!(shapes[0].y > 500.0);
+    assert_eq!(shapes[0].x, 100.0);
+}
+
+#[test]
+fn test_walls_contain_circles() {
+    let (mut world, mut shapes) = seeded_scene(7);
+    for _ in 0..600 {
+        world.step(DT, &mut shapes);
+        for s in &shapes {
+            assert!(s.x - s.radius >= -1e-9 && s.x + s.radius <= 500.0 + 1e-9);
+            assert!(s.y - s.radius >= -1e-9 && s.y + s.radius <= 400.0 + 1e-9);
+        }
+    }
+}
+
+#[test]
+fn test_elastic_collisions_conserve_kinetic_energy() {
+    let mut shapes = vec![
+        circle(50.0, 150.0, 20.0),
+        circle(250.0, 160.0, 30.0),
+        circle(150.0, 60.0, 15.0),
+    ];
+    let velocities = [(300.0, 20.0), (-150.0, 80.0), (40.0, -250.0)];
+    let mut world = launch(weightless(1.0, 0.0), &shapes, &velocities);
+    world.bounds = Some(Bounds {
+        width: 300.0,
+        height: 300.0,
+    });
+    let initial = kinetic_energy(&world, &shapes);
+
+    for _ in 0..1200 {
+        world.step(DT, &mut shapes);
+    }
+    let after = kinetic_energy(&world, &shapes);
+    assert!(
+        (after - initial).abs() / initial < 1e-9,
+        "Energy drifted from {} to {}",
+        initial,
+        after
+    );
+}
+
+#[test]
+fn test_inelastic_collisions_never_gain_energy() {
+    let (mut world, mut shapes) = seeded_scene(11);
+    world.sync(&shapes);
+    let floor = 400.0;
+    let initial = total_energy(&world, &shapes, floor);
+
+    let mut energy = initial;
+    for _ in 0..1200 {
+        world.step(DT, &mut shapes);
+        energy = total_energy(&world, &shapes, floor);
+        // Semi-implicit Euler lets the total wobble slightly within a step.
+        assert!(energy <= initial * 1.01, "Energy grew to {}", energy);
+    }
+    assert!(
+        energy < initial * 0.5,
+        "Restitution and friction should dissipate energy"
+    );
+}
+
+#[test]
+fn test_collisions_conserve_momentum() {
+    // No walls and no gravity, so only circle-circle impulses act.
+    let mut shapes = vec![circle(0.0, 0.0, 20.0), circle(100.0, 8.0, 35.0)];
+    // An off-centre hit exercises both the normal and the friction impulse.
+    let velocities = [(200.0, 0.0), (-50.0, 10.0)];
+    let mut world = launch(weightless(0.5, 0.4), &shapes, &velocities);
+    let (px, py) = momentum(&world, &shapes);
+
+    let mut collided = false;
+    for _ in 0..120 {
+        let before = world.bodies().to_vec();
+        world.step(DT, &mut shapes);
+        collided |= world.bodies() != before.as_slice();
+    }
+    assert!(collided, "The circles should have collided");
+
+    let (qx, qy) = momentum(&world, &shapes);
+    assert!((qx - px).abs() < 1e-6 * px.abs().max(1.0));
+    assert!((qy - py).abs() < 1e-6 * px.abs().max(1.0));
+}
+
+#[test]
+fn test_same_seed_replays_identically() {
+    let run = |seed| {
+        let (mut world, mut shapes) = seeded_scene(seed);
+        for _ in 0..600 {
+            world.step(DT, &mut shapes);
+        }
+        (world, shapes)
+    };
+
+    let (world_a, shapes_a) = run(42);
+    let (world_b, shapes_b) = run(42);
+    assert_eq!(world_a, world_b);
+    for (a, b) in shapes_a.iter().zip(&shapes_b) {
+        assert_eq!(
+            (a.x, a.y, a.radius),
+            (b.x, b.y, b.radius),
+            "Replay must be bit-exact"
+        );
+    }
+
+    let (_, shapes_c) = run(43);
+    assert!(shapes_a.iter().zip(&shapes_c).any(|(a, c)| a.x != c.x));
+}
+
+#[test]
+fn test_state_update_steps_physics_only_when_enabled() {
+    let mut state = DrawingState::new();
+    state.add_shape(100.0, 100.0);
+    state.update(DT);
+    assert_eq!(state.shapes[0].y, 100.0);
+
+    state.set_physics_enabled(true);
+    state.set_physics_bounds(1000.0, 1000.0);
+    state.update(DT);
+    assert!(state.shapes[0].y > 100.0);
+
+    state.set_physics_enabled(false);
+    assert!(state.physics.is_none());
+    let y = state.shapes[0].y;
+    state.update(DT);
+    assert_eq!(state.shapes[0].y, y);
+}
+
+#[test]
+fn test_removed_shapes_drop_their_bodies() {
+    let mut state = DrawingState::new();
+    state.set_physics_enabled(true);
+    state.add_shape(10.0, 10.0);
+    state.add_shape(50.0, 10.0);
+    state.update(DT);
+    assert_eq!(state.physics.as_ref().unwrap().bodies().len(), 2);
+
+    state.clear_shapes();
+    state.update(DT);
+    assert!(state.physics.as_ref().unwrap().bodies().is_empty());
+}
+
+#[test]
+fn test_state_enables_physics_with_its_seed() {
+    let mut state = DrawingState::new();
+    state.physics_seed = Some(7);
+    state.set_physics_enabled(true);
+    assert_eq!(
+        state.physics,
+        Some(PhysicsWorld::with_seed(PhysicsConfig::default(), 7))
+    );
+
+    // Enabling it again starts from the same seed.
+    state.update(DT);
+    state.set_physics_enabled(false);
+    state.set_physics_enabled(true);
+    assert_eq!(
+        state.physics,
+        Some(PhysicsWorld::with_seed(PhysicsConfig::default(), 7))
+    );
+}
+
+#[test]
+fn test_physics_does_not_affect_state_equality() {
+    let mut simulated = DrawingState::new();
+    simulated.shapes.push(circle(100.0, 100.0, 20.0));
+    let still = simulated.clone();
+    simulated.set_physics_enabled(true);
+    assert_eq!(simulated, still);
+
+    simulated.update(DT);
+    assert_ne!(simulated, still, "A falling shape moved");
+}
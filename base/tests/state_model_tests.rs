@@ -0,0 +1,138 @@
+// base/tests/state_model_tests.rs
+//! Drives `DrawingState` with random sequences of operations and checks it
+//! against a plain list of shape centers after every step.
+use base::state::{DrawingState, Shape};
+use proptest::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// Canvas-sized coordinates, including negative ones from panning.
+const COORDINATE: std::ops::Range<f64> = -10_000.0..10_000.0;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Add { x: f64, y: f64 },
+    Remove(usize),
+    Replace(Vec<(f64, f64)>),
+    Clear,
+    Update(f64),
+    RoundTrip,
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (COORDINATE, COORDINATE).prop_map(|(x, y)| Op::Add { x, y }),
+        2 => (0..12usize).prop_map(Op::Remove),
+        1 => prop::collection::vec((COORDINATE, COORDINATE), 0..6).prop_map(Op::Replace),
+        1 => Just(Op::Clear),
+        1 => (0.0..0.1f64).prop_map(Op::Update),
+        1 => Just(Op::RoundTrip),
+    ]
+}
+
+fn shape(x: f64, y: f64) -> Shape {
+    Shape {
+        x,
+        y,
+        radius: 20.0,
+        color: "rgb(200, 100, 100)".to_string(),
+        opacity: 1.0,
+    }
+}
+
+fn round_trip(state: &DrawingState) -> DrawingState {
+    let json = serde_json::to_string(state).expect("Serialization should succeed");
+    serde_json::from_str(&json).expect("Deserialization should succeed")
+}
+
+/// The invariants that hold after every operation.
+fn check_against_model(state: &DrawingState, model: &[(f64, f64)]) -> Result<(), TestCaseError> {
+    prop_assert_eq!(state.shapes.len(), model.len());
+    for (index, (shape, &(x, y))) in state.shapes.iter().zip(model).enumerate() {
+        prop_assert_eq!((shape.x, shape.y), (x, y), "shape {} moved", index);
+        prop_assert!((10.0..=50.0).contains(&shape.radius));
+        prop_assert!((0.0..=1.0).contains(&shape.opacity));
+        // The center hits this shape or one drawn on top of it.
+        let hit = state.shape_at(x, y);
+        prop_assert!(
+            hit.is_some_and(|hit| hit >= index),
+            "shape_at missed {}",
+            index
+        );
+    }
+    // Only the shapes are saved; the animation clock is runtime state.
+    prop_assert_eq!(&round_trip(state).shapes, &state.shapes);
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn test_operations_match_the_model(seed: u64, ops in prop::collection::vec(op(), 1..40)) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut state = DrawingState::new();
+        let mut model: Vec<(f64, f64)> = Vec::new();
+
+        for op in ops {
+            match op {
+                Op::Add { x, y } => {
+                    state.add_shape_with_rng(x, y, &mut rng);
+                    model.push((x, y));
+                }
+                Op::Remove(index) => {
+                    let removed = state.remove_shape(index);
+                    if index < model.len() {
+                        let (x, y) = model.remove(index);
+                        let removed = removed.expect("An existing shape should be removed");
+                        prop_assert_eq!((removed.x, removed.y), (x, y));
+                    } else {
+                        prop_assert!(removed.is_none());
+                    }
+                }
+                Op::Replace(centers) => {
+                    let shapes = centers.iter().map(|&(x, y)| shape(x, y)).collect();
+                    let old = state.replace_shapes(shapes);
+                    let old_centers: Vec<(f64, f64)> = old.iter().map(|s| (s.x, s.y)).collect();
+                    prop_assert_eq!(old_centers, model);
+                    model = centers;
+                }
+                Op::Clear => {
+                    state.clear_shapes();
+                    model.clear();
+                }
+                // Without animations or physics, time passing changes nothing.
+                Op::Update(dt) => state.update(dt),
+                Op::RoundTrip => state = round_trip(&state),
+            }
+            check_against_model(&state, &model)?;
+        }
+    }
+
+    #[test]
+    fn test_serialization_round_trips_any_shapes(
+        shapes in prop::collection::vec(
+            (COORDINATE, COORDINATE, 10.0..50.0f64, any::<[u8; 3]>(), 0.0..=1.0f64),
+            0..20,
+        )
+    ) {
+        let mut state = DrawingState::new();
+        state.replace_shapes(
+            shapes
+                .into_iter()
+                .map(|(x, y, radius, [r, g, b], opacity)| Shape {
+                    x,
+                    y,
+                    radius,
+                    color: format!("rgb({}, {}, {})", r, g, b),
+                    opacity,
+                })
+                .collect(),
+        );
+        let restored = round_trip(&state);
+        prop_assert_eq!(&restored, &state);
+        // Serializing the restored state gives the same document again.
+        prop_assert_eq!(
+            serde_json::to_string(&restored).unwrap(),
+            serde_json::to_string(&state).unwrap()
+        );
+    }
+}
@@ -0,0 +1,113 @@
+// base/tests/timestep_tests.rs
+use base::physics::{PhysicsConfig, PhysicsWorld};
+use base::state::DrawingState;
+use base::timestep::{FixedTimestep, DEFAULT_MAX_STEPS_PER_TICK, DEFAULT_STEP_SECONDS};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+#[test]
+fn test_advance_produces_whole_steps() {
+    let mut timestep = FixedTimestep::new(0.1);
+    assert_eq!(timestep.advance(0.05), 0, "Half a step should not run yet");
+    assert_eq!(timestep.advance(0.05), 1, "Leftover time should carry over");
+    assert_eq!(timestep.advance(0.25), 2);
+    assert!((timestep.alpha() - 0.5).abs() < 1e-9);
+}
+
+#[test]
+fn test_exact_frame_times_do_not_drift() {
+    let mut timestep = FixedTimestep::default();
+    let total: u32 = (0..600)
+        .map(|_| timestep.advance(DEFAULT_STEP_SECONDS))
+        .sum();
+    assert_eq!(
+        total, 600,
+        "One step per step-sized frame, with no rounding loss"
+    );
+}
+
+#[test]
+fn test_tick_uses_millisecond_timestamps() {
+    let mut timestep = FixedTimestep::new(0.01);
+    assert_eq!(
+        timestep.tick(1000.0),
+        0,
+        "The first tick only sets a baseline"
+    );
+    assert_eq!(timestep.tick(1030.0), 3);
+    assert_eq!(timestep.tick(1030.0), 0);
+    assert_eq!(timestep.tick(1020.0), 0, "Time going backwards is ignored");
+}
+
+#[test]
+fn test_long_stall_is_capped() {
+    let mut timestep = FixedTimestep::default();
+    assert_eq!(timestep.advance(10.0), DEFAULT_MAX_STEPS_PER_TICK);
+    assert_eq!(timestep.advance(0.0), 0, "Excess time should be discarded");
+
+    let mut timestep = FixedTimestep::new(0.1).with_max_steps_per_tick(2);
+    assert_eq!(timestep.advance(1.0), 2);
+}
+
+#[test]
+fn test_reset_skips_paused_time() {
+    let mut timestep = FixedTimestep::new(0.01);
+    timestep.tick(0.0);
+    assert_eq!(timestep.tick(15.0), 1);
+
+    // Simulate a pause: the next timestamp is far in the future.
+    timestep.reset();
+    assert_eq!(timestep.tick(60_000.0), 0);
+    assert_eq!(timestep.tick(60_020.0), 2);
+}
+
+#[test]
+#[should_panic]
+fn test_zero_step_is_rejected() {
+    FixedTimestep::new(0.0);
+}
+
+#[test]
+fn test_stepped_update_does_not_depend_on_the_frame_rate() {
+    // Bouncing, colliding circles that start with seeded random speeds.
+    let run = |frame_ms: f64| {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut state = DrawingState::new();
+        for i in 0..6 {
+            state.add_shape_with_rng(50.0 + i as f64 * 60.0, 100.0, &mut rng);
+        }
+        let config = PhysicsConfig {
+            max_initial_speed: 300.0,
+            ..PhysicsConfig::default()
+        };
+        state.physics = Some(PhysicsWorld::with_seed(config, 42));
+        state.set_physics_bounds(400.0, 300.0);
+
+        let mut timestep = FixedTimestep::new(0.01);
+        let mut steps = 0;
+        let mut timestamp = 0.0;
+        while timestamp <= 1000.0 {
+            for _ in 0..timestep.tick(timestamp) {
+                state.update(timestep.step());
+                steps += 1;
+            }
+            timestamp += frame_ms;
+        }
+        (steps, state.shapes)
+    };
+
+    let (steps, shapes) = run(10.0);
+    assert_eq!(steps, 100);
+    assert!(
+        shapes.iter().all(|shape| shape.y != 100.0),
+        "Every circle should have moved"
+    );
+    for frame_ms in [20.0, 25.0, 40.0, 50.0] {
+        assert_eq!(
+            run(frame_ms),
+            (steps, shapes.clone()),
+            "{} ms frames should simulate the same steps",
+            frame_ms
+        );
+    }
+}
@@ -0,0 +1,39 @@
+// bench/browser-bench.mjs
+// @ts-check
+// Opens the benchmark page in headless Chromium and prints its results as
+// JSON on stdout. `cli bench --browser` serves the page and runs this script:
+//
+//     node bench/browser-bench.mjs http://127.0.0.1:<port>/bench.html
+import { chromium } from '@playwright/test';
+
+/** How long the whole page may take, in milliseconds. */
+const TIMEOUT_MS = 5 * 60 * 1000;
+
+const url = process.argv[2];
+if (!url) {
+    console.error('Usage: node bench/browser-bench.mjs <URL of bench.html>');
+    process.exit(2);
+}
+
+const browser = await chromium.launch();
+try {
+    const page = await browser.newPage();
+    page.on('console', (message) => console.error(`[page] ${message.text()}`));
+    page.on('pageerror', (error) => console.error(`[page] ${error.message}`));
+
+    await page.goto(url);
+    const handle = await page.waitForFunction(
+        () => window.benchResults ?? (window.benchError && { error: window.benchError }),
+        undefined,
+        { timeout: TIMEOUT_MS, polling: 250 },
+    );
+    const results = await handle.jsonValue();
+    if (!Array.isArray(results)) {
+        console.error(`Benchmarks failed: ${results.error}`);
+        process.exitCode = 1;
+    } else {
+        console.log(JSON.stringify({ userAgent: await page.evaluate(() => navigator.userAgent), results }));
+    }
+} finally {
+    await browser.close();
+}
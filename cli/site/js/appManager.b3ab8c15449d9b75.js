@@ -0,0 +1,3 @@
+import init from '../pkg/app.180079e06d45d60d.js';
+const next = await import(`../pkg/app.180079e06d45d60d.js?v=${version}`);
+const url = 'https://example.com/app.js';
@@ -0,0 +1 @@
+import { initializeApp } from './js/appManager.b3ab8c15449d9b75.js';
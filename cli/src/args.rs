@@ -1,11 +1,30 @@
+use crate::build::BuildBackend;
+use crate::optimize::{ByteSize, WasmOptLevel};
+use crate::proxy::ProxyRule;
 use clap::builder::TypedValueParser;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+use warp::http::{HeaderName, HeaderValue};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about = "Project Development CLI", long_about = None)]
 pub struct Cli {
     #[clap(subcommand)]
     pub command: Commands,
+    #[clap(
+        long,
+        global = true,
+        value_name = "DIR",
+        help = "Workspace root to work in [default: found from the current directory]"
+    )]
+    pub workspace: Option<PathBuf>,
+    #[clap(
+        long,
+        global = true,
+        help = "Print JSON events on stdout (one per line) instead of progress output"
+    )]
+    pub json: bool,
 }
 
 #[derive(Subcommand, Debug)]
@@ -14,22 +33,79 @@ pub enum Commands {
     Build {
         #[clap(long, help = "Build WASM in debug mode (faster, larger output)")]
         wasm_debug: bool,
+        #[clap(
+            long,
+            value_delimiter = ',',
+            help = "Cargo features for the WASM build, replacing build.features from project.toml"
+        )]
+        features: Option<Vec<String>>,
+        #[clap(
+            long,
+            value_enum,
+            help = "Build with wasm-pack or with cargo and wasm-bindgen [default: wasm-pack, or build.backend]"
+        )]
+        backend: Option<BuildBackend>,
+        #[clap(flatten)]
+        optimize: OptimizeArgs,
     },
     /// Serves the static directory
     Serve {
-        #[clap(short, long, default_value_t = 8080)]
-        port: u16,
-        #[clap(long, default_value = "0.0.0.0")]
-        host: String,
+        #[clap(flatten)]
+        server: ServerArgs,
+        #[clap(
+            long,
+            help = "Cache content-hashed assets as immutable, like a production deployment"
+        )]
+        prod_headers: bool,
     },
     /// Builds the WASM app and then serves the static directory
     Dev {
-        #[clap(short, long, default_value_t = 8080)]
-        port: u16,
-        #[clap(long, default_value = "0.0.0.0")]
-        host: String,
+        #[clap(flatten)]
+        server: ServerArgs,
         #[clap(long, help = "Build WASM in debug mode")]
         wasm_debug: bool,
+        #[clap(long, help = "Rebuild the WASM app when sources change")]
+        watch: bool,
+        #[clap(
+            long,
+            value_delimiter = ',',
+            help = "Cargo features for the WASM build, replacing build.features from project.toml"
+        )]
+        features: Option<Vec<String>>,
+        #[clap(
+            long,
+            value_enum,
+            help = "Build with wasm-pack or with cargo and wasm-bindgen [default: wasm-pack, or build.backend]"
+        )]
+        backend: Option<BuildBackend>,
+    },
+    /// Builds a release and assembles a deployable site with hashed, precompressed assets
+    Release {
+        #[clap(
+            long,
+            value_name = "DIR",
+            help = "Directory to write the site to [default: dist, or paths.dist from project.toml]"
+        )]
+        out_dir: Option<PathBuf>,
+        #[clap(
+            long,
+            help = "Package the existing WASM output instead of building a release"
+        )]
+        skip_build: bool,
+        #[clap(
+            long,
+            value_delimiter = ',',
+            help = "Cargo features for the WASM build, replacing build.features from project.toml"
+        )]
+        features: Option<Vec<String>>,
+        #[clap(
+            long,
+            value_enum,
+            help = "Build with wasm-pack or with cargo and wasm-bindgen [default: wasm-pack, or build.backend]"
+        )]
+        backend: Option<BuildBackend>,
+        #[clap(flatten)]
+        optimize: OptimizeArgs,
     },
     /// Runs tests for base (native) and/or app (WASM)
     Test {
@@ -46,8 +122,114 @@ pub enum Commands {
         browser: Option<String>,
         #[clap(long, help = "Run app tests in headless mode")]
         headless: bool,
+        #[clap(
+            long,
+            conflicts_with_all(&["base", "browser", "headless"]),
+            help = "Run the app tests that need no browser in Node, skipping those configured with run_in_browser"
+        )]
+        node: bool,
+        #[clap(
+            long,
+            value_name = "PATH",
+            requires = "headless",
+            help = "WebDriver binary for the browser, e.g. a specific chromedriver"
+        )]
+        driver_path: Option<PathBuf>,
+        #[clap(
+            long,
+            value_name = "FILE",
+            requires = "headless",
+            help = "JSON file of WebDriver capabilities to start the browser with"
+        )]
+        webdriver_json: Option<PathBuf>,
+        #[clap(
+            long = "browser-arg",
+            value_name = "ARG",
+            requires = "headless",
+            allow_hyphen_values = true,
+            help = "Command-line argument for the browser, e.g. --browser-arg=--lang=de (repeatable)"
+        )]
+        browser_args: Vec<String>,
+        #[clap(
+            long,
+            value_name = "DURATION",
+            value_parser = parse_duration,
+            help = "How long each app test may run, e.g. 60 or 2m [default: 20s]"
+        )]
+        timeout: Option<Duration>,
+        #[clap(
+            long,
+            value_name = "NAME",
+            help = "Only run the tests whose names contain NAME"
+        )]
+        filter: Option<String>,
+        #[clap(
+            long,
+            value_name = "N",
+            help = "Run a failed test suite again up to N times [default: 0, or test.retries]"
+        )]
+        retries: Option<u32>,
+        #[clap(
+            long,
+            value_name = "FILE",
+            help = "Write the test results to FILE as JUnit XML [default: test.junit]"
+        )]
+        junit: Option<PathBuf>,
+        #[clap(
+            long,
+            value_enum,
+            help = "Run app tests with wasm-pack or with cargo and wasm-bindgen-test-runner [default: wasm-pack, or build.backend]"
+        )]
+        backend: Option<BuildBackend>,
+        #[clap(
+            long,
+            conflicts_with = "keep_going",
+            help = "Stop the other test suites as soon as one fails [default, unless test.keep-going]"
+        )]
+        fail_fast: bool,
+        #[clap(long, help = "Run every test suite even after one fails")]
+        keep_going: bool,
+        #[clap(
+            short,
+            long,
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "How many test suites run at once [default: all, or test.jobs]"
+        )]
+        jobs: Option<u64>,
+        #[clap(
+            long,
+            conflicts_with = "app",
+            help = "Measure the coverage of the base tests, written to target/coverage"
+        )]
+        coverage: bool,
+        #[clap(
+            long,
+            value_name = "PERCENT",
+            requires = "coverage",
+            value_parser = parse_percent,
+            help = "Fail when less than PERCENT of the lines are covered [default: test.min-coverage]"
+        )]
+        min_coverage: Option<f64>,
+        #[clap(
+            long,
+            value_name = "TARGET",
+            conflicts_with_all(&["base", "app", "coverage", "node"]),
+            help = "Fuzz the base crate with this cargo-fuzz target instead of testing (repeatable)"
+        )]
+        fuzz: Vec<String>,
+        #[clap(
+            long,
+            value_name = "DURATION",
+            requires = "fuzz",
+            default_value = "60s",
+            value_parser = parse_duration,
+            help = "How long each fuzz target runs, e.g. 90, 30s, 5m or 1h"
+        )]
+        time: Duration,
     },
-    /// Runs Playwright End-to-End (E2E) tests
+    /// Checks the toolchain and environment the other commands need
+    Doctor,
+    /// Builds the app, serves it on a free port and runs the Playwright End-to-End (E2E) tests against it
     E2E {
         #[clap(long, help = "Run Playwright tests in UI mode")]
         ui: bool,
@@ -57,7 +239,213 @@ pub enum Commands {
         project: Option<String>,
         #[clap(long, help = "Run tests in headed mode", conflicts_with_all(&["ui", "debug"]))]
         headed: bool,
+        #[clap(
+            long,
+            help = "Test the existing WASM output instead of building it first"
+        )]
+        skip_build: bool,
+        #[clap(
+            long,
+            conflicts_with = "skip_build",
+            help = "Test a release build instead of a debug build"
+        )]
+        release: bool,
+        #[clap(
+            long,
+            value_enum,
+            conflicts_with = "skip_build",
+            help = "Build with wasm-pack or with cargo and wasm-bindgen [default: wasm-pack, or build.backend]"
+        )]
+        backend: Option<BuildBackend>,
         #[clap(last = true, help = "Pass additional arguments to Playwright CLI")]
         playwright_args: Vec<String>,
     },
+    /// Runs the benchmarks and compares them with a saved baseline
+    Bench {
+        #[clap(
+            long,
+            help = "Also measure rendering and wasm-JS calls in headless Chromium, using a release build"
+        )]
+        browser: bool,
+        #[clap(
+            long,
+            value_name = "NAME",
+            value_parser = parse_baseline_name,
+            help = "The saved results to compare with [default: main, or bench.baseline]"
+        )]
+        baseline: Option<String>,
+        #[clap(
+            long,
+            value_name = "NAME",
+            value_parser = parse_baseline_name,
+            help = "Save the results as a baseline for later runs"
+        )]
+        save_baseline: Option<String>,
+        #[clap(
+            long,
+            value_name = "PERCENT",
+            value_parser = parse_regression,
+            help = "Fail when a benchmark is this much slower than the baseline [default: 10, or bench.max-regression]"
+        )]
+        max_regression: Option<f64>,
+        #[clap(
+            long,
+            requires = "browser",
+            help = "Measure the existing WASM output instead of building it first"
+        )]
+        skip_build: bool,
+        #[clap(
+            long,
+            value_enum,
+            requires = "browser",
+            conflicts_with = "skip_build",
+            help = "Build with wasm-pack or with cargo and wasm-bindgen [default: wasm-pack, or build.backend]"
+        )]
+        backend: Option<BuildBackend>,
+    },
+}
+
+impl Commands {
+    /// The subcommand as typed, for the summary.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Commands::Build { .. } => "build",
+            Commands::Serve { .. } => "serve",
+            Commands::Dev { .. } => "dev",
+            Commands::Release { .. } => "release",
+            Commands::Test { .. } => "test",
+            Commands::Doctor => "doctor",
+            Commands::E2E { .. } => "e2e",
+            Commands::Bench { .. } => "bench",
+        }
+    }
+}
+
+/// Options shared by the commands that run the web server.
+#[derive(Args, Debug)]
+pub struct ServerArgs {
+    #[clap(short, long, help = "Port to listen on [default: 8080]")]
+    pub port: Option<u16>,
+    #[clap(long, help = "Address to listen on [default: 0.0.0.0]")]
+    pub host: Option<String>,
+    #[clap(long, help = "Serve over HTTPS with a cached self-signed certificate")]
+    pub https: bool,
+    #[clap(long, requires = "key", help = "PEM certificate to serve HTTPS with")]
+    pub cert: Option<PathBuf>,
+    #[clap(long, requires = "cert", help = "PEM private key for --cert")]
+    pub key: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Serve index.html for unknown paths without a file extension (client-side routing)"
+    )]
+    pub spa: bool,
+    #[clap(
+        long = "header",
+        value_name = "NAME: VALUE",
+        value_parser = parse_header,
+        help = "Add a header to every response (repeatable)"
+    )]
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    #[clap(
+        long,
+        help = "Send COOP/COEP headers so the page is cross-origin isolated (SharedArrayBuffer)"
+    )]
+    pub cross_origin_isolated: bool,
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Page to serve for unknown paths [default: 404.html in the static directory, if present]"
+    )]
+    pub not_found_page: Option<PathBuf>,
+    #[clap(
+        long,
+        value_name = "PATH=URL",
+        help = "Forward requests under PATH to a local server, e.g. /api=http://127.0.0.1:3000 (repeatable)"
+    )]
+    pub proxy: Vec<ProxyRule>,
+}
+
+/// Options for the optimization stage of release builds.
+#[derive(Args, Debug, Default)]
+pub struct OptimizeArgs {
+    #[clap(
+        long,
+        value_name = "LEVEL",
+        help = "wasm-opt level for release builds: off, 0-4, s or z [default: s, or build.wasm-opt]"
+    )]
+    pub wasm_opt: Option<WasmOptLevel>,
+    #[clap(
+        long,
+        value_name = "SIZE",
+        help = "Fail the release build if the .wasm file is larger than this, e.g. 500KB or 1.5MiB"
+    )]
+    pub size_budget: Option<ByteSize>,
+}
+
+fn parse_header(s: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected 'Name: value', got '{}'", s))?;
+    let name = HeaderName::from_bytes(name.trim().as_bytes())
+        .map_err(|_| format!("invalid header name '{}'", name.trim()))?;
+    let value = HeaderValue::from_str(value.trim())
+        .map_err(|_| format!("invalid value for header '{}'", name))?;
+    Ok((name, value))
+}
+
+fn parse_percent(value: &str) -> Result<f64, String> {
+    let percent: f64 = value
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+    if (0.0..=100.0).contains(&percent) {
+        Ok(percent)
+    } else {
+        Err(format!("{} is not between 0 and 100", percent))
+    }
+}
+
+fn parse_regression(value: &str) -> Result<f64, String> {
+    let percent: f64 = value
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+    if percent >= 0.0 {
+        Ok(percent)
+    } else {
+        Err(format!("{} is not a percentage of at least 0", percent))
+    }
+}
+
+fn parse_baseline_name(value: &str) -> Result<String, String> {
+    crate::bench::check_baseline_name(value)?;
+    Ok(value.to_string())
+}
+
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a duration like 30s, 5m or 1h", value))?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        _ => {
+            return Err(format!(
+                "unknown unit '{}' in '{}', use s, m or h",
+                unit, value
+            ))
+        }
+    };
+    let seconds = number
+        .checked_mul(unit_seconds)
+        .ok_or_else(|| format!("'{}' is too long", value))?;
+    if seconds == 0 {
+        return Err("the duration must be at least one second".to_string());
+    }
+    Ok(Duration::from_secs(seconds))
 }
@@ -0,0 +1,292 @@
+use crate::report::{self, progress};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
+
+/// The script that drives `bench.html` in a headless browser.
+const BROWSER_BENCH_SCRIPT: &str = "bench/browser-bench.mjs";
+
+/// The browser harness in the static directory. It is only served by `cli
+/// bench --browser` and stays out of releases.
+pub const BROWSER_HARNESS_FILES: &[&str] = &["bench.html", "js/bench.js"];
+
+/// The median time of one iteration of each benchmark, in nanoseconds, by id.
+pub type BenchResults = BTreeMap<String, f64>;
+
+/// How `cli bench` runs and judges the benchmarks.
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Also run the browser harness against a release build.
+    pub browser: bool,
+    /// The saved results to compare with.
+    pub baseline: String,
+    /// Save the results under this name afterwards.
+    pub save_baseline: Option<String>,
+    /// Fail when a benchmark is slower than the baseline by more than this percentage.
+    pub max_regression: f64,
+}
+
+/// Baseline names become file names, so they are kept simple.
+pub fn check_baseline_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "must be a name of letters, digits, '-', '_' and '.', got '{}'",
+            name
+        ))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Baseline {
+    /// Seconds since the Unix epoch when the baseline was saved.
+    saved_at: u64,
+    results: BenchResults,
+}
+
+fn baseline_path(baselines_dir: &Path, name: &str) -> PathBuf {
+    baselines_dir.join(format!("{}.json", name))
+}
+
+/// The baseline saved as `name`, if there is one.
+pub fn load_baseline(baselines_dir: &Path, name: &str) -> Result<Option<BenchResults>> {
+    let path = baseline_path(baselines_dir, name);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read '{}'", path.display()))?;
+    let baseline: Baseline = serde_json::from_str(&text)
+        .with_context(|| format!("Failed to parse the baseline '{}'", path.display()))?;
+    Ok(Some(baseline.results))
+}
+
+/// Saves `results` as the baseline `name`, replacing an earlier one.
+pub fn save_baseline(baselines_dir: &Path, name: &str, results: &BenchResults) -> Result<PathBuf> {
+    fs::create_dir_all(baselines_dir)
+        .with_context(|| format!("Failed to create '{}'", baselines_dir.display()))?;
+    let baseline = Baseline {
+        saved_at: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default(),
+        results: results.clone(),
+    };
+    let path = baseline_path(baselines_dir, name);
+    fs::write(&path, serde_json::to_string_pretty(&baseline)?)
+        .with_context(|| format!("Failed to write '{}'", path.display()))?;
+    Ok(path)
+}
+
+/// When each benchmark under `criterion_dir` last wrote its results.
+pub type CriterionSnapshot = HashMap<PathBuf, SystemTime>;
+
+/// Records the results criterion has written so far, so the next
+/// `read_criterion_results` returns only the benchmarks that run in between.
+pub fn criterion_snapshot(criterion_dir: &Path) -> CriterionSnapshot {
+    let mut snapshot = CriterionSnapshot::new();
+    let mut dirs = vec![criterion_dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if !path.is_dir() {
+                continue;
+            }
+            // Each benchmark keeps its latest results in `<id>/new`.
+            let latest = path.join("new");
+            match fs::metadata(latest.join("benchmark.json")).and_then(|m| m.modified()) {
+                Ok(modified) => {
+                    snapshot.insert(latest, modified);
+                }
+                Err(_) => dirs.push(path),
+            }
+        }
+    }
+    snapshot
+}
+
+/// Reads the results of the benchmarks that ran since `before` was taken.
+pub fn read_criterion_results(
+    criterion_dir: &Path,
+    before: &CriterionSnapshot,
+) -> Result<BenchResults> {
+    let mut results = BenchResults::new();
+    for (dir, modified) in criterion_snapshot(criterion_dir) {
+        if before.get(&dir) != Some(&modified) {
+            let (id, time_ns) = read_criterion_benchmark(&dir)?;
+            results.insert(id, time_ns);
+        }
+    }
+    if results.is_empty() {
+        bail!(
+            "The base benchmarks wrote no criterion results to '{}'",
+            criterion_dir.display()
+        );
+    }
+    Ok(results)
+}
+
+/// The id and median time of the benchmark whose latest results are in `dir`.
+fn read_criterion_benchmark(dir: &Path) -> Result<(String, f64)> {
+    let read = |file: &str| -> Result<serde_json::Value> {
+        let path = dir.join(file);
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Failed to parse '{}'", path.display()))
+    };
+    let benchmark = read("benchmark.json")?;
+    let estimates = read("estimates.json")?;
+    let id = benchmark["full_id"].as_str();
+    let time_ns = estimates["median"]["point_estimate"].as_f64();
+    match (id, time_ns) {
+        (Some(id), Some(time_ns)) => Ok((id.to_string(), time_ns)),
+        _ => bail!("Unexpected criterion results in '{}'", dir.display()),
+    }
+}
+
+/// Opens `bench.html` from `base_url` in headless Chromium through
+/// `bench/browser-bench.mjs` and returns its results.
+pub fn run_browser_benchmarks(workspace_root: &Path, base_url: &str) -> Result<BenchResults> {
+    let script = workspace_root.join(BROWSER_BENCH_SCRIPT);
+    if !script.is_file() {
+        bail!(
+            "The browser benchmark script '{}' is missing",
+            script.display()
+        );
+    }
+    let url = format!("{}/bench.html", base_url);
+    progress!("Executing: node {} {}", BROWSER_BENCH_SCRIPT, url);
+    // The page's log goes to stderr; stdout carries only the results.
+    let output = Command::new("node")
+        .arg(&script)
+        .arg(&url)
+        .current_dir(workspace_root)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .context("Failed to run node. Is Node.js installed?")?;
+    if !output.status.success() {
+        bail!("The browser benchmarks failed with {}", output.status);
+    }
+
+    #[derive(Deserialize)]
+    struct Output {
+        results: Vec<BrowserResult>,
+    }
+    #[derive(Deserialize)]
+    struct BrowserResult {
+        id: String,
+        time_ns: f64,
+    }
+    let parsed: Output = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse the results of the browser benchmarks")?;
+    Ok(parsed
+        .results
+        .into_iter()
+        .map(|result| (result.id, result.time_ns))
+        .collect())
+}
+
+/// How one benchmark changed against the baseline.
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    pub id: String,
+    pub time_ns: f64,
+    pub baseline_ns: Option<f64>,
+    /// Positive when slower than the baseline.
+    pub change_percent: Option<f64>,
+    pub regressed: bool,
+}
+
+pub fn compare(
+    results: &BenchResults,
+    baseline: Option<&BenchResults>,
+    max_regression: f64,
+) -> Vec<Comparison> {
+    results
+        .iter()
+        .map(|(id, &time_ns)| {
+            let baseline_ns = baseline.and_then(|baseline| baseline.get(id)).copied();
+            let change_percent = baseline_ns
+                .filter(|&baseline_ns| baseline_ns > 0.0)
+                .map(|baseline_ns| (time_ns - baseline_ns) / baseline_ns * 100.0);
+            Comparison {
+                id: id.clone(),
+                time_ns,
+                baseline_ns,
+                change_percent,
+                regressed: change_percent.is_some_and(|change| change > max_regression),
+            }
+        })
+        .collect()
+}
+
+/// Prints the results next to the baseline and emits them as a `bench` event.
+pub fn print_comparison(comparisons: &[Comparison], baseline_name: &str, max_regression: f64) {
+    let width = comparisons
+        .iter()
+        .map(|comparison| comparison.id.len())
+        .max()
+        .unwrap_or_default()
+        .max("benchmark".len());
+    progress!(
+        "\n  {:<width$}  {:>12}  {:>12}  {:>8}",
+        "benchmark",
+        "time",
+        baseline_name,
+        "change",
+        width = width
+    );
+    for comparison in comparisons {
+        let baseline = comparison
+            .baseline_ns
+            .map(format_time)
+            .unwrap_or_else(|| "-".to_string());
+        let change = match comparison.change_percent {
+            Some(change) if comparison.regressed => format!("{:+.1}% !", change),
+            Some(change) => format!("{:+.1}%", change),
+            None => "new".to_string(),
+        };
+        progress!(
+            "  {:<width$}  {:>12}  {:>12}  {:>8}",
+            comparison.id,
+            format_time(comparison.time_ns),
+            baseline,
+            change,
+            width = width
+        );
+    }
+    report::emit_event(
+        "bench",
+        serde_json::json!({
+            "baseline": baseline_name,
+            "max_regression": max_regression,
+            "results": comparisons,
+        }),
+    );
+}
+
+/// A time in nanoseconds in the unit that suits it, e.g. `1.33 µs`.
+fn format_time(ns: f64) -> String {
+    if ns >= 1e9 {
+        format!("{:.2} s", ns / 1e9)
+    } else if ns >= 1e6 {
+        format!("{:.2} ms", ns / 1e6)
+    } else if ns >= 1e3 {
+        format!("{:.2} µs", ns / 1e3)
+    } else {
+        format!("{:.1} ns", ns)
+    }
+}
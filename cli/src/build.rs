@@ -1,22 +1,74 @@
+use crate::optimize::{optimize_wasm, WasmOptLevel};
 use crate::process_runner::{run_command, CommandConfig};
+use crate::report::{self, progress};
 use anyhow::{bail, Context, Result};
-use std::path::Path;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-pub fn build_wasm(crate_path: &Path, out_dir: &Path, debug_mode: bool) -> Result<()> {
-    let build_type = if debug_mode { "debug" } else { "release" };
-    println!(
+/// The WASM target both backends compile for.
+pub const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+/// The tool chain that turns the app crate into a `pkg` directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum BuildBackend {
+    /// `wasm-pack build`.
+    #[default]
+    WasmPack,
+    /// `cargo build` for the WASM target, then the `wasm-bindgen` CLI.
+    Cargo,
+}
+
+/// How `build_wasm` builds the app crate.
+#[derive(Debug, Clone)]
+pub struct BuildOptions {
+    /// Build in debug mode (faster, larger output).
+    pub debug: bool,
+    /// The file name of the generated `.js` and `_bg.wasm` files.
+    pub out_name: String,
+    /// Cargo features to enable.
+    pub features: Vec<String>,
+    /// Which tools run the build.
+    pub backend: BuildBackend,
+    /// The `wasm-opt` level for release builds.
+    pub wasm_opt: WasmOptLevel,
+    /// Release builds whose `_bg.wasm` is larger than this many bytes fail.
+    pub size_budget: Option<u64>,
+    /// Where the sizes of the last release build are kept for comparison.
+    pub size_history: PathBuf,
+}
+
+pub fn build_wasm(crate_path: &Path, out_dir: &Path, options: &BuildOptions) -> Result<()> {
+    let build_type = if options.debug { "debug" } else { "release" };
+    progress!(
         "Building WASM application in {} mode from: {}",
         build_type,
         crate_path.display()
     );
-    println!("Output directory: {}", out_dir.display());
+    progress!("Output directory: {}", out_dir.display());
+
+    match options.backend {
+        BuildBackend::WasmPack => build_with_wasm_pack(crate_path, out_dir, options)?,
+        BuildBackend::Cargo => build_with_cargo(crate_path, out_dir, options)?,
+    }
+
+    if !options.debug {
+        let wasm_path = out_dir.join(format!("{}_bg.wasm", options.out_name));
+        report::stage("optimize", || optimize_wasm(&wasm_path, options))?;
+    }
+
+    progress!("\nBuild successful! Output is in {}", out_dir.display());
+    Ok(())
+}
 
+fn build_with_wasm_pack(crate_path: &Path, out_dir: &Path, options: &BuildOptions) -> Result<()> {
     if std::process::Command::new("wasm-pack")
         .arg("--version")
         .output()
         .is_err()
     {
-        bail!("wasm-pack not found. Please install it: https://rustwasm.github.io/wasm-pack/installer/");
+        bail!("wasm-pack not found. Please install it: https://rustwasm.github.io/wasm-pack/installer/ or use --backend cargo");
     }
 
     let mut args_vec: Vec<&str> = vec![
@@ -28,30 +80,229 @@ pub fn build_wasm(crate_path: &Path, out_dir: &Path, debug_mode: bool) -> Result
             .to_str()
             .context("Output dir path is not valid UTF-8")?,
         "--out-name",
-        "app",
+        &options.out_name,
     ];
 
-    let features_string_storage: String;
-
-    if debug_mode {
+    // Release builds are optimized by `optimize_wasm` instead of wasm-pack's
+    // bundled wasm-opt, so the level is ours to choose.
+    if options.debug {
         args_vec.push("--dev");
     } else {
         args_vec.push("--release");
-        features_string_storage = "default wee_alloc".to_string();
+        args_vec.push("--no-opt");
+    }
+
+    let features_string = options.features.join(" ");
+    if !options.features.is_empty() {
         args_vec.push("--features");
-        args_vec.push(&features_string_storage);
+        args_vec.push(&features_string);
     }
 
     let cmd_config = CommandConfig {
+        stage: "build app (wasm-pack)",
         program_name: "wasm-pack",
         args: &args_vec,
         current_dir: Some(crate_path),
         env_vars: None,
         inherit_stdio: true,
+        capture: true,
+        cargo_diagnostics: false,
     };
 
-    run_command(cmd_config).context("wasm-pack build failed")?;
+    run_command(cmd_config).context("wasm-pack build failed")
+}
 
-    println!("\nBuild successful! Output is in {}", out_dir.display());
+/// Does what `wasm-pack build --target web` does: compiles the library for
+/// the WASM target and generates the JS bindings next to the `.wasm` file.
+fn build_with_cargo(crate_path: &Path, out_dir: &Path, options: &BuildOptions) -> Result<()> {
+    let metadata = CrateMetadata::read(crate_path)?;
+    check_wasm_bindgen_cli("wasm-bindgen", &metadata.workspace_root)?;
+
+    let mut args_vec: Vec<&str> = vec!["build", "--lib", "--target", WASM_TARGET];
+    if !options.debug {
+        args_vec.push("--release");
+    }
+    let features_string = options.features.join(",");
+    if !options.features.is_empty() {
+        args_vec.push("--features");
+        args_vec.push(&features_string);
+    }
+    let cmd_config = CommandConfig {
+        stage: "build app (cargo)",
+        program_name: "cargo",
+        args: &args_vec,
+        current_dir: Some(crate_path),
+        env_vars: None,
+        inherit_stdio: true,
+        capture: true,
+        cargo_diagnostics: true,
+    };
+    run_command(cmd_config).with_context(|| {
+        format!(
+            "cargo build failed; if the {} target is missing, run: rustup target add {}",
+            WASM_TARGET, WASM_TARGET
+        )
+    })?;
+
+    let profile_dir = if options.debug { "debug" } else { "release" };
+    let wasm_file = metadata
+        .target_directory
+        .join(WASM_TARGET)
+        .join(profile_dir)
+        .join(format!("{}.wasm", metadata.lib_name));
+    let wasm_file = wasm_file
+        .to_str()
+        .context("Target dir path is not valid UTF-8")?;
+    let mut args_vec: Vec<&str> = vec![
+        wasm_file,
+        "--target",
+        "web",
+        "--out-dir",
+        out_dir
+            .to_str()
+            .context("Output dir path is not valid UTF-8")?,
+        "--out-name",
+        &options.out_name,
+    ];
+    if options.debug {
+        args_vec.push("--debug");
+    }
+    let cmd_config = CommandConfig {
+        stage: "wasm-bindgen",
+        program_name: "wasm-bindgen",
+        args: &args_vec,
+        current_dir: Some(crate_path),
+        env_vars: None,
+        inherit_stdio: true,
+        capture: true,
+        cargo_diagnostics: false,
+    };
+    run_command(cmd_config).context("wasm-bindgen failed")?;
+
+    // wasm-pack keeps its output out of version control the same way.
+    fs::write(out_dir.join(".gitignore"), "*\n")?;
+    Ok(())
+}
+
+/// What the cargo backend needs to know about the app crate.
+pub struct CrateMetadata {
+    /// The crate's library name, which names the compiled `.wasm` file.
+    pub lib_name: String,
+    pub target_directory: PathBuf,
+    pub workspace_root: PathBuf,
+}
+
+impl CrateMetadata {
+    pub fn read(crate_path: &Path) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Metadata {
+            packages: Vec<Package>,
+            target_directory: PathBuf,
+            workspace_root: PathBuf,
+        }
+        #[derive(Deserialize)]
+        struct Package {
+            manifest_path: PathBuf,
+            targets: Vec<Target>,
+        }
+        #[derive(Deserialize)]
+        struct Target {
+            name: String,
+            kind: Vec<String>,
+        }
+
+        let output = std::process::Command::new("cargo")
+            .args(["metadata", "--format-version", "1", "--no-deps"])
+            .current_dir(crate_path)
+            .output()
+            .context("Failed to run cargo metadata")?;
+        if !output.status.success() {
+            bail!(
+                "cargo metadata failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let metadata: Metadata = serde_json::from_slice(&output.stdout)
+            .context("Failed to parse the output of cargo metadata")?;
+
+        let manifest_path = crate_path.join("Cargo.toml").canonicalize()?;
+        let lib_name = metadata
+            .packages
+            .iter()
+            .filter(|p| p.manifest_path.canonicalize().ok().as_ref() == Some(&manifest_path))
+            .flat_map(|p| &p.targets)
+            .find(|t| t.kind.iter().any(|k| k == "cdylib"))
+            .map(|t| t.name.replace('-', "_"))
+            .with_context(|| {
+                format!(
+                    "'{}' has no library with crate-type \"cdylib\" to build for WASM",
+                    crate_path.display()
+                )
+            })?;
+        Ok(Self {
+            lib_name,
+            target_directory: metadata.target_directory,
+            workspace_root: metadata.workspace_root,
+        })
+    }
+}
+
+/// The `wasm-bindgen` version locked in the workspace's `Cargo.lock`.
+pub fn locked_wasm_bindgen_version(workspace_root: &Path) -> Result<String> {
+    let lock_path = workspace_root.join("Cargo.lock");
+    let text = fs::read_to_string(&lock_path)
+        .with_context(|| format!("Failed to read '{}'", lock_path.display()))?;
+    let lock: toml::Table = toml::from_str(&text)
+        .with_context(|| format!("Failed to parse '{}'", lock_path.display()))?;
+    lock.get("package")
+        .and_then(|packages| packages.as_array())
+        .into_iter()
+        .flatten()
+        .find(|package| package.get("name").and_then(|n| n.as_str()) == Some("wasm-bindgen"))
+        .and_then(|package| package.get("version"))
+        .and_then(|version| version.as_str())
+        .map(str::to_string)
+        .with_context(|| format!("wasm-bindgen is not in '{}'", lock_path.display()))
+}
+
+/// Checks that `program` (`wasm-bindgen` or `wasm-bindgen-test-runner`) is
+/// installed in the version the crate links against. The generated JS and
+/// the compiled module only work together if the two match exactly.
+pub fn check_wasm_bindgen_cli(program: &str, workspace_root: &Path) -> Result<()> {
+    let lock_path = workspace_root.join("Cargo.lock");
+    // The lock file is not checked in; cargo writes it on the first build.
+    if !lock_path.is_file() {
+        let cmd_config = CommandConfig {
+            stage: "generate Cargo.lock",
+            program_name: "cargo",
+            args: &["generate-lockfile"],
+            current_dir: Some(workspace_root),
+            env_vars: None,
+            inherit_stdio: true,
+            capture: true,
+            cargo_diagnostics: false,
+        };
+        run_command(cmd_config).context("Failed to create Cargo.lock")?;
+    }
+    let locked = locked_wasm_bindgen_version(workspace_root)?;
+    let install_hint = format!("cargo install -f wasm-bindgen-cli --version {}", locked);
+    let output = std::process::Command::new(program)
+        .arg("--version")
+        .output()
+        .map_err(|_| anyhow::anyhow!("{} not found. Install it with: {}", program, install_hint))?;
+    let installed = String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+    if installed != locked {
+        bail!(
+            "{} {} is installed, but Cargo.lock uses wasm-bindgen {}. Install the matching version with: {}",
+            program,
+            installed,
+            locked,
+            install_hint
+        );
+    }
     Ok(())
 }
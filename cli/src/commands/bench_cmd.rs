@@ -0,0 +1,106 @@
+use crate::bench::{self, BenchOptions, BenchResults, Comparison};
+use crate::build::{build_wasm, BuildOptions};
+use crate::commands::e2e_cmd::check_build_output;
+use crate::paths::ProjectPaths;
+use crate::process_runner::{run_command, CommandConfig};
+use crate::report::{self, progress};
+use crate::server::{LocalServer, ServeOptions};
+use anyhow::{bail, Result};
+
+/// Runs the base benchmarks, and the browser ones with `options.browser`,
+/// compares them with the baseline and saves them if asked to and nothing
+/// regressed. The browser benchmarks build the app first unless `build` is
+/// `None`.
+pub fn handle_bench_command(
+    paths: &ProjectPaths,
+    out_name: &str,
+    build: Option<&BuildOptions>,
+    serve: &ServeOptions,
+    options: &BenchOptions,
+) -> Result<()> {
+    let before = bench::criterion_snapshot(&paths.criterion_dir);
+    run_command(CommandConfig {
+        stage: "base benchmarks",
+        program_name: "cargo",
+        args: &["bench"],
+        current_dir: Some(&paths.base),
+        env_vars: None,
+        inherit_stdio: true,
+        capture: true,
+        cargo_diagnostics: true,
+    })?;
+    let mut results = bench::read_criterion_results(&paths.criterion_dir, &before)?;
+
+    if options.browser {
+        match build {
+            Some(build) => build_wasm(&paths.app, &paths.static_pkg_dir, build)?,
+            None => check_build_output(&paths.static_pkg_dir, out_name)?,
+        }
+        let browser_results: BenchResults = report::stage("browser benchmarks", || {
+            let server = LocalServer::start(&paths.static_dir, serve)?;
+            bench::run_browser_benchmarks(&paths.workspace_root, server.url())
+        })?;
+        results.extend(browser_results);
+    }
+
+    let baseline = bench::load_baseline(&paths.bench_baselines_dir, &options.baseline)?;
+    let comparisons = bench::compare(&results, baseline.as_ref(), options.max_regression);
+    bench::print_comparison(&comparisons, &options.baseline, options.max_regression);
+    if baseline.is_none() && options.save_baseline.is_none() {
+        progress!(
+            "\nNo baseline '{}' to compare with yet. Save one with --save-baseline {}.",
+            options.baseline,
+            options.baseline
+        );
+    }
+
+    // Compare first, so that a regressed run never replaces a good baseline.
+    if baseline.is_some() {
+        if let Err(error) = check_regressions(&comparisons, options) {
+            if let Some(name) = &options.save_baseline {
+                progress!("\nNot saving the results as baseline '{}'.", name);
+            }
+            return Err(error);
+        }
+    }
+
+    if let Some(name) = &options.save_baseline {
+        let path = bench::save_baseline(&paths.bench_baselines_dir, name, &results)?;
+        progress!(
+            "\nSaved the results as baseline '{}' in '{}'",
+            name,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+fn check_regressions(comparisons: &[Comparison], options: &BenchOptions) -> Result<()> {
+    report::stage("baseline comparison", || {
+        let regressed: Vec<String> = comparisons
+            .iter()
+            .filter(|comparison| comparison.regressed)
+            .map(|comparison| {
+                format!(
+                    "{} ({:+.1}%)",
+                    comparison.id,
+                    comparison.change_percent.unwrap_or_default()
+                )
+            })
+            .collect();
+        match regressed.len() {
+            0 => Ok(()),
+            count => bail!(
+                "{} slower than baseline '{}' by more than {}%: {}",
+                if count == 1 {
+                    "1 benchmark is".to_string()
+                } else {
+                    format!("{} benchmarks are", count)
+                },
+                options.baseline,
+                options.max_regression,
+                regressed.join(", ")
+            ),
+        }
+    })
+}
@@ -1,11 +1,11 @@
-use crate::build::build_wasm;
+use crate::build::{build_wasm, BuildOptions};
 use anyhow::Result;
 use std::path::Path;
 
 pub fn handle_build_command(
     app_path: &Path,
     static_pkg_dir_path: &Path,
-    wasm_debug: bool,
+    options: &BuildOptions,
 ) -> Result<()> {
-    build_wasm(app_path, static_pkg_dir_path, wasm_debug)
+    build_wasm(app_path, static_pkg_dir_path, options)
 }
@@ -1,16 +1,93 @@
-use crate::build::build_wasm;
-use crate::server::serve_static;
+use crate::build::{build_wasm, BuildOptions};
+use crate::live_reload::LiveReload;
+use crate::paths::ProjectPaths;
+use crate::report::progress;
+use crate::server::{serve_static, ServeOptions};
+use crate::watcher::{watch, IgnoreRules, SourceKind, WatchTarget, DEFAULT_DEBOUNCE};
 use anyhow::Result;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::thread;
 
 pub fn handle_dev_command(
-    app_path: &Path,
-    static_dir_path: &Path,
-    static_pkg_dir_path: &Path,
+    paths: &ProjectPaths,
     port: u16,
     host: &str,
-    wasm_debug: bool,
+    options: &ServeOptions,
+    build: &BuildOptions,
+    watch_sources: bool,
 ) -> Result<()> {
-    build_wasm(app_path, static_pkg_dir_path, wasm_debug)?;
-    serve_static(static_dir_path, port, host)
+    let app_path = &paths.app;
+    let static_dir_path = &paths.static_dir;
+    let static_pkg_dir_path = &paths.static_pkg_dir;
+
+    if !watch_sources {
+        build_wasm(app_path, static_pkg_dir_path, build)?;
+        return serve_static(static_dir_path, port, host, options, None);
+    }
+
+    // In watch mode a failing build must not end the session: the error is
+    // printed, shown in the browser, and the next change triggers another attempt.
+    let live_reload = LiveReload::new();
+    if let Err(e) = build_wasm(app_path, static_pkg_dir_path, build) {
+        eprintln!("\nInitial build failed: {:#}\nWaiting for changes...", e);
+        live_reload.build_failed(format!("{:#}", e));
+    }
+
+    let watched_static_dir = canonical(static_dir_path);
+    let targets = vec![
+        WatchTarget {
+            path: canonical(&app_path.join("src")),
+            kind: SourceKind::Rust,
+        },
+        WatchTarget {
+            path: canonical(&paths.base.join("src")),
+            kind: SourceKind::Rust,
+        },
+        WatchTarget {
+            path: watched_static_dir.clone(),
+            kind: SourceKind::Static,
+        },
+    ];
+    let ignore = IgnoreRules {
+        dirs: vec![canonical(static_pkg_dir_path)],
+    };
+    let app_path = app_path.clone();
+    let static_pkg_dir_path = static_pkg_dir_path.clone();
+    let build = build.clone();
+    let notifier = live_reload.clone();
+
+    thread::spawn(move || {
+        let result = watch(&targets, &ignore, DEFAULT_DEBOUNCE, |changes| {
+            for path in changes.rust.iter().chain(&changes.static_files) {
+                progress!("Changed: {}", path.display());
+            }
+            if changes.rust.is_empty() {
+                let paths: Vec<PathBuf> = changes.static_files.iter().cloned().collect();
+                notifier.static_files_changed(&watched_static_dir, &paths);
+                return;
+            }
+            progress!("\nRust sources changed, rebuilding...");
+            match build_wasm(&app_path, &static_pkg_dir_path, &build) {
+                Ok(()) => {
+                    progress!("Rebuild finished. Waiting for changes...");
+                    notifier.build_succeeded();
+                }
+                Err(e) => {
+                    eprintln!("\nRebuild failed: {:#}\nWaiting for changes...", e);
+                    notifier.build_failed(format!("{:#}", e));
+                }
+            }
+        });
+        if let Err(e) = result {
+            eprintln!("File watching stopped: {:#}", e);
+        }
+    });
+
+    serve_static(static_dir_path, port, host, options, Some(&live_reload))
+}
+
+/// Resolves symlinks so paths match those reported by the OS watcher.
+/// Falls back to the path as given if it does not exist yet.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
 }
@@ -0,0 +1,49 @@
+use crate::config::ProjectConfig;
+use crate::doctor::{run_checks, Status};
+use crate::report;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+pub fn handle_doctor_command(workspace_root: &Path, config: &Result<ProjectConfig>) -> Result<()> {
+    let checks = run_checks(workspace_root, config);
+    let count = |status: Status| checks.iter().filter(|c| c.status == status).count();
+    let failed = count(Status::Fail);
+
+    if report::json_output() {
+        report::emit_event(
+            "doctor",
+            serde_json::json!({
+                "checks": checks,
+                "counts": {
+                    "pass": count(Status::Pass),
+                    "warn": count(Status::Warn),
+                    "fail": failed,
+                },
+            }),
+        );
+    } else {
+        println!();
+        for check in &checks {
+            println!(
+                "[{}] {}: {}",
+                check.status.label(),
+                check.name,
+                check.message
+            );
+            if let Some(fix) = &check.fix {
+                println!("       fix: {}", fix);
+            }
+        }
+        println!(
+            "\n{} passed, {} warnings, {} failed",
+            count(Status::Pass),
+            count(Status::Warn),
+            failed
+        );
+    }
+
+    if failed > 0 {
+        bail!("{} check(s) failed", failed);
+    }
+    Ok(())
+}
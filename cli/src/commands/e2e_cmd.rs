@@ -1,49 +1,113 @@
+use crate::build::{build_wasm, BuildOptions};
+use crate::paths::ProjectPaths;
 use crate::process_runner::{run_command, CommandConfig};
-use anyhow::{Context, Result};
+use crate::report::{self, progress};
+use crate::server::{LocalServer, ServeOptions};
+use anyhow::{bail, Context, Result};
 use std::collections::HashMap;
 use std::path::Path;
 
+/// Playwright takes the address of the server from this variable; see
+/// `playwright.config.js`.
+const BASE_URL_VAR: &str = "E2E_BASE_URL";
+
+/// How to run Playwright.
+#[derive(Debug, Default)]
+pub struct E2eOptions {
+    pub ui_mode: bool,
+    pub debug_mode: bool,
+    pub headed_mode: bool,
+    pub project: Option<String>,
+    pub additional_args: Vec<String>,
+}
+
+/// Builds the app unless `build` is `None`, serves the static directory on
+/// a free port of 127.0.0.1 and runs Playwright against it.
 pub fn handle_e2e_command(
-    workspace_root: &Path,
-    ui_mode: bool,
-    debug_mode: bool,
-    headed_mode: bool,
-    project: Option<String>,
-    additional_args: Vec<String>,
+    paths: &ProjectPaths,
+    out_name: &str,
+    build: Option<&BuildOptions>,
+    serve: &ServeOptions,
+    options: &E2eOptions,
 ) -> Result<()> {
-    println!("\nRunning Playwright E2E tests...");
+    match build {
+        Some(build) => build_wasm(&paths.app, &paths.static_pkg_dir, build)?,
+        None => check_build_output(&paths.static_pkg_dir, out_name)?,
+    }
+
+    let server = LocalServer::start(&paths.static_dir, serve)?;
+    let base_url = server.url().to_string();
+
+    progress!(
+        "Serving '{}' for the E2E tests at {}",
+        paths.static_dir.display(),
+        base_url
+    );
+    report::emit_event(
+        "listening",
+        serde_json::json!({
+            "url": base_url,
+            "static_dir": paths.static_dir,
+        }),
+    );
+
+    let result = run_playwright(&paths.workspace_root, options, &base_url);
+    drop(server);
+    result?;
+
+    progress!("\nPlaywright E2E tests passed successfully!");
+    Ok(())
+}
+
+pub(crate) fn check_build_output(static_pkg_dir_path: &Path, out_name: &str) -> Result<()> {
+    for file in [format!("{}.js", out_name), format!("{}_bg.wasm", out_name)] {
+        if !static_pkg_dir_path.join(&file).is_file() {
+            bail!(
+                "'{}' not found in '{}'. Run 'cargo run --bin cli -- build' first, or drop --skip-build.",
+                file,
+                static_pkg_dir_path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_playwright(workspace_root: &Path, options: &E2eOptions, base_url: &str) -> Result<()> {
+    progress!("\nRunning Playwright E2E tests...");
 
     let mut pnpm_script_args: Vec<String> = vec!["test:e2e".to_string()];
 
-    if ui_mode {
+    if options.ui_mode {
         pnpm_script_args.push("--ui".to_string());
-    } else if debug_mode {
+    } else if options.debug_mode {
         pnpm_script_args.push("--debug".to_string());
-    } else if headed_mode {
+    } else if options.headed_mode {
         pnpm_script_args.push("--headed".to_string());
     }
 
-    if let Some(p_name) = project {
+    if let Some(p_name) = &options.project {
         pnpm_script_args.push("--project".to_string());
-        pnpm_script_args.push(p_name);
+        pnpm_script_args.push(p_name.clone());
     }
 
-    if !additional_args.is_empty() {
-        pnpm_script_args.extend(additional_args);
-    }
+    pnpm_script_args.extend(options.additional_args.iter().cloned());
 
     let pnpm_script_args_refs: Vec<&str> = pnpm_script_args.iter().map(AsRef::as_ref).collect();
 
+    // The UI and debug modes are interactive and need the terminal itself.
     let cmd_config = CommandConfig {
+        stage: "e2e tests",
         program_name: "pnpm",
         args: &pnpm_script_args_refs,
         current_dir: Some(workspace_root),
-        env_vars: Some(HashMap::from([("CI".to_string(), "true".to_string())])),
+        env_vars: Some(HashMap::from([
+            ("CI".to_string(), "true".to_string()),
+            (BASE_URL_VAR.to_string(), base_url.to_string()),
+        ])),
         inherit_stdio: true,
+        capture: !options.ui_mode && !options.debug_mode,
+        cargo_diagnostics: false,
     };
 
-    run_command(cmd_config).context("Failed to execute Playwright command")?;
-
-    println!("\nPlaywright E2E tests passed successfully!");
-    Ok(())
+    run_command(cmd_config).context("Failed to execute Playwright command")
 }
@@ -1,11 +1,17 @@
+pub mod bench_cmd;
 pub mod build_cmd;
 pub mod dev_cmd;
+pub mod doctor_cmd;
 pub mod e2e_cmd;
+pub mod release_cmd;
 pub mod serve_cmd;
 pub mod test_cmd;
 
+pub use bench_cmd::handle_bench_command;
 pub use build_cmd::handle_build_command;
 pub use dev_cmd::handle_dev_command;
-pub use e2e_cmd::handle_e2e_command;
+pub use doctor_cmd::handle_doctor_command;
+pub use e2e_cmd::{handle_e2e_command, E2eOptions};
+pub use release_cmd::handle_release_command;
 pub use serve_cmd::handle_serve_command;
 pub use test_cmd::handle_test_command;
@@ -0,0 +1,68 @@
+use crate::bench::BROWSER_HARNESS_FILES;
+use crate::build::{build_wasm, BuildOptions};
+use crate::optimize::check_size_budget;
+use crate::paths::ProjectPaths;
+use crate::release::{assemble_release, ReleaseInput};
+use crate::report::{self, progress};
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+pub fn handle_release_command(
+    paths: &ProjectPaths,
+    dist_dir: &Path,
+    build: &BuildOptions,
+    skip_build: bool,
+) -> Result<()> {
+    // A release build goes to its own directory so the dev package in the
+    // static directory is neither overwritten nor shipped.
+    let pkg_dir = if skip_build {
+        paths.static_pkg_dir.clone()
+    } else {
+        let pkg_dir = paths.target_dir.join("cli-release").join("pkg");
+        let _ = std::fs::remove_dir_all(&pkg_dir);
+        build_wasm(&paths.app, &pkg_dir, build)?;
+        pkg_dir
+    };
+    for file in [
+        format!("{}.js", build.out_name),
+        format!("{}_bg.wasm", build.out_name),
+    ] {
+        if !pkg_dir.join(&file).is_file() {
+            bail!(
+                "'{}' not found in '{}'; run the release without --skip-build.",
+                file,
+                pkg_dir.display()
+            );
+        }
+    }
+
+    // A fresh build already checked the budget; a prebuilt package has not been.
+    if let (true, Some(budget)) = (skip_build, build.size_budget) {
+        let wasm_path = pkg_dir.join(format!("{}_bg.wasm", build.out_name));
+        report::stage("size budget", || check_size_budget(&wasm_path, budget))?;
+    }
+
+    let pkg_site_path = paths
+        .static_pkg_dir
+        .strip_prefix(&paths.static_dir)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|_| PathBuf::from("pkg"));
+    // Whatever the static directory holds where the package goes is a stale
+    // dev build, and the benchmark harness is not part of the site.
+    let mut exclude = vec![paths.static_dir.join(&pkg_site_path)];
+    exclude.extend(
+        BROWSER_HARNESS_FILES
+            .iter()
+            .map(|file| paths.static_dir.join(file)),
+    );
+    let input = ReleaseInput {
+        static_dir: &paths.static_dir,
+        pkg_dir: &pkg_dir,
+        pkg_site_path: &pkg_site_path,
+        exclude: &exclude,
+    };
+    progress!("Assembling release in '{}'...", dist_dir.display());
+    report::stage("assemble release", || assemble_release(&input, dist_dir))?;
+    progress!("Release ready in '{}'.", dist_dir.display());
+    Ok(())
+}
@@ -1,21 +1,28 @@
-use crate::server::serve_static;
+use crate::report::progress;
+use crate::server::{serve_static, ServeOptions};
 use anyhow::Result;
 use std::path::Path;
 
 pub fn handle_serve_command(
     static_dir_path: &Path,
     static_pkg_dir_path: &Path,
+    out_name: &str,
     port: u16,
     host: &str,
+    options: &ServeOptions,
 ) -> Result<()> {
-    if !static_pkg_dir_path.join("app.js").exists()
-        || !static_pkg_dir_path.join("app_bg.wasm").exists()
+    if !static_pkg_dir_path
+        .join(format!("{}.js", out_name))
+        .exists()
+        || !static_pkg_dir_path
+            .join(format!("{}_bg.wasm", out_name))
+            .exists()
     {
-        println!(
+        progress!(
             "Warning: WASM output files not found in '{}'.",
             static_pkg_dir_path.display()
         );
-        println!("Consider running 'cargo run --bin cli -- build' first.");
+        progress!("Consider running 'cargo run --bin cli -- build' first.");
     }
-    serve_static(static_dir_path, port, host)
+    serve_static(static_dir_path, port, host, options, None)
 }
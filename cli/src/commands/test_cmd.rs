@@ -1,34 +1,81 @@
-use crate::test_runner::{run_app_tests, run_base_tests};
-use anyhow::{Context, Result};
+use crate::coverage::{self, LlvmTools, APP_COVERAGE_UNSUPPORTED};
+use crate::fuzz::{self, FuzzOptions};
+use crate::junit;
+use crate::orchestrator::run_jobs;
+use crate::report::{self, progress};
+use crate::test_runner::{app_tests_jobs, base_tests_job, TestOptions};
+use anyhow::Result;
+use std::collections::HashMap;
 use std::path::Path;
 
 pub fn handle_test_command(
+    base_path: &Path,
     app_path: &Path,
     base: bool,
     app: bool,
-    browser: Option<String>,
-    headless: bool,
+    options: &TestOptions,
 ) -> Result<()> {
+    if let Some(fuzz) = &options.fuzz {
+        return run_fuzz_targets(base_path, fuzz, options);
+    }
+
     let run_base = base || !app;
     let run_app = app || !base;
 
+    // Find the LLVM tools before spending time on the tests.
+    let llvm_tools = match &options.coverage {
+        Some(_) => Some(LlvmTools::find()?),
+        None => None,
+    };
+
+    let mut jobs = Vec::new();
     if run_base {
-        run_base_tests().context("Failed to run base tests")?;
+        let env_vars = match &options.coverage {
+            Some(coverage) => coverage::prepare(coverage)?,
+            None => HashMap::new(),
+        };
+        jobs.push(base_tests_job(
+            base_path,
+            env_vars,
+            options.filter.as_deref(),
+        ));
     }
-
     if run_app {
-        if let Some(specific_browser) = browser {
-            run_app_tests(app_path, &specific_browser, headless)?;
-        } else {
-            let browsers = if cfg!(target_os = "macos") {
-                vec!["chrome", "firefox", "safari"]
-            } else {
-                vec!["chrome", "firefox"]
-            };
-            for b in browsers {
-                run_app_tests(app_path, b, headless)?;
-            }
+        if options.coverage.is_some() {
+            progress!("Note: {}", APP_COVERAGE_UNSUPPORTED);
         }
+        jobs.extend(app_tests_jobs(app_path, options)?);
+    }
+    for job in &mut jobs {
+        job.retries = options.retries;
+        job.keep_output = options.junit.is_some();
+    }
+
+    let max_parallel = options.jobs.unwrap_or(jobs.len());
+    let outcome = run_jobs(jobs, options.failure_policy, max_parallel);
+    if let Some(path) = &options.junit {
+        report::stage("junit report", || junit::write_report(path, &outcome.jobs))?;
+        progress!("Wrote the test results to '{}'", path.display());
+    }
+    outcome.into_result()?;
+
+    if let (Some(coverage), Some(tools)) = (&options.coverage, &llvm_tools) {
+        report::stage("coverage report", || {
+            coverage::write_report(tools, base_path, coverage, run_app)
+        })?;
     }
     Ok(())
 }
+
+/// Runs the fuzz targets side by side, like the test suites.
+fn run_fuzz_targets(base_path: &Path, fuzz: &FuzzOptions, options: &TestOptions) -> Result<()> {
+    let jobs = fuzz::fuzz_jobs(base_path, fuzz)?;
+    progress!(
+        "Fuzzing {} for {}s each. Crashes are saved to '{}'.",
+        fuzz.targets.join(", "),
+        fuzz.time.as_secs(),
+        base_path.join("fuzz").join("artifacts").display()
+    );
+    let max_parallel = options.jobs.unwrap_or(jobs.len());
+    run_jobs(jobs, options.failure_policy, max_parallel).into_result()
+}
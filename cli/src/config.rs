@@ -0,0 +1,436 @@
+use crate::build::BuildBackend;
+use crate::optimize::{ByteSize, WasmOptLevel};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The optional project configuration file at the workspace root.
+pub const CONFIG_FILE: &str = "project.toml";
+/// The same settings can live under this table of the workspace `Cargo.toml`.
+const CARGO_METADATA_TABLE: &str = "[workspace.metadata.cli]";
+
+/// Settings read from `project.toml`. Command-line flags take precedence.
+/// Every field is optional; the defaults describe this template's layout.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    pub paths: PathsConfig,
+    pub build: BuildConfig,
+    pub server: ServerConfig,
+    pub test: TestConfig,
+    pub bench: BenchConfig,
+}
+
+/// Project directories, relative to the workspace root.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PathsConfig {
+    /// The crate compiled to WASM.
+    pub app: PathBuf,
+    /// The native logic crate, watched and tested alongside the app.
+    pub base: PathBuf,
+    /// The directory the server serves.
+    #[serde(rename = "static")]
+    pub static_dir: PathBuf,
+    /// Where the WASM package is written. Defaults to `pkg` inside the static directory.
+    pub out_dir: Option<PathBuf>,
+    /// Where `cli release` assembles the deployable site.
+    pub dist: PathBuf,
+}
+
+impl Default for PathsConfig {
+    fn default() -> Self {
+        Self {
+            app: PathBuf::from("app"),
+            base: PathBuf::from("base"),
+            static_dir: PathBuf::from("static"),
+            out_dir: None,
+            dist: PathBuf::from("dist"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BuildConfig {
+    /// The file name of the generated JS and WASM files, e.g. `app.js`.
+    pub out_name: String,
+    /// Cargo features enabled for every WASM build.
+    pub features: Vec<String>,
+    /// Cargo features enabled only for release builds.
+    pub release_features: Vec<String>,
+    /// Which tools build the WASM package: `wasm-pack` or `cargo`.
+    pub backend: BuildBackend,
+    /// The `wasm-opt` level release builds are optimized with.
+    pub wasm_opt: WasmOptLevel,
+    /// Release builds fail when the `_bg.wasm` file is larger than this.
+    pub size_budget: Option<ByteSize>,
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            out_name: "app".to_string(),
+            features: Vec::new(),
+            release_features: vec!["wee_alloc".to_string()],
+            backend: BuildBackend::default(),
+            wasm_opt: WasmOptLevel::default(),
+            size_budget: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub port: Option<u16>,
+    pub host: Option<String>,
+    /// Proxy rules as path prefix to upstream URL, like `--proxy PATH=URL`.
+    pub proxy: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TestConfig {
+    /// Browsers the app tests run in when `--browser` is not given.
+    pub browsers: Option<Vec<Browser>>,
+    /// Run every test suite even after one fails, instead of stopping the rest.
+    pub keep_going: bool,
+    /// How many test suites run at once. Defaults to all of them.
+    pub jobs: Option<usize>,
+    /// `--coverage` fails when fewer lines than this percentage are covered.
+    pub min_coverage: Option<f64>,
+    /// How many times a failed test suite runs again.
+    pub retries: u32,
+    /// Write the test results as JUnit XML to this file, relative to the workspace root.
+    pub junit: Option<PathBuf>,
+}
+
+impl TestConfig {
+    /// The browsers app tests run in without `--browser`: `browsers` if set,
+    /// or every browser wasm-pack supports on this platform.
+    pub fn browsers(&self) -> Vec<String> {
+        match &self.browsers {
+            Some(browsers) => browsers.iter().map(|b| b.as_str().to_string()).collect(),
+            None if cfg!(target_os = "macos") => vec![
+                "chrome".to_string(),
+                "firefox".to_string(),
+                "safari".to_string(),
+            ],
+            None => vec!["chrome".to_string(), "firefox".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BenchConfig {
+    /// The baseline `cli bench` compares against when `--baseline` is not given.
+    pub baseline: String,
+    /// `cli bench` fails when a benchmark is slower than its baseline by more
+    /// than this percentage.
+    pub max_regression: f64,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            baseline: "main".to_string(),
+            max_regression: 10.0,
+        }
+    }
+}
+
+/// A browser `wasm-pack test` can drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Browser {
+    Chrome,
+    Firefox,
+    Safari,
+}
+
+impl Browser {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Browser::Chrome => "chrome",
+            Browser::Firefox => "firefox",
+            Browser::Safari => "safari",
+        }
+    }
+}
+
+/// Loads the configuration from `project.toml` or `[workspace.metadata.cli]`
+/// in the workspace `Cargo.toml`, or the defaults if neither exists.
+pub fn load_config(workspace_root: &Path) -> Result<ProjectConfig> {
+    let config_path = workspace_root.join(CONFIG_FILE);
+    let from_manifest = manifest_config(&workspace_root.join("Cargo.toml"))?;
+
+    let config = if config_path.is_file() {
+        if from_manifest.is_some() {
+            bail!(
+                "Found both '{}' and {} in Cargo.toml; keep the configuration in one place.",
+                config_path.display(),
+                CARGO_METADATA_TABLE
+            );
+        }
+        let text = fs::read_to_string(&config_path)
+            .with_context(|| format!("Failed to read '{}'", config_path.display()))?;
+        toml::from_str(&text)
+            .with_context(|| format!("Invalid configuration in '{}'", config_path.display()))?
+    } else if let Some(table) = from_manifest {
+        table.try_into().with_context(|| {
+            format!(
+                "Invalid configuration in {} of Cargo.toml",
+                CARGO_METADATA_TABLE
+            )
+        })?
+    } else {
+        return Ok(ProjectConfig::default());
+    };
+
+    validate(&config)?;
+    Ok(config)
+}
+
+/// Returns the `[workspace.metadata.cli]` table, if the manifest has one.
+fn manifest_config(manifest_path: &Path) -> Result<Option<toml::Table>> {
+    let Ok(text) = fs::read_to_string(manifest_path) else {
+        return Ok(None);
+    };
+    let manifest: toml::Table = toml::from_str(&text)
+        .with_context(|| format!("Failed to parse '{}'", manifest_path.display()))?;
+    let table = manifest
+        .get("workspace")
+        .and_then(|w| w.get("metadata"))
+        .and_then(|m| m.get("cli"));
+    match table {
+        Some(toml::Value::Table(table)) => Ok(Some(table.clone())),
+        Some(_) => bail!("{} in Cargo.toml must be a table", CARGO_METADATA_TABLE),
+        None => Ok(None),
+    }
+}
+
+/// Checks values that parse but cannot work.
+fn validate(config: &ProjectConfig) -> Result<()> {
+    let out_name = &config.build.out_name;
+    if out_name.is_empty() || out_name.contains(['/', '\\', '.']) {
+        bail!(
+            "Invalid configuration: build.out-name must be a plain file name without extension, got '{}'",
+            out_name
+        );
+    }
+    if let Some(browsers) = &config.test.browsers {
+        if browsers.is_empty() {
+            bail!("Invalid configuration: test.browsers must list at least one browser");
+        }
+    }
+    if config.test.jobs == Some(0) {
+        bail!("Invalid configuration: test.jobs must be at least 1");
+    }
+    if let Some(min_coverage) = config.test.min_coverage {
+        if !(0.0..=100.0).contains(&min_coverage) {
+            bail!(
+                "Invalid configuration: test.min-coverage must be between 0 and 100, got {}",
+                min_coverage
+            );
+        }
+    }
+    if config.bench.max_regression.is_nan() || config.bench.max_regression < 0.0 {
+        bail!(
+            "Invalid configuration: bench.max-regression must be a percentage of at least 0, got {}",
+            config.bench.max_regression
+        );
+    }
+    if let Err(e) = crate::bench::check_baseline_name(&config.bench.baseline) {
+        bail!("Invalid configuration: bench.baseline {}", e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    // A workspace root holding only the given files, removed when dropped.
+    fn test_root(name: &str, files: &[(&str, &str)]) -> TempDir {
+        let root = TempDir::new("config", name);
+        for (path, contents) in files {
+            root.write(path, contents);
+        }
+        root
+    }
+
+    const FULL_CONFIG: &str = r#"
+[paths]
+app = "crates/app"
+base = "crates/base"
+static = "web"
+out-dir = "web/generated"
+dist = "public"
+
+[build]
+out-name = "site"
+features = ["console_log", "tracing"]
+release-features = []
+backend = "cargo"
+wasm-opt = "z"
+size-budget = "1.5MiB"
+
+[server]
+port = 3000
+host = "127.0.0.1"
+
+[server.proxy]
+"/api" = "http://127.0.0.1:8000"
+
+[test]
+browsers = ["firefox"]
+keep-going = true
+jobs = 2
+min-coverage = 75.5
+retries = 1
+junit = "target/junit.xml"
+"#;
+
+    fn assert_full_config(config: &ProjectConfig) {
+        assert_eq!(config.paths.app, Path::new("crates/app"));
+        assert_eq!(config.paths.base, Path::new("crates/base"));
+        assert_eq!(config.paths.static_dir, Path::new("web"));
+        assert_eq!(
+            config.paths.out_dir.as_deref(),
+            Some(Path::new("web/generated"))
+        );
+        assert_eq!(config.paths.dist, Path::new("public"));
+
+        assert_eq!(config.build.out_name, "site");
+        assert_eq!(config.build.features, ["console_log", "tracing"]);
+        assert!(config.build.release_features.is_empty());
+        assert_eq!(config.build.backend, BuildBackend::Cargo);
+        assert_eq!(config.build.wasm_opt, WasmOptLevel::Oz);
+        assert_eq!(config.build.size_budget, Some(ByteSize(1_572_864)));
+
+        assert_eq!(config.server.port, Some(3000));
+        assert_eq!(config.server.host.as_deref(), Some("127.0.0.1"));
+        assert_eq!(
+            config.server.proxy,
+            BTreeMap::from([("/api".to_string(), "http://127.0.0.1:8000".to_string())])
+        );
+
+        assert_eq!(config.test.browsers(), ["firefox"]);
+        assert!(config.test.keep_going);
+        assert_eq!(config.test.jobs, Some(2));
+        assert_eq!(config.test.min_coverage, Some(75.5));
+        assert_eq!(config.test.retries, 1);
+        assert_eq!(
+            config.test.junit.as_deref(),
+            Some(Path::new("target/junit.xml"))
+        );
+    }
+
+    #[test]
+    fn test_defaults_without_a_config() {
+        let root = test_root("defaults", &[("Cargo.toml", "[workspace]\n")]);
+        let config = load_config(root.path()).unwrap();
+        assert_eq!(config.paths.app, Path::new("app"));
+        assert_eq!(config.paths.static_dir, Path::new("static"));
+        assert_eq!(config.paths.out_dir, None);
+        assert_eq!(config.build.out_name, "app");
+        assert_eq!(config.build.release_features, ["wee_alloc"]);
+        assert_eq!(config.build.backend, BuildBackend::WasmPack);
+        assert_eq!(config.build.wasm_opt, WasmOptLevel::Os);
+        assert_eq!(config.server.port, None);
+        assert!(config.server.proxy.is_empty());
+        assert!(!config.test.keep_going);
+        assert_eq!(config.test.retries, 0);
+        assert_eq!(config.bench.baseline, "main");
+    }
+
+    #[test]
+    fn test_project_toml_sections() {
+        let root = test_root(
+            "project-toml",
+            &[("Cargo.toml", "[workspace]\n"), (CONFIG_FILE, FULL_CONFIG)],
+        );
+        assert_full_config(&load_config(root.path()).unwrap());
+    }
+
+    #[test]
+    fn test_workspace_metadata_sections() {
+        // The same tables, nested under `[workspace.metadata.cli]`.
+        let manifest = FULL_CONFIG.replace("\n[", "\n[workspace.metadata.cli.");
+        let manifest = format!("[workspace]\nmembers = []\n{}", manifest);
+        let root = test_root("cargo-metadata", &[("Cargo.toml", &manifest)]);
+        assert_full_config(&load_config(root.path()).unwrap());
+    }
+
+    #[test]
+    fn test_config_in_both_places_is_an_error() {
+        let root = test_root(
+            "both",
+            &[
+                (
+                    "Cargo.toml",
+                    "[workspace]\n[workspace.metadata.cli.server]\nport = 1\n",
+                ),
+                (CONFIG_FILE, "[server]\nport = 2\n"),
+            ],
+        );
+        let error = load_config(root.path()).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("keep the configuration in one place"),
+            "{:#}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_invalid_configs_are_errors() {
+        for (name, config, message) in [
+            (
+                "unknown-key",
+                "[server]\nprot = 1\n",
+                "unknown field `prot`",
+            ),
+            ("unknown-section", "[deploy]\n", "unknown field `deploy`"),
+            (
+                "backend",
+                "[build]\nbackend = \"make\"\n",
+                "unknown variant `make`",
+            ),
+            (
+                "size",
+                "[build]\nsize-budget = \"1GB\"\n",
+                "invalid size '1GB'",
+            ),
+            (
+                "out-name",
+                "[build]\nout-name = \"app.js\"\n",
+                "build.out-name",
+            ),
+            ("browsers", "[test]\nbrowsers = []\n", "test.browsers"),
+            ("jobs", "[test]\njobs = 0\n", "test.jobs"),
+            (
+                "coverage",
+                "[test]\nmin-coverage = 101\n",
+                "test.min-coverage",
+            ),
+        ] {
+            let root = test_root(name, &[(CONFIG_FILE, config)]);
+            let error = format!("{:#}", load_config(root.path()).unwrap_err());
+            assert!(error.contains(message), "{}: {}", name, error);
+        }
+
+        let root = test_root(
+            "metadata-not-table",
+            &[("Cargo.toml", "[workspace.metadata]\ncli = 1\n")],
+        );
+        let error = load_config(root.path()).unwrap_err();
+        assert!(error.to_string().contains("must be a table"), "{:#}", error);
+    }
+}
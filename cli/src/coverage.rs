@@ -0,0 +1,304 @@
+use crate::report::{self, progress};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Why the app tests run without coverage, shown whenever `--coverage` runs them.
+pub const APP_COVERAGE_UNSUPPORTED: &str = "Coverage of the app (WASM) tests is not supported: \
+     wasm-bindgen-test only collects it on a nightly toolchain with unstable flags. \
+     The app tests run without coverage.";
+
+/// Where coverage is written and what it must reach.
+#[derive(Debug, Clone)]
+pub struct CoverageOptions {
+    /// Holds `lcov.info`, `html/` and the raw profiles and build.
+    pub output_dir: PathBuf,
+    /// Fail when fewer lines than this percentage are covered.
+    pub min_lines: Option<f64>,
+}
+
+impl CoverageOptions {
+    fn profile_dir(&self) -> PathBuf {
+        self.output_dir.join("profraw")
+    }
+
+    /// Instrumented builds go to their own target directory, so they do not
+    /// replace the regular build.
+    fn target_dir(&self) -> PathBuf {
+        self.output_dir.join("target")
+    }
+
+    pub fn lcov_path(&self) -> PathBuf {
+        self.output_dir.join("lcov.info")
+    }
+
+    pub fn html_dir(&self) -> PathBuf {
+        self.output_dir.join("html")
+    }
+}
+
+/// The LLVM tools that read the profiles, matching the LLVM of `rustc`.
+pub struct LlvmTools {
+    profdata: PathBuf,
+    cov: PathBuf,
+}
+
+impl LlvmTools {
+    /// Finds `llvm-profdata` and `llvm-cov` from rustup's `llvm-tools` component.
+    pub fn find() -> Result<Self> {
+        let find = |name: &str| {
+            find_llvm_tool(name).ok_or_else(|| {
+                anyhow!(
+                    "{} not found. Install it with: rustup component add llvm-tools",
+                    name
+                )
+            })
+        };
+        Ok(Self {
+            profdata: find("llvm-profdata")?,
+            cov: find("llvm-cov")?,
+        })
+    }
+}
+
+/// The `name` tool in the sysroot of the active toolchain, if installed.
+pub fn find_llvm_tool(name: &str) -> Option<PathBuf> {
+    let sysroot = rustc_output(&["--print", "sysroot"])?;
+    let version = rustc_output(&["-vV"])?;
+    let host = version
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))?;
+    let tool = PathBuf::from(sysroot.trim())
+        .join("lib")
+        .join("rustlib")
+        .join(host.trim())
+        .join("bin")
+        .join(format!("{}{}", name, std::env::consts::EXE_SUFFIX));
+    tool.is_file().then_some(tool)
+}
+
+fn rustc_output(args: &[&str]) -> Option<String> {
+    let output = Command::new("rustc").args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Removes the profiles of earlier runs and returns the environment that
+/// makes `cargo test` build instrumented tests and write new ones.
+pub fn prepare(options: &CoverageOptions) -> Result<HashMap<String, String>> {
+    let profile_dir = options.profile_dir();
+    if profile_dir.exists() {
+        fs::remove_dir_all(&profile_dir)
+            .with_context(|| format!("Failed to clear '{}'", profile_dir.display()))?;
+    }
+    fs::create_dir_all(&profile_dir)
+        .with_context(|| format!("Failed to create '{}'", profile_dir.display()))?;
+    Ok(coverage_env(options))
+}
+
+fn coverage_env(options: &CoverageOptions) -> HashMap<String, String> {
+    let mut rustflags = std::env::var("RUSTFLAGS").unwrap_or_default();
+    if !rustflags.is_empty() {
+        rustflags.push(' ');
+    }
+    rustflags.push_str("-C instrument-coverage");
+    HashMap::from([
+        ("RUSTFLAGS".to_string(), rustflags),
+        (
+            "LLVM_PROFILE_FILE".to_string(),
+            options
+                .profile_dir()
+                .join("base-%p-%m.profraw")
+                .to_string_lossy()
+                .into_owned(),
+        ),
+        (
+            "CARGO_TARGET_DIR".to_string(),
+            options.target_dir().to_string_lossy().into_owned(),
+        ),
+    ])
+}
+
+/// Covered and total counts of lines, functions or regions.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Totals {
+    pub covered: u64,
+    pub count: u64,
+    pub percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageSummary {
+    pub lines: Totals,
+    pub functions: Totals,
+    pub regions: Totals,
+}
+
+/// Merges the profiles of the base test run into `lcov.info` and an HTML
+/// report covering the sources of `base_path`, prints the totals and checks
+/// them against the threshold. `app_tested` notes that the app tests ran
+/// without coverage.
+pub fn write_report(
+    tools: &LlvmTools,
+    base_path: &Path,
+    options: &CoverageOptions,
+    app_tested: bool,
+) -> Result<()> {
+    let profiles: Vec<PathBuf> = fs::read_dir(options.profile_dir())?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "profraw"))
+        .collect();
+    if profiles.is_empty() {
+        bail!(
+            "The base tests wrote no coverage profiles to '{}'",
+            options.profile_dir().display()
+        );
+    }
+
+    let merged = options.output_dir.join("base.profdata");
+    let mut merge = Command::new(&tools.profdata);
+    merge
+        .args(["merge", "-sparse", "-o"])
+        .arg(&merged)
+        .args(&profiles);
+    run_tool(merge)?;
+
+    let objects = test_executables(base_path, options)?;
+    let sources = base_path.join("src");
+    let llvm_cov = |subcommand: &str| {
+        let mut cmd = Command::new(&tools.cov);
+        cmd.arg(subcommand).arg("-instr-profile").arg(&merged);
+        // The first binary is positional; sources follow the others.
+        cmd.arg(&objects[0]);
+        for object in &objects[1..] {
+            cmd.arg("-object").arg(object);
+        }
+        cmd
+    };
+
+    let mut export = llvm_cov("export");
+    export.arg("-format=lcov").arg(&sources);
+    let lcov = run_tool(export)?;
+    fs::write(options.lcov_path(), lcov)
+        .with_context(|| format!("Failed to write '{}'", options.lcov_path().display()))?;
+
+    let mut show = llvm_cov("show");
+    show.arg("-format=html")
+        .arg("-show-line-counts-or-regions")
+        .arg(format!("-output-dir={}", options.html_dir().display()))
+        .arg(&sources);
+    run_tool(show)?;
+
+    let mut summary = llvm_cov("export");
+    summary
+        .args(["-format=text", "-summary-only"])
+        .arg(&sources);
+    let summary = parse_summary(&run_tool(summary)?)?;
+
+    let mut table = llvm_cov("report");
+    table.arg(&sources);
+    progress!(
+        "\n{}",
+        String::from_utf8_lossy(&run_tool(table)?).trim_end()
+    );
+    progress!(
+        "Line coverage of base: {:.1}% ({} of {} lines)",
+        summary.lines.percent,
+        summary.lines.covered,
+        summary.lines.count
+    );
+    progress!("LCOV report: {}", options.lcov_path().display());
+    progress!(
+        "HTML report: {}",
+        options.html_dir().join("index.html").display()
+    );
+    report::emit_event(
+        "coverage",
+        serde_json::json!({
+            "summary": summary,
+            "lcov": options.lcov_path(),
+            "html": options.html_dir().join("index.html"),
+            "min_lines": options.min_lines,
+            "unsupported": if app_tested { vec!["app"] } else { vec![] },
+        }),
+    );
+
+    if let Some(min_lines) = options.min_lines {
+        if summary.lines.percent < min_lines {
+            bail!(
+                "Line coverage of {:.1}% is below the minimum of {}%",
+                summary.lines.percent,
+                min_lines
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The test binaries the last `cargo test` built, which hold the coverage mappings.
+fn test_executables(base_path: &Path, options: &CoverageOptions) -> Result<Vec<PathBuf>> {
+    let output = Command::new("cargo")
+        .args(["test", "--no-run", "--message-format=json"])
+        .current_dir(base_path)
+        .envs(coverage_env(options))
+        .output()
+        .context("Failed to run cargo test --no-run")?;
+    if !output.status.success() {
+        bail!(
+            "cargo test --no-run failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let executables: Vec<PathBuf> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|message| message["reason"] == "compiler-artifact")
+        .filter_map(|message| message["executable"].as_str().map(PathBuf::from))
+        .collect();
+    if executables.is_empty() {
+        bail!("cargo test --no-run reported no test executables");
+    }
+    Ok(executables)
+}
+
+fn run_tool(mut cmd: Command) -> Result<Vec<u8>> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let output = cmd
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        bail!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+/// Reads the totals from `llvm-cov export -summary-only`.
+fn parse_summary(json: &[u8]) -> Result<CoverageSummary> {
+    let export: serde_json::Value =
+        serde_json::from_slice(json).context("Failed to parse the llvm-cov summary")?;
+    let totals = &export["data"][0]["totals"];
+    let read = |kind: &str| -> Result<Totals> {
+        let value = &totals[kind];
+        Ok(Totals {
+            covered: value["covered"]
+                .as_u64()
+                .ok_or_else(|| anyhow!("llvm-cov summary has no {} totals", kind))?,
+            count: value["count"].as_u64().unwrap_or_default(),
+            percent: value["percent"].as_f64().unwrap_or_default(),
+        })
+    };
+    Ok(CoverageSummary {
+        lines: read("lines")?,
+        functions: read("functions")?,
+        regions: read("regions")?,
+    })
+}
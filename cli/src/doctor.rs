@@ -0,0 +1,394 @@
+use crate::build::{locked_wasm_bindgen_version, BuildBackend, WASM_TARGET};
+use crate::config::{ProjectConfig, CONFIG_FILE};
+use crate::coverage::find_llvm_tool;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The oldest Node.js the E2E tooling supports, from `engines` in package.json.
+const MIN_NODE_MAJOR: u32 = 18;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    pub fn label(self) -> &'static str {
+        match self {
+            Status::Pass => "pass",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        }
+    }
+}
+
+/// The outcome of one check, with what to do about it unless it passed.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &str, message: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: Status::Pass,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    fn problem(
+        name: &str,
+        status: Status,
+        message: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Checks the tools every command relies on. `config` is the result of
+/// loading the project configuration; on error the defaults are assumed.
+pub fn run_checks(workspace_root: &Path, config: &anyhow::Result<ProjectConfig>) -> Vec<Check> {
+    let default_config = ProjectConfig::default();
+    let (config_check, config) = match config {
+        Ok(config) => {
+            let source = if workspace_root.join(CONFIG_FILE).is_file() {
+                format!("loaded {}", CONFIG_FILE)
+            } else {
+                "using the defaults".to_string()
+            };
+            (Check::pass("project configuration", source), config)
+        }
+        Err(e) => (
+            Check::problem(
+                "project configuration",
+                Status::Fail,
+                format!("{:#}", e),
+                format!(
+                    "Fix {} (or [workspace.metadata.cli] in Cargo.toml)",
+                    CONFIG_FILE
+                ),
+            ),
+            &default_config,
+        ),
+    };
+    let backend = config.build.backend;
+
+    let mut checks = vec![
+        config_check,
+        check_rustc(),
+        check_wasm_target(),
+        check_wasm_pack(backend),
+    ];
+    let locked = locked_wasm_bindgen_version(workspace_root).ok();
+    for program in ["wasm-bindgen", "wasm-bindgen-test-runner"] {
+        checks.push(check_wasm_bindgen_cli(program, locked.as_deref(), backend));
+    }
+    checks.push(check_wasm_opt());
+    checks.push(check_llvm_tools());
+    checks.push(check_cargo_fuzz());
+    checks.push(check_node());
+    checks.push(check_pnpm());
+    checks.push(check_playwright(workspace_root));
+    for browser in config.test.browsers() {
+        checks.push(check_webdriver(&browser, backend));
+    }
+    checks
+}
+
+/// Runs `program args` and returns the first line it prints, if it runs at all.
+fn first_output_line(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let line = stdout
+        .lines()
+        .chain(stderr.lines())
+        .map(str::trim)
+        .find(|line| !line.is_empty())?
+        .to_string();
+    Some(line)
+}
+
+/// The first word of `line` that looks like a version number, without a leading `v`.
+fn version_in(line: &str) -> Option<&str> {
+    line.split_whitespace()
+        .map(|word| {
+            word.trim_start_matches('v')
+                .trim_matches(|c| c == '(' || c == ')' || c == ',')
+        })
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+fn check_rustc() -> Check {
+    match first_output_line("rustc", &["--version"]) {
+        Some(line) => Check::pass("rustc", line),
+        None => Check::problem(
+            "rustc",
+            Status::Fail,
+            "rustc not found",
+            "Install Rust from https://rustup.rs/",
+        ),
+    }
+}
+
+fn check_wasm_target() -> Check {
+    let name = "wasm32 target";
+    let fix = format!("rustup target add {}", WASM_TARGET);
+    let Some(sysroot) = first_output_line("rustc", &["--print", "sysroot"]) else {
+        return Check::problem(name, Status::Fail, "rustc not found", fix);
+    };
+    let target_dir = PathBuf::from(sysroot)
+        .join("lib")
+        .join("rustlib")
+        .join(WASM_TARGET);
+    if target_dir.is_dir() {
+        Check::pass(name, format!("{} is installed", WASM_TARGET))
+    } else {
+        Check::problem(
+            name,
+            Status::Fail,
+            format!("{} is not installed", WASM_TARGET),
+            fix,
+        )
+    }
+}
+
+/// Missing tools the selected backend needs fail; the others only warn.
+fn backend_status(needed_by: BuildBackend, backend: BuildBackend) -> Status {
+    if needed_by == backend {
+        Status::Fail
+    } else {
+        Status::Warn
+    }
+}
+
+fn check_wasm_pack(backend: BuildBackend) -> Check {
+    match first_output_line("wasm-pack", &["--version"]) {
+        Some(line) => Check::pass("wasm-pack", line),
+        None => Check::problem(
+            "wasm-pack",
+            backend_status(BuildBackend::WasmPack, backend),
+            "wasm-pack not found",
+            "cargo install wasm-pack, or build with --backend cargo",
+        ),
+    }
+}
+
+fn check_wasm_bindgen_cli(program: &str, locked: Option<&str>, backend: BuildBackend) -> Check {
+    let install = match locked {
+        Some(locked) => format!("cargo install -f wasm-bindgen-cli --version {}", locked),
+        None => "cargo install -f wasm-bindgen-cli".to_string(),
+    };
+    let status = backend_status(BuildBackend::Cargo, backend);
+    let Some(line) = first_output_line(program, &["--version"]) else {
+        return Check::problem(
+            program,
+            status,
+            format!("{} not found (needed by --backend cargo)", program),
+            install,
+        );
+    };
+    match (version_in(&line), locked) {
+        (Some(installed), Some(locked)) if installed != locked => Check::problem(
+            program,
+            status,
+            format!(
+                "{} is installed, but Cargo.lock uses wasm-bindgen {}",
+                line, locked
+            ),
+            install,
+        ),
+        _ => Check::pass(program, line),
+    }
+}
+
+fn check_wasm_opt() -> Check {
+    match first_output_line("wasm-opt", &["--version"]) {
+        Some(line) => Check::pass("wasm-opt", line),
+        None => Check::problem(
+            "wasm-opt",
+            Status::Warn,
+            "wasm-opt not found; release builds are not optimized",
+            "Install binaryen: https://github.com/WebAssembly/binaryen/releases",
+        ),
+    }
+}
+
+fn check_llvm_tools() -> Check {
+    match find_llvm_tool("llvm-cov").zip(find_llvm_tool("llvm-profdata")) {
+        Some(_) => Check::pass("llvm-tools", "llvm-cov and llvm-profdata are installed"),
+        None => Check::problem(
+            "llvm-tools",
+            Status::Warn,
+            "llvm-tools not installed (needed for cli test --coverage)",
+            "rustup component add llvm-tools",
+        ),
+    }
+}
+
+fn check_cargo_fuzz() -> Check {
+    match first_output_line("cargo", &["+nightly", "fuzz", "--version"]) {
+        Some(line) if line.starts_with("cargo-fuzz") => Check::pass("cargo-fuzz", line),
+        _ => Check::problem(
+            "cargo-fuzz",
+            Status::Warn,
+            "cargo-fuzz or the nightly toolchain not installed (needed for cli test --fuzz)",
+            "rustup toolchain install nightly && cargo install cargo-fuzz",
+        ),
+    }
+}
+
+fn check_node() -> Check {
+    let fix = format!(
+        "Install Node.js {} or newer from https://nodejs.org/",
+        MIN_NODE_MAJOR
+    );
+    let Some(line) = first_output_line("node", &["--version"]) else {
+        return Check::problem(
+            "node",
+            Status::Warn,
+            "node not found (needed for E2E tests)",
+            fix,
+        );
+    };
+    let major = version_in(&line)
+        .and_then(|version| version.split('.').next())
+        .and_then(|major| major.parse::<u32>().ok());
+    match major {
+        Some(major) if major < MIN_NODE_MAJOR => Check::problem(
+            "node",
+            Status::Warn,
+            format!("node {} is older than {}", line, MIN_NODE_MAJOR),
+            fix,
+        ),
+        _ => Check::pass("node", line),
+    }
+}
+
+fn check_pnpm() -> Check {
+    match first_output_line("pnpm", &["--version"]) {
+        Some(line) => Check::pass("pnpm", line),
+        None => Check::problem(
+            "pnpm",
+            Status::Warn,
+            "pnpm not found (needed for E2E tests)",
+            "npm install -g pnpm",
+        ),
+    }
+}
+
+/// Where Playwright keeps its browsers unless `PLAYWRIGHT_BROWSERS_PATH` says otherwise.
+fn playwright_browsers_dir() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("PLAYWRIGHT_BROWSERS_PATH") {
+        return Some(PathBuf::from(path));
+    }
+    if cfg!(target_os = "windows") {
+        std::env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("ms-playwright"))
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join("Library/Caches/ms-playwright"))
+    } else {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache/ms-playwright"))
+    }
+}
+
+fn check_playwright(workspace_root: &Path) -> Check {
+    let name = "playwright browsers";
+    if !workspace_root
+        .join("node_modules")
+        .join("@playwright")
+        .join("test")
+        .is_dir()
+    {
+        return Check::problem(
+            name,
+            Status::Warn,
+            "@playwright/test is not installed",
+            "pnpm install && pnpm run install-playwright-browsers",
+        );
+    }
+    // The projects in playwright.config.js.
+    let mut wanted = vec!["chromium", "firefox"];
+    if cfg!(target_os = "macos") {
+        wanted.push("webkit");
+    }
+    let installed: Vec<String> = playwright_browsers_dir()
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    let missing: Vec<&str> = wanted
+        .iter()
+        .copied()
+        .filter(|browser| {
+            !installed.iter().any(|dir| {
+                dir.strip_prefix(browser)
+                    .is_some_and(|rest| rest.starts_with('-'))
+            })
+        })
+        .collect();
+    if missing.is_empty() {
+        Check::pass(name, format!("{} installed", wanted.join(", ")))
+    } else {
+        Check::problem(
+            name,
+            Status::Warn,
+            format!("not installed: {}", missing.join(", ")),
+            "pnpm run install-playwright-browsers",
+        )
+    }
+}
+
+/// The WebDriver `cli test` drives `browser` with, and the variable that can point to it.
+fn webdriver_for(browser: &str) -> (&'static str, &'static str) {
+    match browser {
+        "firefox" => ("geckodriver", "GECKODRIVER"),
+        "safari" => ("safaridriver", "SAFARIDRIVER"),
+        _ => ("chromedriver", "CHROMEDRIVER"),
+    }
+}
+
+fn check_webdriver(browser: &str, backend: BuildBackend) -> Check {
+    let (driver, variable) = webdriver_for(browser);
+    let name = format!("webdriver ({})", browser);
+    let program = std::env::var(variable).unwrap_or_else(|_| driver.to_string());
+    if let Some(line) = first_output_line(&program, &["--version"]) {
+        return Check::pass(&name, line);
+    }
+    // wasm-pack downloads chromedriver and geckodriver on first use.
+    if backend == BuildBackend::WasmPack && browser != "safari" {
+        return Check::problem(
+            &name,
+            Status::Warn,
+            format!("{} not found; wasm-pack will download it", driver),
+            format!(
+                "Install {} or set {} to skip the download",
+                driver, variable
+            ),
+        );
+    }
+    let fix = if browser == "safari" {
+        "Run `safaridriver --enable` once (requires macOS)".to_string()
+    } else {
+        format!("Install {} and put it on PATH, or set {}", driver, variable)
+    };
+    Check::problem(&name, Status::Fail, format!("{} not found", driver), fix)
+}
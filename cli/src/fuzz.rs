@@ -0,0 +1,86 @@
+use crate::orchestrator::Job;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// cargo-fuzz needs the sanitizer flags of a nightly compiler.
+const TOOLCHAIN: &str = "+nightly";
+
+/// Which fuzz targets of the base crate to run, and for how long.
+#[derive(Debug, Clone)]
+pub struct FuzzOptions {
+    pub targets: Vec<String>,
+    /// How long each target runs, unless it finds a crash first.
+    pub time: Duration,
+}
+
+/// The cargo-fuzz project of the base crate.
+fn fuzz_dir(base_path: &Path) -> PathBuf {
+    base_path.join("fuzz")
+}
+
+/// The targets in `fuzz/fuzz_targets`, sorted by name.
+pub fn available_targets(base_path: &Path) -> Result<Vec<String>> {
+    let targets_dir = fuzz_dir(base_path).join("fuzz_targets");
+    let entries = fs::read_dir(&targets_dir)
+        .with_context(|| format!("No fuzz targets found in '{}'", targets_dir.display()))?;
+    let mut targets: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        .filter_map(|path| path.file_stem()?.to_str().map(String::from))
+        .collect();
+    targets.sort();
+    Ok(targets)
+}
+
+/// One job per target, each running `cargo fuzz run` for `options.time`.
+/// Checks the targets and cargo-fuzz up front, so nothing starts when one is missing.
+pub fn fuzz_jobs(base_path: &Path, options: &FuzzOptions) -> Result<Vec<Job>> {
+    let available = available_targets(base_path)?;
+    for target in &options.targets {
+        if !available.contains(target) {
+            bail!(
+                "Unknown fuzz target '{}'. Available targets: {}",
+                target,
+                available.join(", ")
+            );
+        }
+    }
+    check_cargo_fuzz()?;
+
+    let max_total_time = format!("-max_total_time={}", options.time.as_secs().max(1));
+    Ok(options
+        .targets
+        .iter()
+        .map(|target| Job {
+            name: format!("fuzz {}", target),
+            program: "cargo".to_string(),
+            args: [TOOLCHAIN, "fuzz", "run", target, "--", &max_total_time]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            current_dir: Some(base_path.to_path_buf()),
+            env_vars: HashMap::new(),
+            cargo_diagnostics: false,
+            retries: 0,
+            keep_output: false,
+        })
+        .collect())
+}
+
+fn check_cargo_fuzz() -> Result<()> {
+    let installed = Command::new("cargo")
+        .args([TOOLCHAIN, "fuzz", "--version"])
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !installed {
+        bail!(
+            "cargo-fuzz or the nightly toolchain is missing. Install them with: \
+             rustup toolchain install nightly && cargo install cargo-fuzz"
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,534 @@
+use crate::orchestrator::JobOutcome;
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// How one test ended, as reported by libtest or wasm-bindgen-test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    pub name: String,
+    pub status: TestStatus,
+    /// What the test printed before failing, from the `failures:` section.
+    pub failure_output: String,
+}
+
+/// The tests of one test binary, e.g. the unit tests of a crate or one file
+/// in its `tests` directory.
+#[derive(Debug, Clone, Default)]
+pub struct TestBinary {
+    /// The target from cargo's `Running <target> (<path>)` line, e.g.
+    /// `tests/timestep_tests.rs`, when there was one.
+    pub name: Option<String>,
+    pub cases: Vec<TestCase>,
+    /// From the binary's `test result: ... finished in 0.02s` line.
+    pub seconds: Option<f64>,
+}
+
+/// Finds the results in the output of `cargo test` or `wasm-pack test`: the
+/// `test <name> ... ok` lines, and the `---- <name> stdout ----` sections
+/// that hold what the failed tests printed. Each `running N tests` line starts
+/// a new binary. Cargo names the binaries on stderr, which is read apart from
+/// stdout, so the names are matched to the binaries by their order.
+pub fn parse_test_output(lines: &[String]) -> Vec<TestBinary> {
+    let mut names = lines.iter().filter_map(|line| binary_name(line.trim()));
+    let mut binaries = vec![TestBinary::default()];
+    // The failed test whose output is being read, and the lines so far.
+    let mut failure: Option<(String, Vec<&str>)> = None;
+
+    for line in lines {
+        let binary = binaries.last_mut().expect("there is always a binary");
+        if let Some(header) = line
+            .strip_prefix("---- ")
+            .and_then(|rest| rest.strip_suffix(" ----"))
+        {
+            if let Some(previous) = failure.take() {
+                add_failure_output(&mut binary.cases, previous);
+            }
+            // `name stdout` from libtest, `name output` from wasm-bindgen-test.
+            let name = header.rsplit_once(' ').map_or(header, |(name, _)| name);
+            failure = Some((name.to_string(), Vec::new()));
+            continue;
+        }
+        if let Some(result) = line.strip_prefix("test result: ") {
+            if let Some(previous) = failure.take() {
+                add_failure_output(&mut binary.cases, previous);
+            }
+            binary.seconds = result
+                .rsplit_once("finished in ")
+                .and_then(|(_, time)| time.trim().strip_suffix('s')?.parse().ok());
+            continue;
+        }
+        if let Some((_, output)) = &mut failure {
+            if line == "failures:" {
+                add_failure_output(
+                    &mut binary.cases,
+                    failure.take().expect("in a failure section"),
+                );
+            } else {
+                output.push(line);
+            }
+            continue;
+        }
+        if is_binary_start(line) {
+            binaries.push(TestBinary {
+                name: names.next(),
+                ..TestBinary::default()
+            });
+            continue;
+        }
+
+        let Some((name, result)) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.split_once(" ... "))
+        else {
+            continue;
+        };
+        let status = match result.trim() {
+            "ok" => TestStatus::Passed,
+            "FAILED" | "FAIL" => TestStatus::Failed,
+            result if result.starts_with("ignored") => TestStatus::Ignored,
+            _ => continue,
+        };
+        binary.cases.push(TestCase {
+            name: name.to_string(),
+            status,
+            failure_output: String::new(),
+        });
+    }
+    if let Some(previous) = failure.take() {
+        let binary = binaries.last_mut().expect("there is always a binary");
+        add_failure_output(&mut binary.cases, previous);
+    }
+    binaries.retain(|binary| !binary.cases.is_empty());
+    binaries
+}
+
+/// `running 3 tests`, which libtest and wasm-bindgen-test print first.
+fn is_binary_start(line: &str) -> bool {
+    line.strip_prefix("running ")
+        .and_then(|rest| rest.strip_suffix(" tests").or(rest.strip_suffix(" test")))
+        .is_some_and(|count| count.parse::<usize>().is_ok())
+}
+
+/// The target in cargo's `Running tests/state.rs (target/debug/deps/...)`,
+/// or `Doc-tests base`. wasm-bindgen-test's own `Running headless tests in
+/// Chrome ...` has no path and is not a binary.
+fn binary_name(line: &str) -> Option<String> {
+    if line.starts_with("Doc-tests ") {
+        return Some(line.to_string());
+    }
+    let target = line.strip_prefix("Running ")?.strip_suffix(')')?;
+    target
+        .rsplit_once(" (")
+        .map(|(target, _)| target.to_string())
+}
+
+/// Attaches the output of a `---- <name> ----` section to the failed test.
+fn add_failure_output(cases: &mut [TestCase], (name, output): (String, Vec<&str>)) {
+    if let Some(case) = cases
+        .iter_mut()
+        .rev()
+        .find(|case| case.name == name && case.status == TestStatus::Failed)
+    {
+        case.failure_output = output.join("\n").trim_end().to_string();
+    }
+}
+
+/// Writes the test results of `suites` to `path` as JUnit XML, one
+/// `<testsuite>` per suite, or per test binary of a suite that ran several. A suite that failed without a failed test, e.g.
+/// because it did not compile, gets a failed test case named after it.
+pub fn write_report(path: &Path, suites: &[JobOutcome]) -> Result<()> {
+    let mut total = Counts::default();
+    let mut body = String::new();
+    for suite in suites {
+        let mut binaries = parse_test_output(&suite.output);
+        let any_failed = binaries
+            .iter()
+            .flat_map(|binary| &binary.cases)
+            .any(|case| case.status == TestStatus::Failed);
+        if !suite.success && !any_failed {
+            if binaries.is_empty() {
+                binaries.push(TestBinary::default());
+            }
+            binaries
+                .last_mut()
+                .expect("there is a binary")
+                .cases
+                .push(TestCase {
+                    name: suite.name.clone(),
+                    status: TestStatus::Failed,
+                    failure_output: suite.output.join("\n"),
+                });
+        }
+        // A suite that ran several binaries, like `cargo test` does, gets a
+        // `<testsuite>` per binary, so tests with the same name stay apart.
+        let split = binaries.len() > 1;
+        for binary in &binaries {
+            let name = match (&binary.name, split) {
+                (Some(binary_name), true) => format!("{} ({})", suite.name, binary_name),
+                _ => suite.name.clone(),
+            };
+            let seconds = if split {
+                binary.seconds.unwrap_or_default()
+            } else {
+                suite.elapsed.as_secs_f64()
+            };
+            let counts = Counts::of(&binary.cases);
+            total.add(&counts);
+            write_suite(&mut body, &name, seconds, &counts, suite, &binary.cases);
+        }
+    }
+
+    // No total time: the suites ran side by side, so theirs do not add up.
+    let xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <testsuites name=\"cli test\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\">\n\
+         {}</testsuites>\n",
+        total.tests, total.failures, total.skipped, body
+    );
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create '{}'", dir.display()))?;
+    }
+    fs::write(path, xml).with_context(|| format!("Failed to write '{}'", path.display()))
+}
+
+fn write_suite(
+    body: &mut String,
+    name: &str,
+    seconds: f64,
+    counts: &Counts,
+    suite: &JobOutcome,
+    cases: &[TestCase],
+) {
+    let _ = writeln!(
+        body,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">",
+        escape(name),
+        counts.tests,
+        counts.failures,
+        counts.skipped,
+        seconds
+    );
+    if suite.attempts > 1 {
+        let _ = writeln!(
+            body,
+            "    <properties>\n      <property name=\"attempts\" value=\"{}\"/>\n    </properties>",
+            suite.attempts
+        );
+    }
+    for case in cases {
+        let open = format!(
+            "    <testcase name=\"{}\" classname=\"{}\"",
+            escape(&case.name),
+            escape(name)
+        );
+        match case.status {
+            TestStatus::Passed => {
+                let _ = writeln!(body, "{}/>", open);
+            }
+            TestStatus::Ignored => {
+                let _ = writeln!(body, "{}>\n      <skipped/>\n    </testcase>", open);
+            }
+            TestStatus::Failed => {
+                let message = if suite.cancelled {
+                    "stopped before it finished"
+                } else {
+                    "test failed"
+                };
+                let _ = writeln!(
+                    body,
+                    "{}>\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                    open,
+                    message,
+                    escape(&case.failure_output)
+                );
+            }
+        }
+    }
+    body.push_str("  </testsuite>\n");
+}
+
+#[derive(Default)]
+struct Counts {
+    tests: usize,
+    failures: usize,
+    skipped: usize,
+}
+
+impl Counts {
+    fn of(cases: &[TestCase]) -> Self {
+        let count = |status| cases.iter().filter(|case| case.status == status).count();
+        Self {
+            tests: cases.len(),
+            failures: count(TestStatus::Failed),
+            skipped: count(TestStatus::Ignored),
+        }
+    }
+
+    fn add(&mut self, other: &Counts) {
+        self.tests += other.tests;
+        self.failures += other.failures;
+        self.skipped += other.skipped;
+    }
+}
+
+/// Escapes `text` for XML text and attribute values, dropping the control
+/// characters XML 1.0 does not allow.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::time::Duration;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    fn outcome(name: &str, success: bool, output: &str) -> JobOutcome {
+        JobOutcome {
+            name: name.to_string(),
+            success,
+            cancelled: false,
+            elapsed: Duration::from_millis(1500),
+            attempts: 1,
+            output: lines(output),
+        }
+    }
+
+    fn report(suites: &[JobOutcome], name: &str) -> String {
+        let dir = TempDir::new("junit", name);
+        let path = dir.path().join("junit.xml");
+        write_report(&path, suites).unwrap();
+        fs::read_to_string(&path).unwrap()
+    }
+
+    const LIBTEST_OUTPUT: &str = "\
+running 4 tests
+test state::adds ... ok
+test state::removes ... FAILED
+test state::slow ... ignored, too slow to run every time
+test state::later ... ignored
+test state::other ... FAILED
+
+failures:
+
+---- state::removes stdout ----
+thread 'state::removes' panicked at src/state.rs:10:5:
+left: 1
+
+---- state::other stdout ----
+other failure
+
+failures:
+    state::other
+    state::removes
+
+test result: FAILED. 1 passed; 2 failed; 2 ignored; 0 measured; 0 filtered out; finished in 0.25s
+";
+
+    #[test]
+    fn test_parses_libtest_results_and_failure_output() {
+        let binaries = parse_test_output(&lines(LIBTEST_OUTPUT));
+        assert_eq!(binaries.len(), 1);
+        assert_eq!(binaries[0].seconds, Some(0.25));
+        let cases = &binaries[0].cases;
+        let statuses: Vec<_> = cases
+            .iter()
+            .map(|case| (case.name.as_str(), case.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("state::adds", TestStatus::Passed),
+                ("state::removes", TestStatus::Failed),
+                ("state::slow", TestStatus::Ignored),
+                ("state::later", TestStatus::Ignored),
+                ("state::other", TestStatus::Failed),
+            ]
+        );
+        assert_eq!(
+            cases[1].failure_output,
+            "thread 'state::removes' panicked at src/state.rs:10:5:\nleft: 1"
+        );
+        assert_eq!(cases[4].failure_output, "other failure");
+        assert!(cases[0].failure_output.is_empty());
+    }
+
+    #[test]
+    fn test_parses_wasm_bindgen_test_results() {
+        let output = "\
+Running headless tests in Chrome on `http://127.0.0.1:4444/`
+running 2 tests
+test app_wasm_tests::mounts ... ok
+test app_wasm_tests::pauses ... FAIL
+
+failures:
+
+---- app_wasm_tests::pauses output ----
+    error output:
+        panicked at tests/app_wasm_tests.rs:260:5
+
+failures:
+
+    app_wasm_tests::pauses
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 filtered out; finished in 0.10s
+";
+        let binaries = parse_test_output(&lines(output));
+        assert_eq!(binaries.len(), 1);
+        assert_eq!(binaries[0].name, None);
+        let failed = &binaries[0].cases[1];
+        assert_eq!(failed.status, TestStatus::Failed);
+        assert_eq!(
+            failed.failure_output,
+            "    error output:\n        panicked at tests/app_wasm_tests.rs:260:5"
+        );
+    }
+
+    // `cargo test` output as the CLI logs it: cargo's stderr lines can end up
+    // anywhere between the test binary's stdout lines.
+    const CARGO_TEST_OUTPUT: &str = "\
+    Finished `test` profile [unoptimized + debuginfo] target(s) in 0.05s
+     Running unittests src/lib.rs (target/debug/deps/base-1a2b3c)
+     Running tests/state_tests.rs (target/debug/deps/state_tests-4d5e6f)
+running 1 test
+test tests::defaults ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+
+running 0 tests
+
+test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+     Running tests/viewport_tests.rs (target/debug/deps/viewport_tests-7a8b9c)
+   Doc-tests base
+running 2 tests
+test tests::defaults ... FAILED
+test tests::zoom ... ok
+
+failures:
+
+---- tests::defaults stdout ----
+viewport failure
+
+failures:
+    tests::defaults
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.02s
+
+running 1 test
+test src/lib.rs - state (line 5) ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.30s
+";
+
+    #[test]
+    fn test_parses_each_test_binary_apart() {
+        let binaries = parse_test_output(&lines(CARGO_TEST_OUTPUT));
+        let names: Vec<_> = binaries
+            .iter()
+            .map(|binary| binary.name.as_deref().unwrap())
+            .collect();
+        // The binary with no tests is left out.
+        assert_eq!(
+            names,
+            [
+                "unittests src/lib.rs",
+                "tests/viewport_tests.rs",
+                "Doc-tests base"
+            ]
+        );
+        assert_eq!(binaries[0].cases[0].status, TestStatus::Passed);
+        assert_eq!(binaries[1].cases[0].name, "tests::defaults");
+        assert_eq!(binaries[1].cases[0].failure_output, "viewport failure");
+        assert_eq!(binaries[2].seconds, Some(0.3));
+    }
+
+    #[test]
+    fn test_report_has_a_suite_per_test_binary() {
+        let xml = report(
+            &[
+                outcome("base tests", false, CARGO_TEST_OUTPUT),
+                outcome(
+                    "app tests (chrome)",
+                    true,
+                    "running 1 test\ntest mounts ... ok",
+                ),
+            ],
+            "binaries",
+        );
+        assert!(xml.contains(
+            "<testsuites name=\"cli test\" tests=\"5\" failures=\"1\" errors=\"0\" skipped=\"0\">"
+        ));
+        assert!(xml.contains(
+            "<testsuite name=\"base tests (unittests src/lib.rs)\" tests=\"1\" failures=\"0\" errors=\"0\" skipped=\"0\" time=\"0.010\">"
+        ), "{}", xml);
+        // The two `tests::defaults` no longer share a suite.
+        assert!(xml.contains(
+            "<testcase name=\"tests::defaults\" classname=\"base tests (unittests src/lib.rs)\"/>"
+        ));
+        assert!(xml.contains(
+            "<testcase name=\"tests::defaults\" classname=\"base tests (tests/viewport_tests.rs)\">\n      <failure message=\"test failed\">viewport failure</failure>"
+        ));
+        // A suite with a single binary keeps its own name and time.
+        assert!(xml.contains(
+            "<testsuite name=\"app tests (chrome)\" tests=\"1\" failures=\"0\" errors=\"0\" skipped=\"0\" time=\"1.500\">"
+        ));
+    }
+
+    #[test]
+    fn test_report_adds_a_failed_case_for_a_suite_without_results() {
+        let xml = report(
+            &[outcome(
+                "base tests",
+                false,
+                "error[E0308]: mismatched types",
+            )],
+            "no-results",
+        );
+        assert!(xml.contains("<testsuite name=\"base tests\" tests=\"1\" failures=\"1\""));
+        assert!(xml.contains(
+            "<testcase name=\"base tests\" classname=\"base tests\">\n      <failure message=\"test failed\">error[E0308]: mismatched types</failure>"
+        ));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("<a href=\"x\">Tom & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+        // XML 1.0 has no place for most control characters, like the escape
+        // that starts a terminal color code.
+        assert_eq!(
+            escape("\u{1b}[31mred\u{0}\u{8}\tand\r\nmore"),
+            "[31mred\tand\r\nmore"
+        );
+        assert_eq!(escape("naïve ✓"), "naïve ✓");
+    }
+}
@@ -0,0 +1,310 @@
+use crate::static_files;
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use warp::filters::BoxedFilter;
+use warp::ws::{Message, WebSocket, Ws};
+use warp::{Filter, Reply};
+
+/// The WebSocket endpoint the injected client connects to.
+const SOCKET_PATH: &str = "__livereload";
+/// The path the client script is served from.
+const CLIENT_PATH: &str = "__livereload.js";
+const CLIENT_SCRIPT: &str = include_str!("live_reload_client.js");
+
+/// A message pushed to every connected browser.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ReloadEvent {
+    /// Reload the whole page.
+    Reload,
+    /// The WASM package was rebuilt; swap it in while keeping the drawing.
+    /// The client falls back to a full reload if that is not possible.
+    HotReload,
+    /// Only these stylesheets changed; swap them without reloading.
+    /// Paths are URL paths relative to the static root, e.g. `css/style.css`.
+    Css { paths: Vec<String> },
+    /// The last build failed; show the message as an overlay.
+    BuildError { message: String },
+}
+
+/// Broadcasts reload events from the build/watch side to the dev server.
+/// Cloning it is cheap; all clones share the same channel.
+#[derive(Clone)]
+pub struct LiveReload {
+    sender: broadcast::Sender<ReloadEvent>,
+    // Replayed to browsers that connect while the build is broken.
+    last_error: Arc<Mutex<Option<String>>>,
+}
+
+impl LiveReload {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(16);
+        Self {
+            sender,
+            last_error: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn send(&self, event: ReloadEvent) {
+        // Sending only fails when no browser is connected, which is fine.
+        let _ = self.sender.send(event);
+    }
+
+    /// A rebuild succeeded: clear any error overlay and hot-swap the new package.
+    pub fn build_succeeded(&self) {
+        *self.last_error.lock().unwrap() = None;
+        self.send(ReloadEvent::HotReload);
+    }
+
+    /// A rebuild failed: show `message` in every connected browser.
+    pub fn build_failed(&self, message: String) {
+        *self.last_error.lock().unwrap() = Some(message.clone());
+        self.send(ReloadEvent::BuildError { message });
+    }
+
+    /// Files in the static directory changed. Stylesheets are swapped in place
+    /// when nothing else changed; anything else reloads the page.
+    pub fn static_files_changed(&self, static_dir: &Path, paths: &[PathBuf]) {
+        let css_paths: Option<Vec<String>> = paths
+            .iter()
+            .map(|path| {
+                let is_css = path.extension().is_some_and(|ext| ext == "css");
+                let relative = path.strip_prefix(static_dir).ok()?;
+                is_css.then(|| relative.to_string_lossy().replace('\\', "/"))
+            })
+            .collect();
+        match css_paths {
+            Some(paths) if !paths.is_empty() => self.send(ReloadEvent::Css { paths }),
+            _ => self.send(ReloadEvent::Reload),
+        }
+    }
+
+    /// Routes for the WebSocket and the client script. The server mounts these
+    /// ahead of proxy rules, so a proxied `/` cannot take them over.
+    pub fn endpoints(&self) -> BoxedFilter<(Box<dyn Reply>,)> {
+        let live_reload = self.clone();
+        let socket = warp::path(SOCKET_PATH)
+            .and(warp::path::end())
+            .and(warp::ws())
+            .map(move |ws: Ws| {
+                let live_reload = live_reload.clone();
+                Box::new(ws.on_upgrade(move |socket| live_reload.client_session(socket)))
+                    as Box<dyn Reply>
+            });
+
+        let client = warp::path(CLIENT_PATH).and(warp::path::end()).map(|| {
+            Box::new(warp::reply::with_header(
+                CLIENT_SCRIPT,
+                "Content-Type",
+                "text/javascript; charset=utf-8",
+            )) as Box<dyn Reply>
+        });
+
+        socket.or(client).unify().boxed()
+    }
+
+    /// Routes for `/` and `/index.html`, serving `index.html` with the client
+    /// script injected. Everything else falls through to the caller.
+    pub fn pages(&self, static_dir: &Path) -> BoxedFilter<(Box<dyn Reply>,)> {
+        warp::path::end()
+            .or(warp::path("index.html").and(warp::path::end()))
+            .unify()
+            .and(self.index(static_dir))
+            .boxed()
+    }
+
+    /// Serves `index.html` with the client script injected, for any GET or
+    /// HEAD request. The server leaves out the body for HEAD.
+    pub fn index(&self, static_dir: &Path) -> BoxedFilter<(Box<dyn Reply>,)> {
+        let index_path = static_dir.join("index.html");
+        static_files::get_or_head()
+            .and_then(move |_| {
+                let index_path = index_path.clone();
+                async move {
+                    let html = tokio::fs::read_to_string(&index_path)
+                        .await
+                        .map_err(|_| warp::reject::not_found())?;
+                    Ok::<_, warp::Rejection>(
+                        Box::new(warp::reply::html(inject_client(&html))) as Box<dyn Reply>
+                    )
+                }
+            })
+            .boxed()
+    }
+
+    async fn client_session(self, socket: WebSocket) {
+        let mut events = self.sender.subscribe();
+        let (mut outgoing, mut incoming) = socket.split();
+
+        let pending_error = self.last_error.lock().unwrap().clone();
+        if let Some(message) = pending_error {
+            if send_event(&mut outgoing, &ReloadEvent::BuildError { message })
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        if send_event(&mut outgoing, &event).await.is_err() {
+                            break;
+                        }
+                    }
+                    // A slow client missed some events; a reload catches it up.
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        if send_event(&mut outgoing, &ReloadEvent::Reload).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                message = incoming.next() => match message {
+                    Some(Ok(_)) => {}
+                    _ => break,
+                },
+            }
+        }
+    }
+}
+
+async fn send_event<S>(outgoing: &mut S, event: &ReloadEvent) -> Result<(), ()>
+where
+    S: SinkExt<Message> + Unpin,
+{
+    let json = serde_json::to_string(event).map_err(|_| ())?;
+    outgoing.send(Message::text(json)).await.map_err(|_| ())
+}
+
+/// Adds the client script tag before `</body>`, in any case, or at the end if
+/// there is none.
+fn inject_client(html: &str) -> String {
+    let tag = format!("<script src=\"/{}\"></script>\n", CLIENT_PATH);
+    // ASCII lowercasing keeps byte offsets, so the index applies to `html`.
+    match html.to_ascii_lowercase().rfind("</body>") {
+        Some(index) => format!("{}{}{}", &html[..index], tag, &html[index..]),
+        None => format!("{}{}", html, tag),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    const TAG: &str = "<script src=\"/__livereload.js\"></script>\n";
+
+    #[test]
+    fn test_inject_client_goes_before_the_closing_body_tag() {
+        assert_eq!(
+            inject_client("<body><p>hi</p></body>\n</html>"),
+            format!("<body><p>hi</p>{}</body>\n</html>", TAG)
+        );
+        // Only the last `</body>` counts, e.g. not one inside a comment.
+        assert_eq!(
+            inject_client("<!-- </body> --><body></body>"),
+            format!("<!-- </body> --><body>{}</body>", TAG)
+        );
+    }
+
+    #[test]
+    fn test_inject_client_matches_the_body_tag_in_any_case() {
+        assert_eq!(
+            inject_client("<BODY>Ünïcode</BODY></HTML>"),
+            format!("<BODY>Ünïcode{}</BODY></HTML>", TAG)
+        );
+        assert_eq!(
+            inject_client("<body></Body>"),
+            format!("<body>{}</Body>", TAG)
+        );
+    }
+
+    #[test]
+    fn test_inject_client_appends_without_a_body_tag() {
+        assert_eq!(inject_client("<h1>Hi</h1>"), format!("<h1>Hi</h1>{}", TAG));
+        assert_eq!(inject_client(""), TAG);
+    }
+
+    fn next_event(events: &mut broadcast::Receiver<ReloadEvent>) -> ReloadEvent {
+        events.try_recv().expect("an event should have been sent")
+    }
+
+    #[test]
+    fn test_static_files_changed_swaps_stylesheets_in_place() {
+        let live_reload = LiveReload::new();
+        let mut events = live_reload.sender.subscribe();
+        let static_dir = Path::new("/site/static");
+
+        live_reload.static_files_changed(
+            static_dir,
+            &[
+                static_dir.join("css/style.css"),
+                static_dir.join("theme.css"),
+            ],
+        );
+        match next_event(&mut events) {
+            ReloadEvent::Css { paths } => assert_eq!(paths, ["css/style.css", "theme.css"]),
+            event => panic!("Expected a CSS swap, got {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_static_files_changed_reloads_for_anything_but_stylesheets() {
+        let live_reload = LiveReload::new();
+        let mut events = live_reload.sender.subscribe();
+        let static_dir = Path::new("/site/static");
+
+        let batches = [
+            vec![static_dir.join("index.html")],
+            vec![static_dir.join("style.css"), static_dir.join("js/app.js")],
+            // A stylesheet outside the static directory has no URL to swap.
+            vec![PathBuf::from("/elsewhere/style.css")],
+            vec![],
+        ];
+        for paths in batches {
+            live_reload.static_files_changed(static_dir, &paths);
+            assert!(
+                matches!(next_event(&mut events), ReloadEvent::Reload),
+                "{:?} should reload the page",
+                paths
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routes_serve_the_client_and_the_injected_index() {
+        let static_dir = TempDir::new("live-reload", "routes");
+        static_dir.write("index.html", "<body></body>");
+        let live_reload = LiveReload::new();
+        let routes = live_reload
+            .endpoints()
+            .or(live_reload.pages(static_dir.path()))
+            .unify();
+
+        let client = warp::test::request()
+            .path("/__livereload.js")
+            .reply(&routes)
+            .await;
+        assert_eq!(client.status(), 200);
+        assert_eq!(
+            client.headers()["content-type"],
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(client.body(), CLIENT_SCRIPT);
+
+        for path in ["/", "/index.html"] {
+            let index = warp::test::request().path(path).reply(&routes).await;
+            assert_eq!(index.status(), 200);
+            assert_eq!(index.body(), &format!("<body>{}</body>", TAG));
+        }
+
+        // Anything else is left to the static files.
+        assert!(!warp::test::request().path("/app.js").matches(&routes).await);
+    }
+}
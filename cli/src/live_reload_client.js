@@ -0,0 +1,99 @@
+// Injected into index.html by `cli dev --watch`. Reloads the page, hot-swaps
+// the WASM package or swaps stylesheets when the dev server reports changes,
+// and shows build errors.
+(() => {
+    const protocol = location.protocol === 'https:' ? 'wss:' : 'ws:';
+    const url = `${protocol}//${location.host}/__livereload`;
+    const overlayId = '__livereload-overlay';
+
+    function showOverlay(message) {
+        let overlay = document.getElementById(overlayId);
+        if (!overlay) {
+            overlay = document.createElement('pre');
+            overlay.id = overlayId;
+            overlay.style.cssText = [
+                'position:fixed', 'inset:0', 'margin:0', 'padding:2em', 'z-index:2147483647',
+                'overflow:auto', 'background:rgba(20,0,0,0.92)', 'color:#ff8080',
+                'font:14px/1.5 monospace', 'white-space:pre-wrap',
+            ].join(';');
+            overlay.title = 'Click to dismiss';
+            overlay.addEventListener('click', () => overlay.remove());
+            document.body.appendChild(overlay);
+        }
+        overlay.textContent = `Build failed\n\n${message}`;
+    }
+
+    function hideOverlay() {
+        document.getElementById(overlayId)?.remove();
+    }
+
+    // Swaps in the rebuilt WASM package without losing the drawing. Anything
+    // that prevents that, e.g. a changed state schema, falls back to a reload.
+    async function hotReload() {
+        hideOverlay();
+        if (!window.appInstance) {
+            location.reload();
+            return;
+        }
+        try {
+            const { hotReloadApp } = await import('/js/appManager.js');
+            const next = await hotReloadApp(window.appInstance, Date.now().toString());
+            if (!next) {
+                location.reload();
+                return;
+            }
+            window.appInstance = next;
+            console.log('[livereload] WASM package hot-swapped.');
+        } catch (e) {
+            console.warn('[livereload] Hot swap failed, reloading:', e);
+            location.reload();
+        }
+    }
+
+    function swapStylesheets(paths) {
+        const links = document.querySelectorAll('link[rel="stylesheet"]');
+        let swapped = 0;
+        for (const link of links) {
+            const href = new URL(link.href);
+            if (paths.some(path => href.pathname.endsWith(`/${path}`))) {
+                href.searchParams.set('livereload', Date.now().toString());
+                link.href = href.toString();
+                swapped++;
+            }
+        }
+        // A stylesheet we do not link directly (e.g. via @import) changed.
+        if (swapped === 0) {
+            location.reload();
+        }
+    }
+
+    function connect(isReconnect) {
+        const socket = new WebSocket(url);
+        socket.addEventListener('open', () => {
+            // The server restarted while we were disconnected; pick up its output.
+            if (isReconnect) {
+                location.reload();
+            }
+        });
+        socket.addEventListener('message', event => {
+            const message = JSON.parse(event.data);
+            switch (message.type) {
+                case 'reload':
+                    location.reload();
+                    break;
+                case 'hotReload':
+                    hotReload();
+                    break;
+                case 'css':
+                    swapStylesheets(message.paths);
+                    break;
+                case 'buildError':
+                    showOverlay(message.message);
+                    break;
+            }
+        });
+        socket.addEventListener('close', () => setTimeout(() => connect(true), 1000));
+    }
+
+    connect(false);
+})();
@@ -1,69 +1,443 @@
 mod args;
+mod bench;
 mod build;
 mod commands;
+mod config;
+mod coverage;
+mod doctor;
+mod fuzz;
+mod junit;
+mod live_reload;
+mod optimize;
+mod orchestrator;
 mod paths;
 mod process_runner;
+mod proxy;
+mod release;
+mod report;
 mod server;
+mod static_files;
 mod test_runner;
+#[cfg(test)]
+mod test_support;
+mod tls;
+mod watcher;
 
 use anyhow::{Context, Result};
-use args::{Cli, Commands as CliCommands};
+use args::{Cli, Commands as CliCommands, OptimizeArgs, ServerArgs};
+use bench::BenchOptions;
+use build::{BuildBackend, BuildOptions};
 use clap::Parser;
-use paths::get_workspace_root;
+use config::{load_config, ProjectConfig};
+use coverage::CoverageOptions;
+use fuzz::FuzzOptions;
+use orchestrator::FailurePolicy;
+use paths::{find_target_dir, find_workspace_root, ProjectPaths};
+use process_runner::CommandFailed;
+use proxy::ProxyRule;
+use report::progress;
+use server::ServeOptions;
+use std::collections::HashSet;
+use std::process::ExitCode;
+use std::time::Instant;
+use test_runner::TestOptions;
+use tls::resolve_tls;
 
 use commands::{
-    handle_build_command, handle_dev_command, handle_e2e_command, handle_serve_command,
-    handle_test_command,
+    handle_bench_command, handle_build_command, handle_dev_command, handle_doctor_command,
+    handle_e2e_command, handle_release_command, handle_serve_command, handle_test_command,
+    E2eOptions,
 };
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    report::set_json_output(cli.json);
+    let command = cli.command.name();
+    let started = Instant::now();
+    let result = run(cli);
+    report::finish(command, &result, started.elapsed());
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            exit_code(&e)
+        }
+    }
+}
+
+/// The exit code of the program that failed, such as Playwright's, or 1.
+fn exit_code(error: &anyhow::Error) -> ExitCode {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<CommandFailed>())
+        .and_then(|failed| failed.status.code())
+        .and_then(|code| u8::try_from(code).ok())
+        .filter(|&code| code != 0)
+        .map_or(ExitCode::FAILURE, ExitCode::from)
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let workspace_root = find_workspace_root(cli.workspace.as_deref())
+        .context("Failed to determine workspace root")?;
+    let config = load_config(&workspace_root);
+
+    progress!("Workspace root detected as: {}", workspace_root.display());
 
-    let workspace_root = get_workspace_root().context("Failed to determine workspace root")?;
-    println!("Workspace root detected as: {}", workspace_root.display());
+    // The doctor reports a broken configuration instead of stopping at it.
+    if let CliCommands::Doctor = cli.command {
+        return handle_doctor_command(&workspace_root, &config);
+    }
 
-    let app_path = workspace_root.join("app");
-    let static_dir_path = workspace_root.join("static");
-    let static_pkg_dir_path = static_dir_path.join("pkg");
+    let config = config?;
+    let target_dir = find_target_dir(&workspace_root);
+    let paths = ProjectPaths::new(workspace_root, target_dir, &config.paths);
+    let app_path = paths.app.clone();
+    let static_dir_path = paths.static_dir.clone();
+    let static_pkg_dir_path = paths.static_pkg_dir.clone();
 
     match cli.command {
-        CliCommands::Build { wasm_debug } => {
-            handle_build_command(&app_path, &static_pkg_dir_path, wasm_debug)?;
-        }
-        CliCommands::Serve { port, host } => {
-            handle_serve_command(&static_dir_path, &static_pkg_dir_path, port, &host)?;
-        }
-        CliCommands::Dev {
-            port,
-            host,
+        CliCommands::Build {
             wasm_debug,
+            features,
+            backend,
+            optimize,
+        } => {
+            let build = build_options(&config, &paths, wasm_debug, features, backend, &optimize);
+            handle_build_command(&app_path, &static_pkg_dir_path, &build)?;
+        }
+        CliCommands::Serve {
+            server,
+            prod_headers,
         } => {
-            handle_dev_command(
-                &app_path,
+            let (port, host) = listen_address(&server, &config);
+            let options = serve_options(&server, &host, &config, &paths, prod_headers)?;
+            handle_serve_command(
                 &static_dir_path,
                 &static_pkg_dir_path,
+                &config.build.out_name,
                 port,
                 &host,
-                wasm_debug,
+                &options,
             )?;
         }
+        CliCommands::Dev {
+            server,
+            wasm_debug,
+            watch,
+            features,
+            backend,
+        } => {
+            let (port, host) = listen_address(&server, &config);
+            let options = serve_options(&server, &host, &config, &paths, false)?;
+            let build = build_options(
+                &config,
+                &paths,
+                wasm_debug,
+                features,
+                backend,
+                &OptimizeArgs::default(),
+            );
+            handle_dev_command(&paths, port, &host, &options, &build, watch)?;
+        }
+        CliCommands::Release {
+            out_dir,
+            skip_build,
+            features,
+            backend,
+            optimize,
+        } => {
+            let dist_dir = out_dir.unwrap_or_else(|| paths.dist_dir.clone());
+            let build = build_options(&config, &paths, false, features, backend, &optimize);
+            handle_release_command(&paths, &dist_dir, &build, skip_build)?;
+        }
         CliCommands::Test {
             base,
             app,
             browser,
             headless,
+            node,
+            driver_path,
+            webdriver_json,
+            browser_args,
+            timeout,
+            filter,
+            retries,
+            junit,
+            backend,
+            fail_fast,
+            keep_going,
+            jobs,
+            coverage,
+            min_coverage,
+            fuzz,
+            time,
         } => {
-            handle_test_command(&app_path, base, app, browser, headless)?;
+            let browsers = match browser {
+                Some(browser) => vec![browser],
+                None => config.test.browsers(),
+            };
+            let failure_policy = if keep_going || (config.test.keep_going && !fail_fast) {
+                FailurePolicy::KeepGoing
+            } else {
+                FailurePolicy::FailFast
+            };
+            let options = TestOptions {
+                browsers,
+                headless,
+                backend: backend.unwrap_or(config.build.backend),
+                failure_policy,
+                jobs: jobs.map(|jobs| jobs as usize).or(config.test.jobs),
+                coverage: coverage.then(|| CoverageOptions {
+                    output_dir: paths.coverage_dir.clone(),
+                    min_lines: min_coverage.or(config.test.min_coverage),
+                }),
+                fuzz: (!fuzz.is_empty()).then_some(FuzzOptions {
+                    targets: fuzz,
+                    time,
+                }),
+                node,
+                driver_path,
+                webdriver_json,
+                browser_args,
+                webdriver_dir: paths.webdriver_dir.clone(),
+                timeout,
+                filter,
+                retries: retries.unwrap_or(config.test.retries),
+                junit: junit.or_else(|| {
+                    config
+                        .test
+                        .junit
+                        .as_ref()
+                        .map(|junit| paths.workspace_root.join(junit))
+                }),
+            };
+            handle_test_command(&paths.base, &app_path, base, app, &options)?;
         }
+        CliCommands::Doctor => unreachable!("handled above"),
         CliCommands::E2E {
             ui,
             debug,
             headed,
             project,
+            skip_build,
+            release,
+            backend,
             playwright_args,
         } => {
-            handle_e2e_command(&workspace_root, ui, debug, headed, project, playwright_args)?;
+            let build = build_options(
+                &config,
+                &paths,
+                !release,
+                None,
+                backend,
+                &OptimizeArgs::default(),
+            );
+            let serve = ServeOptions {
+                proxy: proxy_rules(&[], &config)?,
+                ..ServeOptions::default()
+            };
+            let options = E2eOptions {
+                ui_mode: ui,
+                debug_mode: debug,
+                headed_mode: headed,
+                project,
+                additional_args: playwright_args,
+            };
+            handle_e2e_command(
+                &paths,
+                &config.build.out_name,
+                (!skip_build).then_some(&build),
+                &serve,
+                &options,
+            )?;
+        }
+        CliCommands::Bench {
+            browser,
+            baseline,
+            save_baseline,
+            max_regression,
+            skip_build,
+            backend,
+        } => {
+            let build = build_options(
+                &config,
+                &paths,
+                false,
+                None,
+                backend,
+                &OptimizeArgs::default(),
+            );
+            let serve = ServeOptions {
+                proxy: proxy_rules(&[], &config)?,
+                ..ServeOptions::default()
+            };
+            let options = BenchOptions {
+                browser,
+                baseline: baseline.unwrap_or_else(|| config.bench.baseline.clone()),
+                save_baseline,
+                max_regression: max_regression.unwrap_or(config.bench.max_regression),
+            };
+            handle_bench_command(
+                &paths,
+                &config.build.out_name,
+                (!skip_build).then_some(&build),
+                &serve,
+                &options,
+            )?;
         }
     }
     Ok(())
 }
+
+/// The port and host from the flags, then `project.toml`, then the defaults.
+fn listen_address(args: &ServerArgs, config: &ProjectConfig) -> (u16, String) {
+    let port = args.port.or(config.server.port).unwrap_or(8080);
+    let host = args
+        .host
+        .clone()
+        .or_else(|| config.server.host.clone())
+        .unwrap_or_else(|| "0.0.0.0".to_string());
+    (port, host)
+}
+
+/// The WASM build settings from `project.toml`, with `--features` replacing
+/// `build.features`. Release builds add `build.release-features`.
+fn build_options(
+    config: &ProjectConfig,
+    paths: &ProjectPaths,
+    debug: bool,
+    cli_features: Option<Vec<String>>,
+    backend: Option<BuildBackend>,
+    optimize: &OptimizeArgs,
+) -> BuildOptions {
+    let mut features = cli_features.unwrap_or_else(|| config.build.features.clone());
+    if !debug {
+        features.extend(config.build.release_features.iter().cloned());
+    }
+    // Keep the first of each feature, in the order given.
+    let mut seen = HashSet::new();
+    features.retain(|feature| seen.insert(feature.clone()));
+    BuildOptions {
+        debug,
+        out_name: config.build.out_name.clone(),
+        features,
+        backend: backend.unwrap_or(config.build.backend),
+        wasm_opt: optimize.wasm_opt.unwrap_or(config.build.wasm_opt),
+        size_budget: optimize
+            .size_budget
+            .or(config.build.size_budget)
+            .map(|budget| budget.0),
+        size_history: paths.size_history.clone(),
+    }
+}
+
+fn serve_options(
+    args: &ServerArgs,
+    host: &str,
+    config: &ProjectConfig,
+    paths: &ProjectPaths,
+    prod_headers: bool,
+) -> Result<ServeOptions> {
+    if let Some(page) = &args.not_found_page {
+        if !page.is_file() {
+            anyhow::bail!("404 page '{}' does not exist.", page.display());
+        }
+    }
+    Ok(ServeOptions {
+        prod_headers,
+        tls: resolve_tls(
+            args.https,
+            args.cert.clone(),
+            args.key.clone(),
+            host,
+            &paths.dev_cert_dir,
+        )?,
+        spa_fallback: args.spa,
+        headers: args.headers.clone(),
+        cross_origin_isolated: args.cross_origin_isolated,
+        not_found_page: args.not_found_page.clone(),
+        proxy: proxy_rules(&args.proxy, config)?,
+    })
+}
+
+/// The proxy rules from `project.toml`, with `--proxy` flags replacing any
+/// rule for the same path.
+fn proxy_rules(cli_rules: &[ProxyRule], config: &ProjectConfig) -> Result<Vec<ProxyRule>> {
+    let mut rules = Vec::new();
+    for (prefix, target) in &config.server.proxy {
+        let rule = ProxyRule::new(prefix, target)
+            .with_context(|| format!("Invalid proxy rule in {}", config::CONFIG_FILE))?;
+        if !cli_rules.iter().any(|r| r.prefix == rule.prefix) {
+            rules.push(rule);
+        }
+    }
+    rules.extend(cli_rules.iter().cloned());
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimize::{ByteSize, WasmOptLevel};
+    use std::path::PathBuf;
+
+    fn features(config: &ProjectConfig, debug: bool, cli: Option<&[&str]>) -> Vec<String> {
+        let paths = ProjectPaths::new(
+            PathBuf::from("/ws"),
+            PathBuf::from("/ws/target"),
+            &config.paths,
+        );
+        let cli = cli.map(|features| features.iter().map(|f| f.to_string()).collect());
+        build_options(config, &paths, debug, cli, None, &OptimizeArgs::default()).features
+    }
+
+    #[test]
+    fn test_build_features_are_deduplicated_in_order() {
+        let mut config = ProjectConfig::default();
+        config.build.features = vec!["b".to_string(), "a".to_string(), "b".to_string()];
+        config.build.release_features = vec!["c".to_string(), "a".to_string()];
+
+        assert_eq!(features(&config, true, None), ["b", "a"]);
+        assert_eq!(features(&config, false, None), ["b", "a", "c"]);
+        // `--features` replaces `build.features`, but not the release features.
+        assert_eq!(
+            features(&config, false, Some(&["a", "x", "a", "c"])),
+            ["a", "x", "c"]
+        );
+        assert!(features(&config, true, Some(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_build_options_fall_back_to_the_config() {
+        let mut config = ProjectConfig::default();
+        config.build.backend = BuildBackend::Cargo;
+        config.build.wasm_opt = WasmOptLevel::Oz;
+        config.build.size_budget = Some(ByteSize(1000));
+        let paths = ProjectPaths::new(
+            PathBuf::from("/ws"),
+            PathBuf::from("/ws/target"),
+            &config.paths,
+        );
+
+        let options = build_options(&config, &paths, false, None, None, &OptimizeArgs::default());
+        assert_eq!(options.backend, BuildBackend::Cargo);
+        assert_eq!(options.wasm_opt, WasmOptLevel::Oz);
+        assert_eq!(options.size_budget, Some(1000));
+
+        let flags = OptimizeArgs {
+            wasm_opt: Some(WasmOptLevel::O2),
+            size_budget: Some(ByteSize(2000)),
+        };
+        let options = build_options(
+            &config,
+            &paths,
+            false,
+            None,
+            Some(BuildBackend::WasmPack),
+            &flags,
+        );
+        assert_eq!(options.backend, BuildBackend::WasmPack);
+        assert_eq!(options.wasm_opt, WasmOptLevel::O2);
+        assert_eq!(options.size_budget, Some(2000));
+    }
+}
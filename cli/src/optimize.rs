@@ -0,0 +1,444 @@
+use crate::build::BuildOptions;
+use crate::process_runner::{run_command, CommandConfig};
+use crate::report::progress;
+use crate::static_files::{compress_with_level, Encoding};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The optimization level `wasm-opt` runs with after a release build.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum WasmOptLevel {
+    /// Skip wasm-opt; custom sections are still stripped.
+    Off,
+    O0,
+    O1,
+    O2,
+    O3,
+    O4,
+    /// Optimize for size.
+    #[default]
+    Os,
+    /// Optimize aggressively for size.
+    Oz,
+}
+
+impl WasmOptLevel {
+    fn flag(self) -> Option<&'static str> {
+        match self {
+            WasmOptLevel::Off => None,
+            WasmOptLevel::O0 => Some("-O0"),
+            WasmOptLevel::O1 => Some("-O1"),
+            WasmOptLevel::O2 => Some("-O2"),
+            WasmOptLevel::O3 => Some("-O3"),
+            WasmOptLevel::O4 => Some("-O4"),
+            WasmOptLevel::Os => Some("-Os"),
+            WasmOptLevel::Oz => Some("-Oz"),
+        }
+    }
+}
+
+impl FromStr for WasmOptLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(WasmOptLevel::Off),
+            "0" => Ok(WasmOptLevel::O0),
+            "1" => Ok(WasmOptLevel::O1),
+            "2" => Ok(WasmOptLevel::O2),
+            "3" => Ok(WasmOptLevel::O3),
+            "4" => Ok(WasmOptLevel::O4),
+            "s" => Ok(WasmOptLevel::Os),
+            "z" => Ok(WasmOptLevel::Oz),
+            _ => Err(format!(
+                "invalid wasm-opt level '{}', expected one of off, 0, 1, 2, 3, 4, s, z",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for WasmOptLevel {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// A size in bytes, written as a number with an optional unit: `350000`,
+/// `350KB`, `512 KiB` or `1.5MiB`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawByteSize")]
+pub struct ByteSize(pub u64);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawByteSize {
+    Bytes(u64),
+    Text(String),
+}
+
+impl TryFrom<RawByteSize> for ByteSize {
+    type Error = String;
+
+    fn try_from(raw: RawByteSize) -> Result<Self, Self::Error> {
+        match raw {
+            RawByteSize::Bytes(bytes) => Ok(ByteSize(bytes)),
+            RawByteSize::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 1.0,
+            "kb" => 1e3,
+            "mb" => 1e6,
+            "kib" => 1024.0,
+            "mib" => 1024.0 * 1024.0,
+            _ => {
+                return Err(format!(
+                    "invalid size '{}', expected e.g. 500KB or 1.5MiB",
+                    s
+                ))
+            }
+        };
+        let number: f64 = number
+            .parse()
+            .map_err(|_| format!("invalid size '{}', expected e.g. 500KB or 1.5MiB", s))?;
+        Ok(ByteSize((number * multiplier).round() as u64))
+    }
+}
+
+/// Formats a byte count for people, e.g. `1.2 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const KIB: f64 = 1024.0;
+    let bytes_f = bytes as f64;
+    if bytes_f >= KIB * KIB {
+        format!("{:.2} MiB", bytes_f / (KIB * KIB))
+    } else if bytes_f >= KIB {
+        format!("{:.1} KiB", bytes_f / KIB)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// The size of a WASM file as stored and as sent over the wire.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct WasmSizes {
+    raw: u64,
+    gzip: u64,
+    brotli: u64,
+}
+
+impl WasmSizes {
+    fn measure(data: &[u8]) -> Result<Self> {
+        Ok(Self {
+            raw: data.len() as u64,
+            gzip: compress_with_level(data, Encoding::Gzip, 9)?.len() as u64,
+            brotli: compress_with_level(data, Encoding::Brotli, 11)?.len() as u64,
+        })
+    }
+}
+
+/// The optimization stage of a release build: runs `wasm-opt` if it is
+/// installed, strips custom sections, prints a size report against the
+/// previous build and enforces the size budget.
+pub fn optimize_wasm(wasm_path: &Path, options: &BuildOptions) -> Result<()> {
+    let file_name = wasm_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let before = fs::read(wasm_path)
+        .with_context(|| format!("Failed to read '{}'", wasm_path.display()))?
+        .len() as u64;
+
+    if let Some(flag) = options.wasm_opt.flag() {
+        if std::process::Command::new("wasm-opt")
+            .arg("--version")
+            .output()
+            .is_ok()
+        {
+            let path = wasm_path
+                .to_str()
+                .context("WASM file path is not valid UTF-8")?;
+            let cmd_config = CommandConfig {
+                stage: "wasm-opt",
+                program_name: "wasm-opt",
+                args: &[flag, "--strip-debug", "--strip-producers", path, "-o", path],
+                current_dir: None,
+                env_vars: None,
+                inherit_stdio: true,
+                capture: true,
+                cargo_diagnostics: false,
+            };
+            run_command(cmd_config).context("wasm-opt failed")?;
+        } else {
+            progress!(
+                "Warning: wasm-opt not found, skipping optimization. Install binaryen to enable it: https://github.com/WebAssembly/binaryen"
+            );
+        }
+    }
+
+    let optimized = fs::read(wasm_path)?;
+    let stripped = strip_custom_sections(&optimized)
+        .with_context(|| format!("Failed to strip custom sections from '{}'", file_name))?;
+    if stripped.len() != optimized.len() {
+        fs::write(wasm_path, &stripped)?;
+    }
+
+    let sizes = WasmSizes::measure(&stripped)?;
+    let mut history = read_history(&options.size_history);
+    let previous = history.get(&file_name).copied();
+    print_report(&file_name, before, &sizes, previous.as_ref());
+
+    if let Some(budget) = options.size_budget {
+        check_budget(&file_name, sizes.raw, budget)?;
+    }
+
+    // Only builds within budget are recorded, so a failing build keeps
+    // being compared against the last good one.
+    history.insert(file_name, sizes);
+    write_history(&options.size_history, &history)
+}
+
+/// Fails if the WASM file at `wasm_path` is larger than `budget` bytes. For
+/// packages that were built earlier, which `optimize_wasm` did not see.
+pub fn check_size_budget(wasm_path: &Path, budget: u64) -> Result<()> {
+    let size = fs::metadata(wasm_path)
+        .with_context(|| format!("Failed to read '{}'", wasm_path.display()))?
+        .len();
+    let file_name = wasm_path.file_name().unwrap_or_default().to_string_lossy();
+    progress!("\nSize of {}: {}", file_name, format_size(size));
+    check_budget(&file_name, size, budget)
+}
+
+fn check_budget(file_name: &str, size: u64, budget: u64) -> Result<()> {
+    if size > budget {
+        bail!(
+            "{} is {}, which exceeds the size budget of {} by {}",
+            file_name,
+            format_size(size),
+            format_size(budget),
+            format_size(size - budget)
+        );
+    }
+    let used = size as f64 / budget as f64 * 100.0;
+    progress!("  budget  {:>10}  ({:.0}% used)", format_size(budget), used);
+    Ok(())
+}
+
+fn print_report(file_name: &str, before: u64, sizes: &WasmSizes, previous: Option<&WasmSizes>) {
+    progress!("\nSize of {}:", file_name);
+    if before != sizes.raw {
+        progress!(
+            "  ({} before optimization, {:.1}% smaller)",
+            format_size(before),
+            (1.0 - sizes.raw as f64 / before as f64) * 100.0
+        );
+    }
+    let rows = [
+        ("raw", sizes.raw, previous.map(|p| p.raw)),
+        ("gzip", sizes.gzip, previous.map(|p| p.gzip)),
+        ("brotli", sizes.brotli, previous.map(|p| p.brotli)),
+    ];
+    for (label, size, previous) in rows {
+        let diff = match previous {
+            Some(previous) if previous == size => "  (unchanged)".to_string(),
+            Some(previous) if size > previous => {
+                format!("  (+{} since the last build)", format_size(size - previous))
+            }
+            Some(previous) => format!("  (-{} since the last build)", format_size(previous - size)),
+            None => String::new(),
+        };
+        progress!("  {:<6}  {:>10}{}", label, format_size(size), diff);
+    }
+}
+
+fn read_history(path: &Path) -> BTreeMap<String, WasmSizes> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn write_history(path: &Path, history: &BTreeMap<String, WasmSizes>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(history)?)
+        .with_context(|| format!("Failed to write '{}'", path.display()))
+}
+
+/// Removes every custom section (names, producers, DWARF, source map URLs)
+/// from a WASM module. None of them are needed to run it.
+fn strip_custom_sections(wasm: &[u8]) -> Result<Vec<u8>> {
+    const HEADER_LENGTH: usize = 8;
+    if wasm.len() < HEADER_LENGTH || &wasm[..4] != b"\0asm" {
+        bail!("not a WebAssembly module");
+    }
+    let mut output = wasm[..HEADER_LENGTH].to_vec();
+    let mut position = HEADER_LENGTH;
+    while position < wasm.len() {
+        let id = wasm[position];
+        let (size, size_length) = read_leb128(&wasm[position + 1..])?;
+        let end = (position + 1 + size_length)
+            .checked_add(size)
+            .filter(|end| *end <= wasm.len())
+            .context("section extends past the end of the module")?;
+        if id != 0 {
+            output.extend_from_slice(&wasm[position..end]);
+        }
+        position = end;
+    }
+    Ok(output)
+}
+
+/// Reads an unsigned LEB128 number, returning it and the number of bytes read.
+fn read_leb128(bytes: &[u8]) -> Result<(usize, usize)> {
+    let mut value = 0usize;
+    for (i, byte) in bytes.iter().take(5).enumerate() {
+        value |= ((byte & 0x7f) as usize) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    bail!("invalid section size")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &[u8] = b"\0asm\x01\0\0\0";
+
+    fn section(id: u8, size: &[u8], contents: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![id];
+        bytes.extend_from_slice(size);
+        bytes.extend_from_slice(contents);
+        bytes
+    }
+
+    fn custom_section(name: &str, payload: &[u8]) -> Vec<u8> {
+        let mut contents = vec![name.len() as u8];
+        contents.extend_from_slice(name.as_bytes());
+        contents.extend_from_slice(payload);
+        section(0, &[contents.len() as u8], &contents)
+    }
+
+    #[test]
+    fn test_strip_custom_sections_keeps_only_known_sections() {
+        // A type section with one `() -> ()` function type.
+        let types = section(1, &[4], &[1, 0x60, 0, 0]);
+        // A data section whose size needs a two-byte LEB128.
+        let data_contents = vec![0; 200];
+        let data = section(11, &[0xc8, 0x01], &data_contents);
+
+        let mut module = HEADER.to_vec();
+        module.extend(custom_section("name", b"\x01\x02\x03"));
+        module.extend(&types);
+        module.extend(custom_section("producers", &[0; 20]));
+        module.extend(&data);
+        module.extend(custom_section("sourceMappingURL", b"\x07app.map"));
+
+        let mut expected = HEADER.to_vec();
+        expected.extend(&types);
+        expected.extend(&data);
+        assert_eq!(strip_custom_sections(&module).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_strip_custom_sections_leaves_a_bare_module_alone() {
+        assert_eq!(strip_custom_sections(HEADER).unwrap(), HEADER);
+    }
+
+    #[test]
+    fn test_strip_custom_sections_rejects_invalid_modules() {
+        let error = strip_custom_sections(b"<html>\0\0").unwrap_err();
+        assert!(error.to_string().contains("not a WebAssembly module"));
+        assert!(strip_custom_sections(b"\0asm").is_err());
+
+        let mut truncated = HEADER.to_vec();
+        truncated.extend(section(1, &[10], &[1, 0x60]));
+        let error = strip_custom_sections(&truncated).unwrap_err();
+        assert!(error.to_string().contains("past the end"));
+
+        let mut unterminated = HEADER.to_vec();
+        unterminated.extend([1, 0x80]);
+        let error = strip_custom_sections(&unterminated).unwrap_err();
+        assert!(error.to_string().contains("invalid section size"));
+    }
+
+    #[test]
+    fn test_read_leb128() {
+        assert_eq!(read_leb128(&[0]).unwrap(), (0, 1));
+        assert_eq!(read_leb128(&[0x7f, 0xff]).unwrap(), (127, 1));
+        assert_eq!(read_leb128(&[0x80, 0x01]).unwrap(), (128, 2));
+        assert_eq!(read_leb128(&[0xe5, 0x8e, 0x26]).unwrap(), (624_485, 3));
+        assert_eq!(
+            read_leb128(&[0xff, 0xff, 0xff, 0xff, 0x0f]).unwrap(),
+            (u32::MAX as usize, 5)
+        );
+    }
+
+    #[test]
+    fn test_read_leb128_rejects_unterminated_numbers() {
+        assert!(read_leb128(&[]).is_err());
+        assert!(read_leb128(&[0x80, 0x80]).is_err());
+        // A u32 never needs more than five bytes.
+        assert!(read_leb128(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x01]).is_err());
+    }
+
+    #[test]
+    fn test_byte_size_units() {
+        let parse = |s: &str| s.parse::<ByteSize>().unwrap().0;
+        assert_eq!(parse("350000"), 350_000);
+        assert_eq!(parse("12b"), 12);
+        assert_eq!(parse("500KB"), 500_000);
+        assert_eq!(parse("500KiB"), 512_000);
+        assert_eq!(parse("2 kb"), 2_000);
+        assert_eq!(parse("3MB"), 3_000_000);
+        assert_eq!(parse("1MiB"), 1_048_576);
+        assert_eq!(parse(" 1.5MiB "), 1_572_864);
+        assert_eq!(parse("0.5kb"), 500);
+        assert_eq!(parse("1.0005KB"), 1_001);
+    }
+
+    #[test]
+    fn test_byte_size_rejects_invalid_sizes() {
+        for invalid in ["", "KB", "500GB", "1.2.3MB", "-5KB", "five"] {
+            let error = invalid.parse::<ByteSize>().unwrap_err();
+            assert!(error.contains("invalid size"), "{}: {}", invalid, error);
+        }
+    }
+
+    #[test]
+    fn test_check_budget() {
+        assert!(check_budget("app_bg.wasm", 1000, 1000).is_ok());
+        let error = check_budget("app_bg.wasm", 1024 + 512, 1024).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "app_bg.wasm is {}, which exceeds the size budget of {} by {}",
+                format_size(1536),
+                format_size(1024),
+                format_size(512)
+            )
+        );
+    }
+}
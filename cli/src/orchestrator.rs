@@ -0,0 +1,408 @@
+use crate::process_runner::{
+    join_output, pipe_output, read_output, with_message_format, OutputLog,
+};
+use crate::report::{self, progress, Diagnostic, StageRecord};
+use anyhow::{bail, Context, Result};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How often running jobs are checked for exits and Ctrl+C.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How long stopped jobs get to exit after SIGTERM before they are killed.
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Ctrl+C presses (and SIGTERM/SIGHUP) received so far.
+static INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+static INSTALL_HANDLER: Once = Once::new();
+
+/// An external program that runs alongside others, its output prefixed
+/// with its name. The name is also its stage in the summary.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub name: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub current_dir: Option<PathBuf>,
+    pub env_vars: HashMap<String, String>,
+    /// The program is cargo: ask it for JSON messages and collect its diagnostics.
+    pub cargo_diagnostics: bool,
+    /// How many times to run the job again after it fails.
+    pub retries: u32,
+    /// Keep the output in the job's [`JobOutcome`], e.g. to find test results in it.
+    pub keep_output: bool,
+}
+
+/// What to do with the other jobs once one fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Stop the running jobs and start no new ones.
+    FailFast,
+    /// Run every job, then report all that failed.
+    KeepGoing,
+}
+
+/// How a job that started ended.
+#[derive(Debug, Clone)]
+pub struct JobOutcome {
+    pub name: String,
+    pub success: bool,
+    /// Stopped because another job failed or on Ctrl+C.
+    pub cancelled: bool,
+    /// How long the last attempt ran.
+    pub elapsed: Duration,
+    /// 1, plus the retries it took.
+    pub attempts: u32,
+    /// The output of the last attempt, if the job asked to keep it.
+    pub output: Vec<String>,
+}
+
+/// What [`run_jobs`] did: the jobs that ran, in the order they ended.
+#[derive(Debug)]
+pub struct JobsOutcome {
+    pub jobs: Vec<JobOutcome>,
+    pub interrupted: bool,
+}
+
+impl JobsOutcome {
+    /// Fails if the run was interrupted or a job failed.
+    pub fn into_result(self) -> Result<()> {
+        if self.interrupted {
+            bail!("Interrupted");
+        }
+        let failed: Vec<String> = self
+            .jobs
+            .into_iter()
+            .filter(|job| !job.success && !job.cancelled)
+            .map(|job| job.name)
+            .collect();
+        if !failed.is_empty() {
+            bail!("Failed: {}", failed.join(", "));
+        }
+        Ok(())
+    }
+}
+
+struct RunningJob {
+    job: Job,
+    attempt: u32,
+    command: String,
+    child: Child,
+    started: Instant,
+    readers: Vec<JoinHandle<Vec<Diagnostic>>>,
+    output: Option<OutputLog>,
+}
+
+/// Runs `jobs`, at most `max_parallel` at a time, until all have finished or
+/// `policy` says to stop. A failed job with retries left starts again before
+/// the policy applies. Each job runs in its own process group, so stopping
+/// it, on failure or on Ctrl+C, also stops whatever it started, such as a
+/// WebDriver.
+pub fn run_jobs(jobs: Vec<Job>, policy: FailurePolicy, max_parallel: usize) -> JobsOutcome {
+    install_interrupt_handler();
+    let interrupts_before = INTERRUPTS.load(Ordering::SeqCst);
+    let prefix_width = jobs.iter().map(|job| job.name.len()).max().unwrap_or(0);
+
+    // Each job with its attempt number.
+    let mut pending: VecDeque<(Job, u32)> = jobs.into_iter().map(|job| (job, 1)).collect();
+    let mut running: Vec<RunningJob> = Vec::new();
+    let mut outcomes: Vec<JobOutcome> = Vec::new();
+    let failed = |outcomes: &[JobOutcome]| outcomes.iter().any(|job| !job.success);
+
+    let interrupted = loop {
+        let interrupted = INTERRUPTS.load(Ordering::SeqCst) > interrupts_before;
+        let mut index = 0;
+        while index < running.len() {
+            let status = match try_finish(&mut running[index].child) {
+                Ok(None) => {
+                    index += 1;
+                    continue;
+                }
+                Ok(Some(status)) => Some(status),
+                Err(_) => None,
+            };
+            let job = running.remove(index);
+            let passed = status.is_some_and(|status| status.success());
+            if !passed && !interrupted && job.attempt <= job.job.retries {
+                let attempt = job.attempt + 1;
+                let retry = job.job.clone();
+                progress!(
+                    "[{}] failed{}, retrying (attempt {} of {})",
+                    retry.name,
+                    status.map(|s| format!(" with {}", s)).unwrap_or_default(),
+                    attempt,
+                    retry.retries + 1
+                );
+                discard_attempt(job);
+                pending.push_front((retry, attempt));
+                continue;
+            }
+            outcomes.push(finish_job(job, status, false));
+        }
+
+        if interrupted || (policy == FailurePolicy::FailFast && failed(&outcomes)) {
+            outcomes.extend(stop_jobs(std::mem::take(&mut running)));
+            break interrupted;
+        }
+
+        while running.len() < max_parallel.max(1) {
+            let Some((job, attempt)) = pending.pop_front() else {
+                break;
+            };
+            match start_job(&job, attempt, prefix_width) {
+                Ok(started) => running.push(started),
+                Err(e) => {
+                    let message = format!("{:#}", e);
+                    eprintln!("[{}] {}", job.name, message);
+                    outcomes.push(JobOutcome {
+                        name: job.name,
+                        success: false,
+                        cancelled: false,
+                        elapsed: Duration::ZERO,
+                        attempts: attempt,
+                        output: vec![message],
+                    });
+                    if policy == FailurePolicy::FailFast {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if running.is_empty() && pending.is_empty() {
+            break false;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    if !pending.is_empty() {
+        let skipped: Vec<String> = pending.into_iter().map(|(job, _)| job.name).collect();
+        progress!("Not started: {}", skipped.join(", "));
+    }
+    JobsOutcome {
+        jobs: outcomes,
+        interrupted,
+    }
+}
+
+fn start_job(job: &Job, attempt: u32, prefix_width: usize) -> Result<RunningJob> {
+    let args: Vec<&str> = job.args.iter().map(String::as_str).collect();
+    let args = if job.cargo_diagnostics {
+        with_message_format(&args)
+    } else {
+        args
+    };
+    let command = format!("{} {}", job.program, args.join(" "));
+
+    let mut cmd = Command::new(&job.program);
+    cmd.args(&args).envs(&job.env_vars).stdin(Stdio::null());
+    if let Some(dir) = &job.current_dir {
+        cmd.current_dir(dir);
+    }
+    pipe_output(&mut cmd);
+    // Its own process group, so Ctrl+C reaches us alone and we decide what stops.
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    progress!("[{}] Executing: {}", job.name, command);
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to execute command: {}", command))?;
+    let prefix = format!("[{:<width$}] ", job.name, width = prefix_width);
+    let output = job.keep_output.then(OutputLog::default);
+    let readers = read_output(&mut child, job.cargo_diagnostics, &prefix, output.clone());
+    Ok(RunningJob {
+        job: job.clone(),
+        attempt,
+        command,
+        child,
+        started: Instant::now(),
+        readers,
+        output,
+    })
+}
+
+/// Checks whether the job has exited, without blocking. Anything it left
+/// running in its group, like a WebDriver, would keep its output open, so
+/// the group is killed first. That happens before the job is reaped: until
+/// then its pid, which is also the group id, cannot go to another process.
+fn try_finish(child: &mut Child) -> std::io::Result<Option<ExitStatus>> {
+    #[cfg(unix)]
+    {
+        if !has_exited(child)? {
+            return Ok(None);
+        }
+        signal_group(child, libc::SIGKILL);
+    }
+    child.try_wait()
+}
+
+/// Whether the child has exited, leaving it for `Child::try_wait` to reap.
+#[cfg(unix)]
+fn has_exited(child: &Child) -> std::io::Result<bool> {
+    // SAFETY: siginfo_t is plain data, for which all zeroes is a valid value.
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    // SAFETY: `info` is a valid siginfo_t to write to. WNOWAIT leaves the
+    // child waitable, so std still reaps it.
+    let result = unsafe {
+        libc::waitid(
+            libc::P_PID,
+            child.id() as libc::id_t,
+            &mut info,
+            libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+        )
+    };
+    if result == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // Without an exited child, WNOHANG leaves `info` zeroed.
+    Ok(info.si_signo != 0)
+}
+
+/// Cleans up after a failed attempt that is going to be retried.
+fn discard_attempt(job: RunningJob) {
+    join_output(job.readers);
+}
+
+/// Records the stage of a job that has exited and returns how it went.
+fn finish_job(job: RunningJob, status: Option<ExitStatus>, cancelled: bool) -> JobOutcome {
+    let diagnostics = join_output(job.readers);
+    let name = job.job.name;
+    let success = status.is_some_and(|status| status.success());
+    let elapsed = job.started.elapsed();
+    let attempts = if job.attempt > 1 {
+        format!(" on attempt {}", job.attempt)
+    } else {
+        String::new()
+    };
+    if success {
+        progress!(
+            "[{}] passed in {:.1}s{}",
+            name,
+            elapsed.as_secs_f64(),
+            attempts
+        );
+    } else if cancelled {
+        progress!("[{}] stopped", name);
+    } else {
+        match status {
+            Some(status) => progress!("[{}] failed with {}{}", name, status, attempts),
+            None => progress!("[{}] failed{}", name, attempts),
+        }
+    }
+    report::record_stage(StageRecord {
+        name: name.clone(),
+        command: Some(job.command),
+        success,
+        cancelled: cancelled && !success,
+        elapsed_ms: elapsed.as_millis(),
+        diagnostics,
+    });
+    let output = job
+        .output
+        .map(|log| std::mem::take(&mut *log.lock().unwrap_or_else(|e| e.into_inner())))
+        .unwrap_or_default();
+    JobOutcome {
+        name,
+        success,
+        cancelled: cancelled && !success,
+        elapsed,
+        attempts: job.attempt,
+        output,
+    }
+}
+
+/// Asks every job to terminate, then kills those still running after the
+/// grace period or on a second Ctrl+C.
+fn stop_jobs(mut running: Vec<RunningJob>) -> Vec<JobOutcome> {
+    let mut outcomes = Vec::new();
+    if running.is_empty() {
+        return outcomes;
+    }
+    for job in &mut running {
+        terminate(&mut job.child);
+    }
+    let interrupts = INTERRUPTS.load(Ordering::SeqCst);
+    let deadline = Instant::now() + STOP_GRACE_PERIOD;
+    while !running.is_empty() {
+        if Instant::now() >= deadline || INTERRUPTS.load(Ordering::SeqCst) > interrupts {
+            for job in &mut running {
+                kill(&mut job.child);
+            }
+        }
+        let mut index = 0;
+        while index < running.len() {
+            match try_finish(&mut running[index].child) {
+                Ok(None) => index += 1,
+                Ok(Some(status)) => {
+                    outcomes.push(finish_job(running.remove(index), Some(status), true));
+                }
+                Err(_) => {
+                    outcomes.push(finish_job(running.remove(index), None, true));
+                }
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    outcomes
+}
+
+/// Sends SIGTERM to the job's process group.
+#[cfg(unix)]
+fn terminate(child: &mut Child) {
+    signal_group(child, libc::SIGTERM);
+}
+
+#[cfg(not(unix))]
+fn terminate(child: &mut Child) {
+    kill(child);
+}
+
+/// Kills the job's process group.
+#[cfg(unix)]
+fn kill(child: &mut Child) {
+    signal_group(child, libc::SIGKILL);
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) {
+    // Also ends the processes the job started, which `Child::kill` leaves running.
+    let _ = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &child.id().to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    let _ = child.kill();
+}
+
+#[cfg(unix)]
+fn signal_group(child: &Child, signal: libc::c_int) {
+    // The child leads its group, so the group id is its pid.
+    let Ok(pgid) = libc::pid_t::try_from(child.id()) else {
+        return;
+    };
+    // SAFETY: kill(2) has no memory-safety requirements; a negative pid
+    // addresses the process group.
+    unsafe {
+        libc::kill(-pgid, signal);
+    }
+}
+
+/// Counts Ctrl+C instead of exiting, so `run_jobs` can stop its jobs first.
+fn install_interrupt_handler() {
+    INSTALL_HANDLER.call_once(|| {
+        let installed = ctrlc::set_handler(|| {
+            INTERRUPTS.fetch_add(1, Ordering::SeqCst);
+        });
+        if let Err(e) = installed {
+            eprintln!("Warning: Failed to handle Ctrl+C: {}", e);
+        }
+    });
+}
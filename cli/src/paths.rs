@@ -1,10 +1,146 @@
-use anyhow::Result;
-use std::path::PathBuf;
+use crate::config::PathsConfig;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
-pub fn get_workspace_root() -> Result<PathBuf> {
-    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    manifest_dir
+/// Finds the workspace root: the `--workspace` directory if given, otherwise
+/// the nearest ancestor of the current directory whose `Cargo.toml` has a
+/// `[workspace]` table, falling back to `cargo locate-project --workspace`.
+pub fn find_workspace_root(workspace_override: Option<&Path>) -> Result<PathBuf> {
+    if let Some(dir) = workspace_override {
+        let manifest = dir.join("Cargo.toml");
+        if !manifest.is_file() {
+            bail!(
+                "No Cargo.toml found in --workspace directory '{}'",
+                dir.display()
+            );
+        }
+        return dir
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve '{}'", dir.display()));
+    }
+
+    let current_dir = env::current_dir().context("Failed to read the current directory")?;
+    for dir in current_dir.ancestors() {
+        if is_workspace_manifest(&dir.join("Cargo.toml")) {
+            return Ok(dir.to_path_buf());
+        }
+    }
+    locate_with_cargo(&current_dir).with_context(|| {
+        format!(
+            "No Cargo workspace found from '{}'. Run the CLI inside the project or pass --workspace <DIR>.",
+            current_dir.display()
+        )
+    })
+}
+
+fn is_workspace_manifest(manifest: &Path) -> bool {
+    fs::read_to_string(manifest)
+        .ok()
+        .and_then(|text| text.parse::<toml::Table>().ok())
+        .is_some_and(|table| table.contains_key("workspace"))
+}
+
+/// Asks Cargo, which also understands single-crate projects and members
+/// that point at their workspace with `package.workspace`.
+fn locate_with_cargo(current_dir: &Path) -> Result<PathBuf> {
+    let output = Command::new("cargo")
+        .args(["locate-project", "--workspace", "--message-format", "plain"])
+        .current_dir(current_dir)
+        .output()
+        .context("Failed to run cargo locate-project")?;
+    if !output.status.success() {
+        bail!(
+            "cargo locate-project failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let manifest = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    manifest
         .parent()
-        .ok_or_else(|| anyhow::anyhow!("Failed to get parent of CARGO_MANIFEST_DIR"))
-        .map(PathBuf::from)
+        .map(Path::to_path_buf)
+        .ok_or_else(|| anyhow!("cargo locate-project returned '{}'", manifest.display()))
+}
+
+/// Finds Cargo's target directory, which `CARGO_TARGET_DIR` or
+/// `build.target-dir` in `.cargo/config.toml` may move away from `target`.
+/// Falls back to `target` when `cargo metadata` cannot tell.
+pub fn find_target_dir(workspace_root: &Path) -> PathBuf {
+    target_dir_from_cargo(workspace_root).unwrap_or_else(|_| workspace_root.join("target"))
+}
+
+fn target_dir_from_cargo(workspace_root: &Path) -> Result<PathBuf> {
+    #[derive(Deserialize)]
+    struct Metadata {
+        target_directory: PathBuf,
+    }
+
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .current_dir(workspace_root)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .context("Failed to run cargo metadata")?;
+    if !output.status.success() {
+        bail!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let metadata: Metadata = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse the output of cargo metadata")?;
+    Ok(metadata.target_directory)
+}
+
+/// The project directories the CLI works with, resolved against the workspace root.
+#[derive(Debug, Clone)]
+pub struct ProjectPaths {
+    pub workspace_root: PathBuf,
+    pub app: PathBuf,
+    pub base: PathBuf,
+    pub static_dir: PathBuf,
+    pub static_pkg_dir: PathBuf,
+    /// Where `cli release` writes the deployable site.
+    pub dist_dir: PathBuf,
+    /// Cargo's target directory. The CLI keeps its own files next to Cargo's.
+    pub target_dir: PathBuf,
+    /// Where the sizes of the last release build are recorded.
+    pub size_history: PathBuf,
+    /// Where the generated self-signed HTTPS certificate is cached.
+    pub dev_cert_dir: PathBuf,
+    /// Where `cli test --coverage` writes its reports.
+    pub coverage_dir: PathBuf,
+    /// Where criterion writes the results of the base benchmarks.
+    pub criterion_dir: PathBuf,
+    /// Where `cli bench --save-baseline` keeps the saved results.
+    pub bench_baselines_dir: PathBuf,
+    /// Where `cli test` writes the WebDriver capabilities of each browser.
+    pub webdriver_dir: PathBuf,
+}
+
+impl ProjectPaths {
+    pub fn new(workspace_root: PathBuf, target_dir: PathBuf, config: &PathsConfig) -> Self {
+        let static_dir = workspace_root.join(&config.static_dir);
+        Self {
+            app: workspace_root.join(&config.app),
+            base: workspace_root.join(&config.base),
+            static_pkg_dir: match &config.out_dir {
+                Some(out_dir) => workspace_root.join(out_dir),
+                None => static_dir.join("pkg"),
+            },
+            dist_dir: workspace_root.join(&config.dist),
+            size_history: target_dir.join("wasm-size.json"),
+            dev_cert_dir: target_dir.join("dev-certs"),
+            coverage_dir: target_dir.join("coverage"),
+            criterion_dir: target_dir.join("criterion"),
+            bench_baselines_dir: target_dir.join("bench-baselines"),
+            webdriver_dir: target_dir.join("webdriver"),
+            target_dir,
+            static_dir,
+            workspace_root,
+        }
+    }
 }
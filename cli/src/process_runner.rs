@@ -1,20 +1,40 @@
-use anyhow::{bail, Context, Result};
+use crate::report::{self, progress, Diagnostic, StageRecord};
+use anyhow::{Context, Result};
 use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
 use std::path::Path;
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
 
 #[derive(Debug)]
 pub struct CommandConfig<'a> {
+    /// Names the step in the progress output and the summary, e.g. "base tests".
+    pub stage: &'a str,
     pub program_name: &'a str,
     pub args: &'a [&'a str],
     pub current_dir: Option<&'a Path>,
     pub env_vars: Option<HashMap<String, String>>,
     pub inherit_stdio: bool,
+    /// Read the output while echoing it, so errors can be summarized.
+    /// Always on with `--json`, which keeps child output off stdout.
+    pub capture: bool,
+    /// The program is cargo: ask it for JSON messages and collect its diagnostics.
+    pub cargo_diagnostics: bool,
 }
 
 pub fn run_command(config: CommandConfig) -> Result<()> {
+    let capture = config.capture || report::json_output();
+    let args = if config.cargo_diagnostics && capture {
+        with_message_format(config.args)
+    } else {
+        config.args.to_vec()
+    };
+
     let mut cmd = Command::new(config.program_name);
-    cmd.args(config.args);
+    cmd.args(&args);
 
     if let Some(dir) = config.current_dir {
         cmd.current_dir(dir);
@@ -23,24 +43,452 @@ pub fn run_command(config: CommandConfig) -> Result<()> {
         cmd.envs(envs);
     }
 
-    if config.inherit_stdio {
+    if capture {
+        pipe_output(&mut cmd);
+    } else if config.inherit_stdio {
         cmd.stdout(Stdio::inherit()).stderr(Stdio::inherit());
     }
 
-    let command_display_str = format!("{} {}", config.program_name, config.args.join(" "));
-    println!("Executing: {}", command_display_str);
+    let command_display_str = format!("{} {}", config.program_name, args.join(" "));
+    progress!("Executing: {}", command_display_str);
+
+    let started = Instant::now();
+    let outcome = if capture {
+        run_captured(cmd, config.cargo_diagnostics)
+    } else {
+        cmd.status().map(|status| (status, Vec::new()))
+    };
+    let (status, diagnostics) = match outcome {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            report::record_stage(StageRecord {
+                name: config.stage.to_string(),
+                command: Some(command_display_str.clone()),
+                success: false,
+                cancelled: false,
+                elapsed_ms: started.elapsed().as_millis(),
+                diagnostics: Vec::new(),
+            });
+            return Err(e)
+                .with_context(|| format!("Failed to execute command: {}", command_display_str));
+        }
+    };
 
-    let status = cmd
-        .status()
-        .with_context(|| format!("Failed to execute command: {}", command_display_str))?;
+    report::record_stage(StageRecord {
+        name: config.stage.to_string(),
+        command: Some(command_display_str.clone()),
+        success: status.success(),
+        cancelled: false,
+        elapsed_ms: started.elapsed().as_millis(),
+        diagnostics,
+    });
 
     if !status.success() {
-        bail!(
-            "Command failed with status {}: {}",
+        return Err(CommandFailed {
             status,
-            command_display_str
-        );
+            command: command_display_str,
+        }
+        .into());
     }
 
     Ok(())
 }
+
+/// A program that ran and failed. The CLI exits with its exit code.
+#[derive(Debug)]
+pub struct CommandFailed {
+    pub status: ExitStatus,
+    pub command: String,
+}
+
+impl fmt::Display for CommandFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Command failed with status {}: {}",
+            self.status, self.command
+        )
+    }
+}
+
+impl std::error::Error for CommandFailed {}
+
+/// `args` for cargo with `--message-format` added, which cargo takes right
+/// after its subcommand.
+pub fn with_message_format<'a>(args: &[&'a str]) -> Vec<&'a str> {
+    let message_format = if std::io::stderr().is_terminal() {
+        "--message-format=json-diagnostic-rendered-ansi"
+    } else {
+        "--message-format=json"
+    };
+    let mut args = args.to_vec();
+    if !args.is_empty() {
+        args.insert(1, message_format);
+    }
+    args
+}
+
+/// Pipes the output of `cmd` so it can be read with [`read_output`].
+pub fn pipe_output(cmd: &mut Command) {
+    // Tools disable colors when writing to a pipe; keep them for the terminal.
+    if std::io::stderr().is_terminal() {
+        cmd.env("CARGO_TERM_COLOR", "always");
+    }
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+}
+
+/// The lines a program printed, without color codes, in the order they were read.
+pub type OutputLog = Arc<Mutex<Vec<String>>>;
+
+/// Starts echoing the piped output of `child`, each line after `prefix`, and
+/// keeps the lines in `log` if given. The threads return the diagnostics they
+/// found once the output ends.
+pub fn read_output(
+    child: &mut Child,
+    cargo_diagnostics: bool,
+    prefix: &str,
+    log: Option<OutputLog>,
+) -> Vec<JoinHandle<Vec<Diagnostic>>> {
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let stdout_prefix = prefix.to_string();
+    let stderr_prefix = prefix.to_string();
+    let stdout_log = log.clone();
+    vec![
+        thread::spawn(move || {
+            tee_lines(
+                stdout,
+                false,
+                cargo_diagnostics,
+                &stdout_prefix,
+                stdout_log.as_ref(),
+            )
+        }),
+        thread::spawn(move || tee_lines(stderr, true, false, &stderr_prefix, log.as_ref())),
+    ]
+}
+
+/// Waits for the threads started by [`read_output`] and collects their diagnostics.
+pub fn join_output(readers: Vec<JoinHandle<Vec<Diagnostic>>>) -> Vec<Diagnostic> {
+    readers
+        .into_iter()
+        .flat_map(|reader| reader.join().unwrap_or_default())
+        .collect()
+}
+
+/// Runs `cmd` with piped output, echoing each line as it arrives and
+/// collecting the diagnostics it reports.
+fn run_captured(
+    mut cmd: Command,
+    cargo_diagnostics: bool,
+) -> std::io::Result<(ExitStatus, Vec<Diagnostic>)> {
+    let mut child = cmd.spawn()?;
+    let readers = read_output(&mut child, cargo_diagnostics, "", None);
+    let status = child.wait()?;
+    Ok((status, join_output(readers)))
+}
+
+/// Echoes `source` line by line to our stdout (stderr if `to_stderr` or with
+/// `--json`), each line after `prefix`, and returns the diagnostics found in
+/// it. Cargo JSON messages are shown as cargo would render them and are not
+/// added to `log`.
+fn tee_lines(
+    source: impl Read,
+    to_stderr: bool,
+    cargo_json: bool,
+    prefix: &str,
+    log: Option<&OutputLog>,
+) -> Vec<Diagnostic> {
+    let mut reader = BufReader::new(source);
+    let mut scanner = DiagnosticScanner::default();
+    let mut cargo_diagnostics = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let text = String::from_utf8_lossy(&line);
+        if cargo_json && text.starts_with('{') {
+            match parse_cargo_message(&text) {
+                CargoLine::NotCargo => {}
+                CargoLine::Hidden => continue,
+                CargoLine::Compiler {
+                    rendered,
+                    diagnostic,
+                } => {
+                    let mut stderr = std::io::stderr().lock();
+                    for rendered_line in rendered.lines() {
+                        let _ = writeln!(stderr, "{}{}", prefix, rendered_line);
+                    }
+                    cargo_diagnostics.extend(diagnostic);
+                    continue;
+                }
+            }
+        }
+        scanner.scan(&text);
+        if let Some(log) = log {
+            let line = strip_ansi(text.trim_end_matches(['\r', '\n']));
+            log.lock().unwrap_or_else(|e| e.into_inner()).push(line);
+        }
+        // One write per line keeps the lines of parallel jobs whole.
+        let mut output = Vec::with_capacity(prefix.len() + line.len());
+        output.extend_from_slice(prefix.as_bytes());
+        output.extend_from_slice(&line);
+        if to_stderr || report::json_output() {
+            let _ = std::io::stderr().write_all(&output);
+        } else {
+            let mut stdout = std::io::stdout().lock();
+            let _ = stdout.write_all(&output);
+            let _ = stdout.flush();
+        }
+    }
+    if cargo_json {
+        cargo_diagnostics
+    } else {
+        scanner.diagnostics
+    }
+}
+
+/// A line of `cargo --message-format=json` output.
+enum CargoLine {
+    /// Not a cargo message, e.g. the output of a test.
+    NotCargo,
+    /// Build progress that cargo does not show either.
+    Hidden,
+    /// A compiler message, shown as `rendered`.
+    Compiler {
+        rendered: String,
+        diagnostic: Option<Diagnostic>,
+    },
+}
+
+fn parse_cargo_message(line: &str) -> CargoLine {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+        return CargoLine::NotCargo;
+    };
+    match value.get("reason").and_then(|r| r.as_str()) {
+        None => return CargoLine::NotCargo,
+        Some("compiler-message") => {}
+        Some(_) => return CargoLine::Hidden,
+    }
+    let message = &value["message"];
+    let rendered = message["rendered"].as_str().unwrap_or_default().to_string();
+    let level = message["level"].as_str().unwrap_or_default();
+    let diagnostic = matches!(level, "error" | "warning").then(|| {
+        let location = message["spans"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|span| span["is_primary"].as_bool() == Some(true))
+            .map(|span| {
+                format!(
+                    "{}:{}:{}",
+                    span["file_name"].as_str().unwrap_or_default(),
+                    span["line_start"],
+                    span["column_start"]
+                )
+            });
+        Diagnostic {
+            level: level.to_string(),
+            message: message["message"].as_str().unwrap_or_default().to_string(),
+            location,
+        }
+    });
+    CargoLine::Compiler {
+        rendered,
+        diagnostic,
+    }
+}
+
+/// Finds rustc-style diagnostics in plain output: an `error: message` or
+/// `error[E0308]: message` line, then a ` --> file:line:column` line.
+#[derive(Default)]
+struct DiagnosticScanner {
+    diagnostics: Vec<Diagnostic>,
+    awaiting_location: bool,
+}
+
+impl DiagnosticScanner {
+    fn scan(&mut self, line: &str) {
+        let line = strip_ansi(line);
+        let trimmed = line.trim();
+        if let Some(location) = trimmed.strip_prefix("--> ") {
+            if self.awaiting_location {
+                if let Some(last) = self.diagnostics.last_mut() {
+                    last.location = Some(location.to_string());
+                }
+                self.awaiting_location = false;
+            }
+            return;
+        }
+        let Some(rest) = line.strip_prefix("error") else {
+            return;
+        };
+        // Skip the code in `error[E0308]: ...`.
+        let rest = match rest.strip_prefix('[') {
+            Some(coded) => coded.split_once(']').map_or("", |(_, rest)| rest),
+            None => rest,
+        };
+        if let Some(message) = rest.strip_prefix(": ") {
+            self.diagnostics.push(Diagnostic {
+                level: "error".to_string(),
+                message: message.trim().to_string(),
+                location: None,
+            });
+            self.awaiting_location = true;
+        }
+    }
+}
+
+/// Removes terminal color codes.
+fn strip_ansi(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            output.push(c);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(lines: &[&str]) -> Vec<Diagnostic> {
+        let mut scanner = DiagnosticScanner::default();
+        for line in lines {
+            scanner.scan(line);
+        }
+        scanner.diagnostics
+    }
+
+    #[test]
+    fn test_scanner_finds_errors_and_their_locations() {
+        let diagnostics = scan(&[
+            "   Compiling app v0.1.0\n",
+            "error[E0308]: mismatched types\n",
+            "  --> src/lib.rs:4:9\n",
+            "   |\n",
+            "error: could not compile `app`\n",
+        ]);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].level, "error");
+        assert_eq!(diagnostics[0].message, "mismatched types");
+        assert_eq!(diagnostics[0].location.as_deref(), Some("src/lib.rs:4:9"));
+        assert_eq!(diagnostics[1].message, "could not compile `app`");
+        assert_eq!(diagnostics[1].location, None);
+    }
+
+    #[test]
+    fn test_scanner_ignores_locations_without_an_error() {
+        let diagnostics = scan(&[
+            "warning: unused variable: `x`\n",
+            "  --> src/lib.rs:1:5\n",
+            "error: linking failed\n",
+            "note: see the output above\n",
+        ]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "linking failed");
+        assert_eq!(diagnostics[0].location, None);
+
+        // Only the first location after an error belongs to it.
+        let diagnostics = scan(&["error: first\n", " --> a.rs:1:1\n", " --> b.rs:2:2\n"]);
+        assert_eq!(diagnostics[0].location.as_deref(), Some("a.rs:1:1"));
+        assert!(scan(&["errors: 3\n", "error without a colon\n"]).is_empty());
+    }
+
+    #[test]
+    fn test_scanner_reads_coloured_output() {
+        let diagnostics = scan(&[
+            "\u{1b}[0m\u{1b}[1m\u{1b}[38;5;9merror[E0425]\u{1b}[0m\u{1b}[0m\u{1b}[1m: cannot find value `y`\u{1b}[0m\n",
+            "\u{1b}[0m  \u{1b}[0m\u{1b}[0m\u{1b}[1m\u{1b}[38;5;12m--> \u{1b}[0m\u{1b}[0msrc/main.rs:2:13\u{1b}[0m\n",
+        ]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "cannot find value `y`");
+        assert_eq!(diagnostics[0].location.as_deref(), Some("src/main.rs:2:13"));
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("plain"), "plain");
+        assert_eq!(strip_ansi("\u{1b}[1;31mred\u{1b}[0m text"), "red text");
+        assert_eq!(strip_ansi("\u{1b}[2K\u{1b}[1Gdone"), "done");
+        assert_eq!(strip_ansi(""), "");
+    }
+
+    #[test]
+    fn test_parse_cargo_message_shows_compiler_messages() {
+        let line = r#"{"reason":"compiler-message","message":{"rendered":"error[E0308]: mismatched types\n --> src/lib.rs:4:9\n","level":"error","message":"mismatched types","spans":[{"file_name":"src/other.rs","line_start":1,"column_start":1,"is_primary":false},{"file_name":"src/lib.rs","line_start":4,"column_start":9,"is_primary":true}]}}"#;
+        let CargoLine::Compiler {
+            rendered,
+            diagnostic,
+        } = parse_cargo_message(line)
+        else {
+            panic!("Expected a compiler message");
+        };
+        assert_eq!(
+            rendered,
+            "error[E0308]: mismatched types\n --> src/lib.rs:4:9\n"
+        );
+        let diagnostic = diagnostic.unwrap();
+        assert_eq!(diagnostic.level, "error");
+        assert_eq!(diagnostic.message, "mismatched types");
+        assert_eq!(diagnostic.location.as_deref(), Some("src/lib.rs:4:9"));
+
+        // Notes are shown, but are not diagnostics.
+        let note = r#"{"reason":"compiler-message","message":{"rendered":"note: x\n","level":"note","message":"x","spans":[]}}"#;
+        assert!(matches!(
+            parse_cargo_message(note),
+            CargoLine::Compiler {
+                diagnostic: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_cargo_message_hides_other_cargo_messages() {
+        for line in [
+            r#"{"reason":"compiler-artifact","package_id":"app 0.1.0","filenames":[]}"#,
+            r#"{"reason":"build-script-executed","package_id":"app 0.1.0"}"#,
+            r#"{"reason":"build-finished","success":true}"#,
+        ] {
+            assert!(
+                matches!(parse_cargo_message(line), CargoLine::Hidden),
+                "{}",
+                line
+            );
+        }
+        for line in [r#"{"type":"test","event":"ok"}"#, "{ not json", "{}"] {
+            assert!(
+                matches!(parse_cargo_message(line), CargoLine::NotCargo),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_message_format_goes_after_the_subcommand() {
+        let args = with_message_format(&["build", "--lib", "--release"]);
+        assert_eq!(args.len(), 4);
+        assert_eq!(args[0], "build");
+        assert!(args[1].starts_with("--message-format=json"), "{:?}", args);
+        assert_eq!(&args[2..], ["--lib", "--release"]);
+
+        let args = with_message_format(&["test"]);
+        assert_eq!(args[0], "test");
+        assert!(args[1].starts_with("--message-format=json"));
+        assert!(with_message_format(&[]).is_empty());
+    }
+}
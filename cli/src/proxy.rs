@@ -0,0 +1,421 @@
+use crate::report::progress;
+use anyhow::{anyhow, bail, Result};
+use futures_util::{SinkExt, StreamExt};
+use hyper::body::Bytes;
+use hyper::client::HttpConnector;
+use hyper::{Body, Client};
+use std::str::FromStr;
+use std::time::Instant;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue as UpstreamHeaderValue;
+use tokio_tungstenite::tungstenite::Message as UpstreamMessage;
+use warp::filters::BoxedFilter;
+use warp::http::header::{HeaderName, HOST};
+use warp::http::uri::{Authority, Scheme};
+use warp::http::{HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Uri};
+use warp::ws::{Message, WebSocket, Ws};
+use warp::{Filter, Rejection, Reply};
+
+/// Headers that only apply to a single connection and must not be forwarded.
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Forwards requests under `prefix` to a local upstream server.
+///
+/// Rewriting follows nginx's `proxy_pass`: a target without a path receives
+/// the request path unchanged, while a target with a path (even just `/`) has
+/// it replace the matched prefix. With `/api=http://127.0.0.1:3000/`, a
+/// request for `/api/users` is forwarded as `/users`.
+#[derive(Debug, Clone)]
+pub struct ProxyRule {
+    pub prefix: String,
+    authority: Authority,
+    target_path: Option<String>,
+}
+
+impl ProxyRule {
+    /// Creates a rule from a path prefix such as `/api` and a target URL such
+    /// as `http://127.0.0.1:3000`. `ws://` targets are treated as `http://`.
+    pub fn new(prefix: &str, target: &str) -> Result<Self> {
+        if !prefix.starts_with('/') {
+            bail!("Proxy path '{}' must start with '/'", prefix);
+        }
+        let uri: Uri = target
+            .parse()
+            .map_err(|e| anyhow!("Invalid proxy target '{}': {}", target, e))?;
+        match uri.scheme_str() {
+            Some("http" | "ws") => {}
+            Some(other) => bail!(
+                "Unsupported scheme '{}' in proxy target '{}'; use http:// or ws://",
+                other,
+                target
+            ),
+            None => bail!("Proxy target '{}' needs a scheme, e.g. http://", target),
+        }
+        let authority = uri
+            .authority()
+            .cloned()
+            .ok_or_else(|| anyhow!("Proxy target '{}' needs a host", target))?;
+        if uri.query().is_some() {
+            bail!("Proxy target '{}' must not contain a query string", target);
+        }
+        // `Uri` reports "/" for a target without any path, so look at the text.
+        let has_path = target
+            .split_once("://")
+            .is_some_and(|(_, rest)| rest.contains('/'));
+        let target_path = has_path.then(|| uri.path().to_string());
+
+        Ok(Self {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            authority,
+            target_path,
+        })
+    }
+
+    /// Whether the rule handles `path`. Prefixes match whole segments only,
+    /// so `/api` matches `/api` and `/api/users` but not `/apiary`.
+    pub fn matches(&self, path: &str) -> bool {
+        match path.strip_prefix(&self.prefix) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/') || self.prefix.is_empty(),
+            None => false,
+        }
+    }
+
+    /// The upstream path and query for a matching request.
+    fn upstream_path_and_query(&self, path: &str, query: Option<&str>) -> String {
+        let path = match &self.target_path {
+            None => path.to_string(),
+            Some(target_path) => {
+                let rest = &path[self.prefix.len()..];
+                let joined = format!(
+                    "{}/{}",
+                    target_path.trim_end_matches('/'),
+                    rest.trim_start_matches('/')
+                );
+                // Keep a bare prefix request on the target path itself.
+                if rest.is_empty() && !target_path.ends_with('/') {
+                    target_path.clone()
+                } else {
+                    joined
+                }
+            }
+        };
+        match query {
+            Some(query) => format!("{}?{}", path, query),
+            None => path,
+        }
+    }
+
+    fn upstream_uri(&self, path: &str, query: Option<&str>) -> Result<Uri> {
+        Ok(Uri::builder()
+            .scheme(Scheme::HTTP)
+            .authority(self.authority.clone())
+            .path_and_query(self.upstream_path_and_query(path, query))
+            .build()?)
+    }
+
+    /// The matched path prefix, for log output.
+    pub fn prefix_or_root(&self) -> &str {
+        if self.prefix.is_empty() {
+            "/"
+        } else {
+            &self.prefix
+        }
+    }
+
+    /// The target as written in `--proxy`, for log output.
+    pub fn target(&self) -> String {
+        format!(
+            "http://{}{}",
+            self.authority,
+            self.target_path.as_deref().unwrap_or("")
+        )
+    }
+}
+
+/// Parses `--proxy` values of the form `/api=http://127.0.0.1:3000`.
+impl FromStr for ProxyRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (prefix, target) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected PATH=URL, e.g. /api=http://127.0.0.1:3000"))?;
+        Self::new(prefix.trim(), target.trim())
+    }
+}
+
+/// Routes that forward matching HTTP and WebSocket requests to their upstream.
+/// Longer prefixes win over shorter ones. `scheme` is how browsers reach this
+/// server.
+pub fn routes(mut rules: Vec<ProxyRule>, scheme: Scheme) -> BoxedFilter<(Box<dyn Reply>,)> {
+    rules.sort_by_key(|rule| std::cmp::Reverse(rule.prefix.len()));
+    let client = ProxyClient {
+        client: Client::new(),
+        scheme,
+    };
+
+    let matching_rule = warp::path::full().and_then(move |path: warp::path::FullPath| {
+        let rule = rules
+            .iter()
+            .find(|rule| rule.matches(path.as_str()))
+            .cloned();
+        async move { rule.ok_or_else(warp::reject::not_found) }
+    });
+    let query = warp::query::raw()
+        .map(Some)
+        .or(warp::any().map(|| None))
+        .unify();
+
+    let websocket = matching_rule
+        .clone()
+        .and(warp::path::full())
+        .and(query)
+        .and(warp::header::headers_cloned())
+        .and(warp::ws())
+        .map(
+            |rule: ProxyRule,
+             path: warp::path::FullPath,
+             query: Option<String>,
+             headers: HeaderMap,
+             ws: Ws| {
+                match rule.upstream_uri(path.as_str(), query.as_deref()) {
+                    Ok(uri) => {
+                        let url = uri.to_string().replacen("http://", "ws://", 1);
+                        // Only the first requested subprotocol is offered upstream and
+                        // confirmed to the browser, since the handshake with the browser
+                        // completes before the upstream one does.
+                        let protocol = headers
+                            .get("sec-websocket-protocol")
+                            .and_then(|v| v.to_str().ok())
+                            .and_then(|v| v.split(',').next())
+                            .map(|p| p.trim().to_string());
+                        let upgrade = ws.on_upgrade({
+                            let protocol = protocol.clone();
+                            move |socket| relay_websocket(socket, url, protocol)
+                        });
+                        match protocol {
+                            Some(protocol) => Box::new(warp::reply::with_header(
+                                upgrade,
+                                "sec-websocket-protocol",
+                                protocol,
+                            )) as Box<dyn Reply>,
+                            None => Box::new(upgrade),
+                        }
+                    }
+                    Err(e) => bad_gateway(&e.to_string()),
+                }
+            },
+        );
+
+    let http = matching_rule
+        .and(warp::method())
+        .and(warp::path::full())
+        .and(query)
+        .and(warp::header::headers_cloned())
+        .and(warp::body::bytes())
+        .and_then(
+            move |rule: ProxyRule,
+                  method: Method,
+                  path: warp::path::FullPath,
+                  query: Option<String>,
+                  headers: HeaderMap,
+                  body: Bytes| {
+                let client = client.clone();
+                async move {
+                    Ok::<_, Rejection>(
+                        client
+                            .forward(&rule, method, path.as_str(), query, headers, body)
+                            .await,
+                    )
+                }
+            },
+        );
+
+    websocket.or(http).unify().boxed()
+}
+
+/// The HTTP client requests are forwarded with, and the scheme browsers use
+/// to reach this server, passed on as `X-Forwarded-Proto`.
+#[derive(Clone)]
+struct ProxyClient {
+    client: Client<HttpConnector>,
+    scheme: Scheme,
+}
+
+impl ProxyClient {
+    async fn forward(
+        &self,
+        rule: &ProxyRule,
+        method: Method,
+        path: &str,
+        query: Option<String>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Box<dyn Reply> {
+        let started = Instant::now();
+        let uri = match rule.upstream_uri(path, query.as_deref()) {
+            Ok(uri) => uri,
+            Err(e) => return bad_gateway(&e.to_string()),
+        };
+        let log_line = format!("[proxy] {} {} -> {}", method, path, uri);
+
+        let mut request = Request::builder().method(method).uri(uri);
+        let request_headers = request.headers_mut().expect("builder has no errors yet");
+        copy_end_to_end_headers(&headers, request_headers);
+        if let Some(host) = headers.get(HOST) {
+            request_headers.insert("x-forwarded-host", host.clone());
+        }
+        if let Ok(proto) = HeaderValue::from_str(self.scheme.as_str()) {
+            request_headers.insert("x-forwarded-proto", proto);
+        }
+        if let Ok(host) = HeaderValue::from_str(rule.authority.as_str()) {
+            request_headers.insert(HOST, host);
+        }
+        let request = match request.body(Body::from(body)) {
+            Ok(request) => request,
+            Err(e) => return bad_gateway(&e.to_string()),
+        };
+
+        match self.client.request(request).await {
+            Ok(upstream) => {
+                progress!(
+                    "{} {} ({} ms)",
+                    log_line,
+                    upstream.status().as_u16(),
+                    started.elapsed().as_millis()
+                );
+                let (parts, body) = upstream.into_parts();
+                let mut response = Response::builder().status(parts.status);
+                copy_end_to_end_headers(
+                    &parts.headers,
+                    response.headers_mut().expect("builder has no errors yet"),
+                );
+                match response.body(body) {
+                    Ok(response) => Box::new(response),
+                    Err(e) => bad_gateway(&e.to_string()),
+                }
+            }
+            Err(e) => {
+                eprintln!("{} failed: {}", log_line, e);
+                bad_gateway(&format!("Proxy error: {}", e))
+            }
+        }
+    }
+}
+
+fn copy_end_to_end_headers(from: &HeaderMap, to: &mut HeaderMap) {
+    // Headers named in `Connection` are hop-by-hop for this request too.
+    let connection_tokens: Vec<String> = from
+        .get_all("connection")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|t| t.trim().to_ascii_lowercase())
+        .collect();
+    for (name, value) in from {
+        let name_str = name.as_str();
+        if HOP_BY_HOP_HEADERS.contains(&name_str)
+            || connection_tokens.iter().any(|t| t == name_str)
+            || *name == HOST
+        {
+            continue;
+        }
+        to.append(HeaderName::clone(name), value.clone());
+    }
+}
+
+fn bad_gateway(message: &str) -> Box<dyn Reply> {
+    Box::new(warp::reply::with_status(
+        message.to_string(),
+        StatusCode::BAD_GATEWAY,
+    ))
+}
+
+/// Connects to the upstream WebSocket and relays messages both ways until
+/// either side closes.
+async fn relay_websocket(client: WebSocket, url: String, protocol: Option<String>) {
+    let mut request = match url.as_str().into_client_request() {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("[proxy] WebSocket {} failed: {}", url, e);
+            return;
+        }
+    };
+    if let Some(protocol) = protocol {
+        if let Ok(value) = UpstreamHeaderValue::from_str(&protocol) {
+            request
+                .headers_mut()
+                .insert("sec-websocket-protocol", value);
+        }
+    }
+    let upstream = match tokio_tungstenite::connect_async(request).await {
+        Ok((upstream, _)) => upstream,
+        Err(e) => {
+            eprintln!("[proxy] WebSocket {} failed: {}", url, e);
+            return;
+        }
+    };
+    progress!("[proxy] WebSocket connected -> {}", url);
+
+    let (mut client_tx, mut client_rx) = client.split();
+    let (mut upstream_tx, mut upstream_rx) = upstream.split();
+
+    let to_upstream = async {
+        while let Some(Ok(message)) = client_rx.next().await {
+            let Some(message) = to_upstream_message(message) else {
+                continue;
+            };
+            if upstream_tx.send(message).await.is_err() {
+                break;
+            }
+        }
+        let _ = upstream_tx.close().await;
+    };
+    let to_client = async {
+        while let Some(Ok(message)) = upstream_rx.next().await {
+            let Some(message) = to_client_message(message) else {
+                continue;
+            };
+            if client_tx.send(message).await.is_err() {
+                break;
+            }
+        }
+        let _ = client_tx.close().await;
+    };
+    tokio::select! {
+        _ = to_upstream => {}
+        _ = to_client => {}
+    }
+    progress!("[proxy] WebSocket closed -> {}", url);
+}
+
+fn to_upstream_message(message: Message) -> Option<UpstreamMessage> {
+    if message.is_text() {
+        message.to_str().ok().map(UpstreamMessage::text)
+    } else if message.is_binary() {
+        Some(UpstreamMessage::binary(message.into_bytes()))
+    } else if message.is_close() {
+        Some(UpstreamMessage::Close(None))
+    } else {
+        // Pings and pongs are answered per connection by each side.
+        None
+    }
+}
+
+fn to_client_message(message: UpstreamMessage) -> Option<Message> {
+    match message {
+        UpstreamMessage::Text(text) => Some(Message::text(text)),
+        UpstreamMessage::Binary(bytes) => Some(Message::binary(bytes)),
+        UpstreamMessage::Close(_) => Some(Message::close()),
+        _ => None,
+    }
+}
@@ -0,0 +1,409 @@
+use crate::static_files::{compress_with_level, Encoding};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Written to the root of every release; its presence marks a directory as
+/// safe for `cli release` to replace. The name keeps it apart from a site's
+/// own files, like the `manifest.json` of a web app.
+pub const MANIFEST_FILE: &str = ".release-manifest.json";
+/// Hex digits of the SHA-256 content hash put into file names.
+const HASH_LENGTH: usize = 16;
+/// Files smaller than this are not precompressed.
+const MIN_COMPRESS_SIZE: usize = 1024;
+/// Extensions whose files are renamed to include their content hash.
+const HASHED_EXTENSIONS: &[&str] = &["js", "mjs", "css", "wasm"];
+/// Extensions whose contents are scanned for references to hashed files.
+const TEXT_EXTENSIONS: &[&str] = &["html", "js", "mjs", "css"];
+/// Extensions that get `.br` and `.gz` variants.
+const COMPRESSED_EXTENSIONS: &[&str] = &["html", "js", "mjs", "css", "wasm", "json", "svg", "txt"];
+/// Build metadata wasm-bindgen writes into the package that a site does not need.
+const PACKAGE_BUILD_FILES: &[&str] = &["package.json", ".gitignore", "README.md"];
+
+/// What to put into a release directory.
+pub struct ReleaseInput<'a> {
+    /// The static directory, copied as the site root.
+    pub static_dir: &'a Path,
+    /// The freshly built WASM package.
+    pub pkg_dir: &'a Path,
+    /// Where the package goes inside the site, e.g. `pkg`.
+    pub pkg_site_path: &'a Path,
+    /// Skipped while copying the static directory, e.g. a stale `static/pkg`.
+    pub exclude: &'a [PathBuf],
+}
+
+#[derive(Serialize)]
+struct Manifest {
+    /// Original site path to the content-hashed path that replaced it.
+    assets: BTreeMap<String, String>,
+    /// Every file in the release, with its compressed sizes if it has variants.
+    files: BTreeMap<String, FileEntry>,
+}
+
+#[derive(Serialize)]
+struct FileEntry {
+    size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gzip: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    brotli: Option<u64>,
+}
+
+/// Assembles a deployable site in `dist_dir`: copies the static directory and
+/// the package, renames JS, CSS and WASM files to include a content hash,
+/// rewrites the references to them, writes `.br`/`.gz` variants and a
+/// `.release-manifest.json`.
+pub fn assemble_release(input: &ReleaseInput, dist_dir: &Path) -> Result<()> {
+    check_outside_static_dir(input.static_dir, dist_dir)?;
+    prepare_dist_dir(dist_dir)?;
+    copy_dir(input.static_dir, dist_dir, input.exclude)?;
+    let pkg_dest = dist_dir.join(input.pkg_site_path);
+    copy_dir(input.pkg_dir, &pkg_dest, &[])?;
+    for name in PACKAGE_BUILD_FILES {
+        let _ = fs::remove_file(pkg_dest.join(name));
+    }
+    remove_type_declarations(&pkg_dest)?;
+
+    let assets = hash_assets(dist_dir)?;
+    let files = precompress(dist_dir)?;
+    let manifest = Manifest { assets, files };
+    let json = serde_json::to_string_pretty(&manifest)?;
+    fs::write(dist_dir.join(MANIFEST_FILE), json + "\n")
+        .context("Failed to write the release manifest")?;
+    Ok(())
+}
+
+/// A release inside the static directory would be copied into itself, and
+/// replacing it would delete part of the site.
+fn check_outside_static_dir(static_dir: &Path, dist_dir: &Path) -> Result<()> {
+    let absolute = |path: &Path| -> Result<PathBuf> {
+        let path = std::path::absolute(path)
+            .with_context(|| format!("Failed to resolve '{}'", path.display()))?;
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                component => normalized.push(component),
+            }
+        }
+        Ok(normalized)
+    };
+    if absolute(dist_dir)?.starts_with(absolute(static_dir)?) {
+        bail!(
+            "The release directory '{}' is inside the static directory '{}'; choose an --out-dir outside it.",
+            dist_dir.display(),
+            static_dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// Empties `dist_dir`, refusing to touch a non-empty directory that does not
+/// look like an earlier release.
+fn prepare_dist_dir(dist_dir: &Path) -> Result<()> {
+    if dist_dir.exists() {
+        let is_empty = fs::read_dir(dist_dir)?.next().is_none();
+        if !is_empty && !dist_dir.join(MANIFEST_FILE).is_file() {
+            bail!(
+                "'{}' exists and was not created by `cli release`; remove it or choose another --out-dir.",
+                dist_dir.display()
+            );
+        }
+        fs::remove_dir_all(dist_dir)
+            .with_context(|| format!("Failed to clear '{}'", dist_dir.display()))?;
+    }
+    fs::create_dir_all(dist_dir)
+        .with_context(|| format!("Failed to create '{}'", dist_dir.display()))
+}
+
+fn copy_dir(from: &Path, to: &Path, exclude: &[PathBuf]) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in
+        fs::read_dir(from).with_context(|| format!("Failed to read '{}'", from.display()))?
+    {
+        let entry = entry?;
+        let path = entry.path();
+        if exclude.contains(&path) {
+            continue;
+        }
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&path, &target, exclude)?;
+        } else {
+            fs::copy(&path, &target).with_context(|| {
+                format!(
+                    "Failed to copy '{}' to '{}'",
+                    path.display(),
+                    target.display()
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn remove_type_declarations(dir: &Path) -> Result<()> {
+    for path in walk_files(dir)? {
+        if path.to_string_lossy().ends_with(".d.ts") {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Every file below `dir`, in a stable order.
+fn walk_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                pending.push(entry.path());
+            } else {
+                files.push(entry.path());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.contains(&e))
+}
+
+/// Renames every hashable file to `name.<hash>.ext` and rewrites references to
+/// it. Files are processed dependencies first, so a file's hash covers the
+/// hashed names it refers to and any change propagates up to `index.html`.
+fn hash_assets(dist_dir: &Path) -> Result<BTreeMap<String, String>> {
+    let files = walk_files(dist_dir)?;
+    let hashable: BTreeSet<PathBuf> = files
+        .iter()
+        .filter(|p| has_extension(p, HASHED_EXTENSIONS))
+        .cloned()
+        .collect();
+
+    // What each text file refers to among the hashable files.
+    let mut dependencies: BTreeMap<PathBuf, BTreeSet<PathBuf>> = BTreeMap::new();
+    for file in files.iter().filter(|p| has_extension(p, TEXT_EXTENSIONS)) {
+        let text = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read '{}'", file.display()))?;
+        let references = find_references(&text)
+            .into_iter()
+            .filter_map(|r| resolve_reference(file, &r.path))
+            .filter(|target| hashable.contains(target) && target != file)
+            .collect();
+        dependencies.insert(file.clone(), references);
+    }
+
+    let mut renamed: BTreeMap<PathBuf, PathBuf> = BTreeMap::new();
+    let mut remaining: BTreeSet<PathBuf> = hashable.clone();
+    loop {
+        let ready: Vec<PathBuf> = remaining
+            .iter()
+            .filter(|file| {
+                dependencies
+                    .get(*file)
+                    .is_none_or(|deps| deps.iter().all(|d| renamed.contains_key(d)))
+            })
+            .cloned()
+            .collect();
+        if ready.is_empty() {
+            break;
+        }
+        for file in ready {
+            rewrite_references(&file, &renamed)?;
+            let contents = fs::read(&file)?;
+            let hashed = hashed_path(&file, &contents);
+            fs::rename(&file, &hashed)?;
+            remaining.remove(&file);
+            renamed.insert(file, hashed);
+        }
+    }
+    if !remaining.is_empty() {
+        let names: Vec<String> = remaining.iter().map(|p| site_path(dist_dir, p)).collect();
+        bail!(
+            "Cannot content-hash files that refer to each other in a cycle: {}",
+            names.join(", ")
+        );
+    }
+
+    // Files that are not hashed themselves, like index.html, still point at hashed ones.
+    for file in files.iter().filter(|p| !hashable.contains(*p)) {
+        if dependencies.contains_key(file) {
+            rewrite_references(file, &renamed)?;
+        }
+    }
+
+    Ok(renamed
+        .iter()
+        .map(|(original, hashed)| (site_path(dist_dir, original), site_path(dist_dir, hashed)))
+        .collect())
+}
+
+fn hashed_path(path: &Path, contents: &[u8]) -> PathBuf {
+    let digest = Sha256::digest(contents);
+    let hash: String = digest
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>()[..HASH_LENGTH]
+        .to_string();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.{}.{}", stem, hash, extension))
+}
+
+/// A path written in a quoted string or `url()` in a text file.
+#[derive(Debug, PartialEq)]
+struct Reference {
+    /// Byte range of the path part, excluding any `?query` or `#fragment`.
+    start: usize,
+    end: usize,
+    path: String,
+}
+
+/// Finds quoted strings and CSS `url()` values that look like relative paths.
+fn find_references(text: &str) -> Vec<Reference> {
+    let bytes = text.as_bytes();
+    let mut references = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let (start, terminators): (usize, &[u8]) = match bytes[i] {
+            b'"' | b'\'' | b'`' => (i + 1, std::slice::from_ref(&bytes[i])),
+            b'(' if text[..i].ends_with("url")
+                && !matches!(bytes.get(i + 1), Some(b'"' | b'\'')) =>
+            {
+                (i + 1, b")")
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let mut end = start;
+        while end < bytes.len()
+            && !terminators.contains(&bytes[end])
+            && !matches!(bytes[end], b'\n' | b'?' | b'#' | b'$' | b' ')
+        {
+            end += 1;
+        }
+        let candidate = &text[start..end];
+        if looks_like_relative_path(candidate) {
+            references.push(Reference {
+                start,
+                end,
+                path: candidate.to_string(),
+            });
+        }
+        i = end.max(i + 1);
+    }
+    references
+}
+
+fn looks_like_relative_path(candidate: &str) -> bool {
+    !candidate.is_empty()
+        && !candidate.contains("://")
+        && !candidate.starts_with('/')
+        && !candidate.starts_with("data:")
+        && candidate
+            .rsplit('/')
+            .next()
+            .is_some_and(|name| name.contains('.'))
+}
+
+/// Resolves `reference` relative to the file that contains it.
+fn resolve_reference(file: &Path, reference: &str) -> Option<PathBuf> {
+    let mut resolved = file.parent()?.to_path_buf();
+    for component in Path::new(reference).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(part) => resolved.push(part),
+            _ => return None,
+        }
+    }
+    Some(resolved)
+}
+
+/// Replaces references to renamed files in `file` with their new names.
+fn rewrite_references(file: &Path, renamed: &BTreeMap<PathBuf, PathBuf>) -> Result<()> {
+    if !has_extension(file, TEXT_EXTENSIONS) || renamed.is_empty() {
+        return Ok(());
+    }
+    let text = fs::read_to_string(file)?;
+    let mut output = String::with_capacity(text.len());
+    let mut last = 0;
+    for reference in find_references(&text) {
+        let Some(new_path) =
+            resolve_reference(file, &reference.path).and_then(|target| renamed.get(&target))
+        else {
+            continue;
+        };
+        let new_name = new_path.file_name().unwrap_or_default().to_string_lossy();
+        let directory_part = match reference.path.rfind('/') {
+            Some(slash) => &reference.path[..=slash],
+            None => "",
+        };
+        output.push_str(&text[last..reference.start]);
+        output.push_str(directory_part);
+        output.push_str(&new_name);
+        last = reference.end;
+    }
+    output.push_str(&text[last..]);
+    if output != text {
+        fs::write(file, output)?;
+    }
+    Ok(())
+}
+
+/// Writes `.br` and `.gz` variants next to compressible files, keeping only
+/// those that are smaller than the original.
+fn precompress(dist_dir: &Path) -> Result<BTreeMap<String, FileEntry>> {
+    let mut entries = BTreeMap::new();
+    for file in walk_files(dist_dir)? {
+        let contents = fs::read(&file)?;
+        let mut entry = FileEntry {
+            size: contents.len() as u64,
+            gzip: None,
+            brotli: None,
+        };
+        if has_extension(&file, COMPRESSED_EXTENSIONS) && contents.len() >= MIN_COMPRESS_SIZE {
+            for (encoding, level) in [(Encoding::Brotli, 11), (Encoding::Gzip, 9)] {
+                let compressed = compress_with_level(&contents, encoding, level)?;
+                if compressed.len() >= contents.len() {
+                    continue;
+                }
+                let mut variant = file.clone().into_os_string();
+                variant.push(".");
+                variant.push(encoding.extension());
+                fs::write(&variant, &compressed)?;
+                let size = Some(compressed.len() as u64);
+                match encoding {
+                    Encoding::Brotli => entry.brotli = size,
+                    Encoding::Gzip => entry.gzip = size,
+                }
+            }
+        }
+        entries.insert(site_path(dist_dir, &file), entry);
+    }
+    Ok(entries)
+}
+
+/// The path of a file inside the site, with forward slashes.
+fn site_path(dist_dir: &Path, file: &Path) -> String {
+    file.strip_prefix(dist_dir)
+        .unwrap_or(file)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
@@ -0,0 +1,171 @@
+use serde::Serialize;
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Error locations shown in the summary of a failed command.
+const SUMMARY_ERROR_LIMIT: usize = 5;
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+static STAGES: Mutex<Vec<StageRecord>> = Mutex::new(Vec::new());
+
+/// Prints a line of progress output. With `--json` it goes to stderr, so
+/// stdout carries nothing but JSON events.
+macro_rules! progress {
+    ($($arg:tt)*) => {
+        $crate::report::progress_line(format_args!($($arg)*))
+    };
+}
+pub(crate) use progress;
+
+/// A compiler error or warning, as cargo reports it or as found in a tool's output.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub level: String,
+    pub message: String,
+    /// `file:line:column` of the primary span, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+/// One timed step of a command: an external program or an internal step
+/// like assembling a release.
+#[derive(Debug, Clone, Serialize)]
+pub struct StageRecord {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    pub success: bool,
+    /// Stopped before it finished, because another stage failed or on Ctrl+C.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+    pub elapsed_ms: u128,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Switches to machine-readable output for the rest of the run.
+pub fn set_json_output(json: bool) {
+    JSON_OUTPUT.store(json, Ordering::Relaxed);
+}
+
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+pub fn progress_line(args: fmt::Arguments) {
+    if json_output() {
+        eprintln!("{}", args);
+    } else {
+        println!("{}", args);
+    }
+}
+
+/// Writes one JSON event as a line on stdout. Does nothing without `--json`.
+pub fn emit_event(event: &str, fields: serde_json::Value) {
+    if !json_output() {
+        return;
+    }
+    let mut object = serde_json::Map::new();
+    object.insert("event".to_string(), event.into());
+    if let serde_json::Value::Object(fields) = fields {
+        object.extend(fields);
+    }
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{}", serde_json::Value::Object(object));
+    let _ = stdout.flush();
+}
+
+/// Records a finished stage for the summary and, with `--json`, emits it.
+pub fn record_stage(stage: StageRecord) {
+    emit_event("stage", serde_json::to_value(&stage).unwrap_or_default());
+    STAGES.lock().unwrap().push(stage);
+}
+
+/// Runs `step` as a stage named `name`, timing it.
+pub fn stage<T>(name: &str, step: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let started = Instant::now();
+    let result = step();
+    record_stage(StageRecord {
+        name: name.to_string(),
+        command: None,
+        success: result.is_ok(),
+        cancelled: false,
+        elapsed_ms: started.elapsed().as_millis(),
+        diagnostics: Vec::new(),
+    });
+    result
+}
+
+/// Ends the run: prints the stage timings and, if the command failed, the
+/// failed stage and its first errors. With `--json` this is a `summary` event.
+pub fn finish(command: &str, result: &anyhow::Result<()>, elapsed: Duration) {
+    let stages = STAGES.lock().unwrap().clone();
+    let failed_stage = stages.iter().rev().find(|s| !s.success && !s.cancelled);
+    let errors: Vec<&Diagnostic> = failed_stage
+        .map(|stage| {
+            stage
+                .diagnostics
+                .iter()
+                .filter(|d| d.level == "error")
+                .take(SUMMARY_ERROR_LIMIT)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if json_output() {
+        emit_event(
+            "summary",
+            serde_json::json!({
+                "command": command,
+                "success": result.is_ok(),
+                "elapsed_ms": elapsed.as_millis(),
+                "stages": stages,
+                "failed_stage": failed_stage.map(|s| &s.name),
+                "errors": errors,
+                "error": result.as_ref().err().map(|e| format!("{:#}", e)),
+            }),
+        );
+        return;
+    }
+    if stages.is_empty() {
+        return;
+    }
+
+    println!("\nSummary:");
+    for stage in &stages {
+        println!(
+            "  [{}] {:<32} {:>8}",
+            match (stage.success, stage.cancelled) {
+                (true, _) => " ok ",
+                (false, true) => "stop",
+                (false, false) => "FAIL",
+            },
+            stage.name,
+            format_elapsed(stage.elapsed_ms)
+        );
+    }
+    println!("  total {:>41}", format_elapsed(elapsed.as_millis()));
+    if let Some(stage) = failed_stage {
+        println!("Failed stage: {}", stage.name);
+        if let Some(command) = &stage.command {
+            println!("  command: {}", command);
+        }
+        for error in errors {
+            match &error.location {
+                Some(location) => println!("  {}: {}", location, error.message),
+                None => println!("  {}", error.message),
+            }
+        }
+    }
+}
+
+fn format_elapsed(ms: u128) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    }
+}
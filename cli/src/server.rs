@@ -1,10 +1,215 @@
+use crate::live_reload::LiveReload;
+use crate::proxy::{self, ProxyRule};
+use crate::report::{self, progress};
+use crate::static_files;
+use crate::tls::TlsFiles;
 use anyhow::{bail, Context, Result};
+use hyper::{Client, Uri};
 use local_ip_address::local_ip;
-use std::path::Path;
-use warp::Filter;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use warp::filters::BoxedFilter;
+use warp::http::uri::Scheme;
+use warp::http::{HeaderName, HeaderValue, StatusCode};
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// How `serve_static` serves files, beyond the address it listens on.
+#[derive(Debug, Clone, Default)]
+pub struct ServeOptions {
+    /// Cache content-hashed assets as immutable instead of revalidating everything.
+    pub prod_headers: bool,
+    /// Serve over HTTPS with these files instead of plain HTTP.
+    pub tls: Option<TlsFiles>,
+    /// Answer unknown extensionless paths with `index.html` for client-side routing.
+    pub spa_fallback: bool,
+    /// Extra headers added to every response, overriding any the server sets.
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    /// Send COOP/COEP headers so the page can use `SharedArrayBuffer`.
+    pub cross_origin_isolated: bool,
+    /// Served with status 404 for unknown paths. Defaults to `404.html` in the
+    /// static directory, if it exists.
+    pub not_found_page: Option<PathBuf>,
+    /// Requests under these path prefixes are forwarded to other servers
+    /// instead of being served from the static directory.
+    pub proxy: Vec<ProxyRule>,
+}
 
 #[tokio::main]
-pub async fn serve_static(static_dir_path: &Path, port: u16, host: &str) -> Result<()> {
+pub async fn serve_static(
+    static_dir_path: &Path,
+    port: u16,
+    host: &str,
+    options: &ServeOptions,
+    live_reload: Option<&LiveReload>,
+) -> Result<()> {
+    let routes = site_routes(static_dir_path, options, live_reload)?;
+
+    progress!("Serving static files from '{}'", static_dir_path.display());
+    if live_reload.is_some() {
+        progress!("Live reload enabled");
+    }
+    if options.prod_headers {
+        progress!("Using production caching headers");
+    }
+    if options.spa_fallback {
+        progress!("Serving index.html for client-side routes");
+    }
+    if options.cross_origin_isolated {
+        progress!("Cross-origin isolation enabled (COOP/COEP)");
+    }
+    for rule in &options.proxy {
+        progress!("Proxying {} -> {}", rule.prefix_or_root(), rule.target());
+    }
+    progress!("Listening on: {}:{}", host, port);
+
+    let scheme = if options.tls.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    report::emit_event(
+        "listening",
+        serde_json::json!({
+            "url": format!("{}://{}:{}", scheme, host, port),
+            "static_dir": static_dir_path,
+        }),
+    );
+    if host == "0.0.0.0" {
+        progress!("Accessible at:");
+        progress!("  - {}://localhost:{}", scheme, port);
+        if let Ok(my_local_ip) = local_ip() {
+            progress!("  - {}://{}:{}", scheme, my_local_ip, port);
+        }
+    } else {
+        progress!("Accessible at: {}://{}:{}", scheme, host, port);
+    }
+
+    let host_addr: std::net::IpAddr = host.parse().context("Invalid host address")?;
+    let server = warp::serve(routes);
+    match &options.tls {
+        Some(tls) => {
+            server
+                .tls()
+                .cert_path(&tls.cert)
+                .key_path(&tls.key)
+                .run((host_addr, port))
+                .await
+        }
+        None => server.run((host_addr, port)).await,
+    }
+    Ok(())
+}
+
+/// Serves `static_dir_path` over plain HTTP on `addr` until `shutdown`
+/// completes; port 0 picks a free port. Must be called within a Tokio
+/// runtime. Returns the address it listens on and the server to spawn.
+pub fn bind_static(
+    static_dir_path: &Path,
+    options: &ServeOptions,
+    addr: SocketAddr,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<(SocketAddr, impl Future<Output = ()>)> {
+    let routes = site_routes(static_dir_path, options, None)?;
+    warp::serve(routes)
+        .try_bind_with_graceful_shutdown(addr, shutdown)
+        .with_context(|| format!("Failed to listen on {}", addr))
+}
+
+/// How long a local server gets to answer its first request.
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A server on a free port of 127.0.0.1, running on its own runtime while
+/// the CLI drives a browser against it. Stops when dropped.
+pub struct LocalServer {
+    runtime: Runtime,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: Option<JoinHandle<()>>,
+    url: String,
+}
+
+impl LocalServer {
+    /// Serves `static_dir_path` and waits until it answers.
+    pub fn start(static_dir_path: &Path, options: &ServeOptions) -> Result<Self> {
+        let runtime = Runtime::new().context("Failed to start the server runtime")?;
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let (addr, server) = {
+            let _runtime = runtime.enter();
+            bind_static(
+                static_dir_path,
+                options,
+                SocketAddr::from(([127, 0, 0, 1], 0)),
+                async {
+                    let _ = shutdown_rx.await;
+                },
+            )?
+        };
+        let server = runtime.spawn(server);
+        let url = format!("http://{}", addr);
+        let local_server = Self {
+            runtime,
+            shutdown: Some(shutdown_tx),
+            server: Some(server),
+            url,
+        };
+        local_server
+            .runtime
+            .block_on(wait_until_ready(&local_server.url))?;
+        Ok(local_server)
+    }
+
+    /// The server's address, e.g. `http://127.0.0.1:40123`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for LocalServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(server) = self.server.take() {
+            let _ = self.runtime.block_on(server);
+        }
+    }
+}
+
+/// Waits until the server answers `base_url` successfully.
+async fn wait_until_ready(base_url: &str) -> Result<()> {
+    let uri: Uri = base_url.parse().context("Invalid server URL")?;
+    let client = Client::new();
+    let started = Instant::now();
+    loop {
+        let error = match client.get(uri.clone()).await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => format!("status {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+        if started.elapsed() > READY_TIMEOUT {
+            bail!(
+                "The server at {} did not become ready within {}s ({})",
+                base_url,
+                READY_TIMEOUT.as_secs(),
+                error
+            );
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Everything the server answers: the live reload endpoints, proxied paths
+/// and the files in `static_dir_path`, with the configured headers.
+fn site_routes(
+    static_dir_path: &Path,
+    options: &ServeOptions,
+    live_reload: Option<&LiveReload>,
+) -> Result<BoxedFilter<(Response,)>> {
     if !static_dir_path.is_dir() {
         bail!(
             "Static directory '{}' does not exist or is not a directory.",
@@ -12,28 +217,122 @@ pub async fn serve_static(static_dir_path: &Path, port: u16, host: &str) -> Resu
         );
     }
 
-    let routes = warp::fs::dir(static_dir_path.to_path_buf())
-        .with(warp::reply::with::header(
-            "Cache-Control",
-            "no-cache, no-store, must-revalidate",
-        ))
-        .with(warp::reply::with::header("Pragma", "no-cache"))
-        .with(warp::reply::with::header("Expires", "0"));
+    // Files carry their own validators and Cache-Control; the generated
+    // live reload responses must never be cached.
+    let files = static_files::files(static_dir_path.to_path_buf(), options.prod_headers);
+    let mut routes: BoxedFilter<(Box<dyn Reply>,)> = match live_reload {
+        Some(live_reload) => no_store(live_reload.pages(static_dir_path))
+            .or(files)
+            .unify()
+            .boxed(),
+        None => files,
+    };
 
-    println!("Serving static files from '{}'", static_dir_path.display());
-    println!("Listening on: {}:{}", host, port);
+    if !options.proxy.is_empty() {
+        let scheme = if options.tls.is_some() {
+            Scheme::HTTPS
+        } else {
+            Scheme::HTTP
+        };
+        routes = proxy::routes(options.proxy.clone(), scheme)
+            .or(routes)
+            .unify()
+            .boxed();
+    }
 
-    if host == "0.0.0.0" {
-        println!("Accessible at:");
-        println!("  - http://localhost:{}", port);
-        if let Ok(my_local_ip) = local_ip() {
-            println!("  - http://{}:{}", my_local_ip, port);
-        }
-    } else {
-        println!("Accessible at: http://{}:{}", host, port);
+    // The live reload endpoints come first so that no proxy rule, not even
+    // one for `/`, can take them over.
+    if let Some(live_reload) = live_reload {
+        routes = no_store(live_reload.endpoints()).or(routes).unify().boxed();
     }
 
-    let host_addr: std::net::IpAddr = host.parse().context("Invalid host address")?;
-    warp::serve(routes).run((host_addr, port)).await;
-    Ok(())
+    if options.spa_fallback {
+        let index = match live_reload {
+            Some(live_reload) => no_store(live_reload.index(static_dir_path)),
+            None => static_files::file(static_dir_path.join("index.html"), options.prod_headers),
+        };
+        routes = routes.or(client_side_route().and(index)).unify().boxed();
+    }
+
+    let not_found_page = options
+        .not_found_page
+        .clone()
+        .or_else(|| Some(static_dir_path.join("404.html")).filter(|page| page.is_file()));
+    let headers = response_headers(options);
+    Ok(routes
+        .recover(move |rejection| not_found(rejection, not_found_page.clone()))
+        .unify()
+        .map(move |reply: Box<dyn Reply>| {
+            let mut response = reply.into_response();
+            for (name, value) in &headers {
+                response.headers_mut().insert(name.clone(), value.clone());
+            }
+            response
+        })
+        .boxed())
+}
+
+/// Marks the replies of `routes` as never to be cached.
+fn no_store(routes: BoxedFilter<(Box<dyn Reply>,)>) -> BoxedFilter<(Box<dyn Reply>,)> {
+    routes
+        .with(warp::reply::with::header("Cache-Control", "no-store"))
+        .map(|reply| Box::new(reply) as Box<dyn Reply>)
+        .boxed()
+}
+
+/// Matches GET and HEAD requests for paths whose last segment has no file
+/// extension, i.e. page navigations rather than missing assets.
+fn client_side_route() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    static_files::get_or_head()
+        .and(warp::path::tail())
+        .and_then(|_, tail: warp::path::Tail| async move {
+            let last_segment = tail.as_str().rsplit('/').next().unwrap_or("");
+            if last_segment.contains('.') {
+                Err(warp::reject::not_found())
+            } else {
+                Ok(())
+            }
+        })
+        .untuple_one()
+}
+
+/// The headers added to every response: cross-origin isolation first, so
+/// user-defined headers can override it.
+fn response_headers(options: &ServeOptions) -> Vec<(HeaderName, HeaderValue)> {
+    let mut headers = Vec::new();
+    if options.cross_origin_isolated {
+        headers.push((
+            HeaderName::from_static("cross-origin-opener-policy"),
+            HeaderValue::from_static("same-origin"),
+        ));
+        headers.push((
+            HeaderName::from_static("cross-origin-embedder-policy"),
+            HeaderValue::from_static("require-corp"),
+        ));
+    }
+    headers.extend(options.headers.iter().cloned());
+    headers
+}
+
+async fn not_found(
+    rejection: Rejection,
+    page: Option<PathBuf>,
+) -> Result<Box<dyn Reply>, Rejection> {
+    if !rejection.is_not_found() {
+        return Err(rejection);
+    }
+    let html = match &page {
+        Some(page) => tokio::fs::read_to_string(page).await.ok(),
+        None => None,
+    };
+    Ok(match html {
+        Some(html) => Box::new(warp::reply::with_status(
+            warp::reply::html(html),
+            StatusCode::NOT_FOUND,
+        )),
+        None => Box::new(warp::reply::with_status(
+            "404 Not Found",
+            StatusCode::NOT_FOUND,
+        )),
+    })
 }
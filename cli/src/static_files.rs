@@ -0,0 +1,726 @@
+use anyhow::Result;
+use headers::{
+    AcceptRanges, ContentRange, ETag, HeaderMapExt, IfModifiedSince, IfNoneMatch, IfRange,
+    LastModified, Range,
+};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use warp::filters::BoxedFilter;
+use warp::http::header::{
+    HeaderValue, ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE,
+    VARY,
+};
+use warp::http::{HeaderMap, Method, Response, StatusCode};
+use warp::hyper::body::Bytes;
+use warp::hyper::Body;
+use warp::{Filter, Rejection, Reply};
+
+/// Files smaller than this are not worth compressing on the fly.
+const MIN_COMPRESS_SIZE: u64 = 1024;
+/// Brotli quality for on-the-fly compression. Release builds precompress at
+/// the maximum quality; this trades some ratio for response time.
+const ON_THE_FLY_BROTLI_QUALITY: u32 = 5;
+
+const NO_CACHE: &str = "no-cache";
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// A content encoding the server can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    /// The `Content-Encoding` token.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// The extension of a precompressed variant, e.g. `app_bg.wasm.br`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gz",
+        }
+    }
+}
+
+// On-the-fly compressed bodies, keyed by file and encoding. An entry is only
+// reused while the file's ETag still matches.
+type CompressionCache = Arc<Mutex<HashMap<(PathBuf, Encoding), (String, Bytes)>>>;
+
+/// Serves the files under `root` for GET and HEAD requests. Directories serve
+/// their `index.html`. Responses are negotiated against `Accept-Encoding`
+/// (preferring a fresh precompressed `.br`/`.gz` next to the file), carry an
+/// ETag and Last-Modified, and answer conditional requests with 304 and
+/// single byte ranges with 206. Missing files are rejected as not found;
+/// files that cannot be read are answered with 500.
+///
+/// With `prod_headers`, content-hashed assets are marked immutable for a year;
+/// otherwise every response must be revalidated.
+pub fn files(root: PathBuf, prod_headers: bool) -> BoxedFilter<(Box<dyn Reply>,)> {
+    let cache = CompressionCache::default();
+    get_or_head()
+        .and(warp::path::tail())
+        .and(warp::header::headers_cloned())
+        .and_then(
+            move |method: Method, tail: warp::path::Tail, headers: HeaderMap| {
+                let root = root.clone();
+                let cache = cache.clone();
+                async move {
+                    let path = resolve_path(&root, tail.as_str())
+                        .await
+                        .ok_or_else(warp::reject::not_found)?;
+                    serve_file(&path, &method, &headers, prod_headers, &cache)
+                        .await
+                        .or_else(|e| error_reply(&path, e))
+                }
+            },
+        )
+        .boxed()
+}
+
+/// Serves the single file at `path` for any GET or HEAD request, negotiated
+/// and cached like `files`. Used to answer client-side routes with `index.html`.
+pub fn file(path: PathBuf, prod_headers: bool) -> BoxedFilter<(Box<dyn Reply>,)> {
+    let cache = CompressionCache::default();
+    get_or_head()
+        .and(warp::header::headers_cloned())
+        .and_then(move |method: Method, headers: HeaderMap| {
+            let path = path.clone();
+            let cache = cache.clone();
+            async move {
+                serve_file(&path, &method, &headers, prod_headers, &cache)
+                    .await
+                    .or_else(|e| error_reply(&path, e))
+            }
+        })
+        .boxed()
+}
+
+/// Matches GET and HEAD requests, extracting which of the two it is.
+pub fn get_or_head() -> impl Filter<Extract = (Method,), Error = warp::Rejection> + Clone {
+    warp::method().and_then(|method: Method| async move {
+        if method == Method::GET || method == Method::HEAD {
+            Ok(method)
+        } else {
+            Err(warp::reject::not_found())
+        }
+    })
+}
+
+/// Rejects a request for a file that does not exist as not found, so other
+/// routes and the 404 page get their turn. Any other failure is the server's.
+fn error_reply(path: &Path, error: anyhow::Error) -> Result<Box<dyn Reply>, Rejection> {
+    let missing = error.downcast_ref::<io::Error>().is_some_and(|e| {
+        matches!(
+            e.kind(),
+            io::ErrorKind::NotFound | io::ErrorKind::NotADirectory
+        )
+    });
+    if missing {
+        return Err(warp::reject::not_found());
+    }
+    eprintln!("Failed to serve '{}': {:#}", path.display(), error);
+    Ok(Box::new(warp::reply::with_status(
+        "500 Internal Server Error",
+        StatusCode::INTERNAL_SERVER_ERROR,
+    )))
+}
+
+async fn serve_file(
+    path: &Path,
+    method: &Method,
+    request_headers: &HeaderMap,
+    prod_headers: bool,
+    cache: &CompressionCache,
+) -> Result<Box<dyn Reply>> {
+    let metadata = tokio::fs::metadata(path).await?;
+    let modified = metadata.modified()?;
+    let content_type = content_type(path);
+    let base_etag = file_etag(metadata.len(), modified);
+    let accept_encoding = request_headers
+        .get(ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    // Byte ranges refer to the file itself, so range requests are not
+    // compressed. Like most servers, only the first range of several is served.
+    let range = request_headers
+        .typed_get::<Range>()
+        .and_then(|range| range.iter().next());
+    let mut source = Source::Identity;
+    if let Some(encoding) = negotiate_encoding(accept_encoding).filter(|_| range.is_none()) {
+        if let Some(variant) = fresh_precompressed(path, encoding, modified).await {
+            source = Source::Precompressed(encoding, variant);
+        } else if is_compressible(content_type) && metadata.len() >= MIN_COMPRESS_SIZE {
+            source = Source::OnTheFly(encoding);
+        }
+    }
+
+    // Each encoding is a different representation and needs its own tag.
+    let etag: ETag = match source.encoding() {
+        Some(encoding) => format!("\"{}-{}\"", base_etag, encoding.extension()),
+        None => format!("\"{}\"", base_etag),
+    }
+    .parse()?;
+    let cache_control = if prod_headers && is_hashed_asset(path) {
+        IMMUTABLE
+    } else {
+        NO_CACHE
+    };
+
+    let last_modified = LastModified::from(modified);
+
+    let mut builder = Response::builder()
+        .header(CACHE_CONTROL, cache_control)
+        .header(VARY, "Accept-Encoding");
+    let headers = builder.headers_mut().expect("builder has no errors yet");
+    headers.typed_insert(etag.clone());
+    headers.typed_insert(last_modified);
+    headers.typed_insert(AcceptRanges::bytes());
+
+    if !modified_since(request_headers, &etag, modified) {
+        return Ok(Box::new(
+            builder
+                .status(StatusCode::NOT_MODIFIED)
+                .body(Body::empty())?,
+        ));
+    }
+
+    let mut body = match &source {
+        Source::Identity => Bytes::from(tokio::fs::read(path).await?),
+        Source::Precompressed(_, variant) => Bytes::from(tokio::fs::read(variant).await?),
+        Source::OnTheFly(encoding) => compress_cached(path, *encoding, &base_etag, cache).await?,
+    };
+    // A range for an older version of the file is ignored in favour of all of it.
+    let range = range.filter(|_| {
+        request_headers
+            .typed_get::<IfRange>()
+            .is_none_or(|if_range| !if_range.is_modified(Some(&etag), Some(&last_modified)))
+    });
+    let mut status = StatusCode::OK;
+    if let Some(range) = range {
+        let len = body.len() as u64;
+        let Some((start, end)) = satisfiable_range(range, len) else {
+            let headers = builder.headers_mut().expect("builder has no errors yet");
+            headers.typed_insert(ContentRange::unsatisfied_bytes(len));
+            return Ok(Box::new(
+                builder
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .body(Body::empty())?,
+            ));
+        };
+        let headers = builder.headers_mut().expect("builder has no errors yet");
+        headers.typed_insert(ContentRange::bytes(start..=end, len)?);
+        body = body.slice(start as usize..=end as usize);
+        status = StatusCode::PARTIAL_CONTENT;
+    }
+    builder = builder
+        .header(CONTENT_TYPE, content_type)
+        .header(CONTENT_LENGTH, body.len());
+    if let Some(encoding) = source.encoding() {
+        builder = builder.header(
+            CONTENT_ENCODING,
+            HeaderValue::from_static(encoding.as_str()),
+        );
+    }
+    let body = if method == Method::HEAD {
+        Body::empty()
+    } else {
+        Body::from(body)
+    };
+    Ok(Box::new(builder.status(status).body(body)?))
+}
+
+/// The part of `len` bytes a range from a `Range` header covers, as inclusive
+/// offsets, or `None` if it lies outside them.
+fn satisfiable_range(range: (Bound<u64>, Bound<u64>), len: u64) -> Option<(u64, u64)> {
+    if len == 0 {
+        return None;
+    }
+    match range {
+        (Bound::Included(start), Bound::Included(end)) if start <= end && start < len => {
+            Some((start, end.min(len - 1)))
+        }
+        (Bound::Included(start), Bound::Unbounded) if start < len => Some((start, len - 1)),
+        // `bytes=-500` is the last 500 bytes.
+        (Bound::Unbounded, Bound::Included(suffix)) if suffix > 0 => {
+            Some((len.saturating_sub(suffix), len - 1))
+        }
+        _ => None,
+    }
+}
+
+/// Where a response body comes from.
+enum Source {
+    Identity,
+    Precompressed(Encoding, PathBuf),
+    OnTheFly(Encoding),
+}
+
+impl Source {
+    fn encoding(&self) -> Option<Encoding> {
+        match self {
+            Source::Identity => None,
+            Source::Precompressed(encoding, _) | Source::OnTheFly(encoding) => Some(*encoding),
+        }
+    }
+}
+
+/// Maps a request path onto a file under `root`, refusing to leave it.
+/// Directories resolve to their `index.html`.
+async fn resolve_path(root: &Path, tail: &str) -> Option<PathBuf> {
+    let decoded = percent_encoding::percent_decode_str(tail)
+        .decode_utf8()
+        .ok()?;
+    let mut path = root.to_path_buf();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => return None,
+            s if s.contains('\\') || s.contains(':') => return None,
+            s => path.push(s),
+        }
+    }
+    // Errors are left to `serve_file`, which tells missing files from broken ones.
+    if tokio::fs::metadata(&path)
+        .await
+        .is_ok_and(|metadata| metadata.is_dir())
+    {
+        path.push("index.html");
+    }
+    Some(path)
+}
+
+/// Whether the client's cached copy is stale. `If-None-Match` takes precedence
+/// over `If-Modified-Since`, as required by RFC 9110.
+fn modified_since(request_headers: &HeaderMap, etag: &ETag, modified: SystemTime) -> bool {
+    if let Some(if_none_match) = request_headers.typed_get::<IfNoneMatch>() {
+        return if_none_match.precondition_passes(etag);
+    }
+    if let Some(if_modified_since) = request_headers.typed_get::<IfModifiedSince>() {
+        return if_modified_since.is_modified(modified);
+    }
+    true
+}
+
+/// The content type for a file. `.wasm` is pinned to `application/wasm`, which
+/// `WebAssembly.instantiateStreaming` requires.
+pub fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("wasm") => "application/wasm",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("json" | "map") => "application/json",
+        _ => mime_guess::from_path(path)
+            .first_raw()
+            .unwrap_or("application/octet-stream"),
+    }
+}
+
+fn is_compressible(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    essence.starts_with("text/")
+        || matches!(
+            essence,
+            "application/wasm" | "application/json" | "application/xml" | "image/svg+xml"
+        )
+}
+
+/// Picks the best supported encoding from an `Accept-Encoding` header. Higher
+/// q-values win; on a tie Brotli is preferred for its better ratio.
+pub fn negotiate_encoding(accept_encoding: &str) -> Option<Encoding> {
+    let mut best: Option<(Encoding, f32)> = None;
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let token = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        let candidates: &[Encoding] = match token.as_str() {
+            "br" => &[Encoding::Brotli],
+            "gzip" | "x-gzip" => &[Encoding::Gzip],
+            "*" => &[Encoding::Brotli, Encoding::Gzip],
+            _ => &[],
+        };
+        for &encoding in candidates {
+            if quality <= 0.0 {
+                continue;
+            }
+            let better = match best {
+                None => true,
+                Some((current, q)) => {
+                    quality > q
+                        || (quality == q && encoding == Encoding::Brotli && current != encoding)
+                }
+            };
+            if better {
+                best = Some((encoding, quality));
+            }
+        }
+    }
+    best.map(|(encoding, _)| encoding)
+}
+
+/// Whether a file name carries a content hash, e.g. `app.3f9a1c2b7d4e5f60.js`.
+/// Such files never change under the same name and can be cached forever.
+pub fn is_hashed_asset(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .and_then(|stem| stem.rsplit_once(['.', '-']))
+        .is_some_and(|(_, hash)| hash.len() >= 8 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn file_etag(len: u64, modified: SystemTime) -> String {
+    let nanos = modified
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("{:x}-{:x}", len, nanos)
+}
+
+/// Returns `<path>.br`/`<path>.gz` if it exists and is not older than the file
+/// itself, so a stale variant is never served after a rebuild.
+async fn fresh_precompressed(
+    path: &Path,
+    encoding: Encoding,
+    modified: SystemTime,
+) -> Option<PathBuf> {
+    let mut variant = path.as_os_str().to_owned();
+    variant.push(".");
+    variant.push(encoding.extension());
+    let variant = PathBuf::from(variant);
+    let variant_modified = tokio::fs::metadata(&variant).await.ok()?.modified().ok()?;
+    (variant_modified >= modified).then_some(variant)
+}
+
+async fn compress_cached(
+    path: &Path,
+    encoding: Encoding,
+    etag: &str,
+    cache: &CompressionCache,
+) -> Result<Bytes> {
+    let key = (path.to_path_buf(), encoding);
+    if let Some((cached_etag, body)) = cache.lock().unwrap().get(&key) {
+        if cached_etag == etag {
+            return Ok(body.clone());
+        }
+    }
+    let raw = tokio::fs::read(path).await?;
+    let body = tokio::task::spawn_blocking(move || compress(&raw, encoding)).await??;
+    let body = Bytes::from(body);
+    cache
+        .lock()
+        .unwrap()
+        .insert(key, (etag.to_string(), body.clone()));
+    Ok(body)
+}
+
+/// Compresses `data` with the given encoding at the given quality level
+/// (0-11 for Brotli, 0-9 for gzip).
+pub fn compress_with_level(data: &[u8], encoding: Encoding, level: u32) -> Result<Vec<u8>> {
+    match encoding {
+        Encoding::Brotli => {
+            let mut output = Vec::new();
+            {
+                let mut writer = brotli::CompressorWriter::new(&mut output, 4096, level, 22);
+                writer.write_all(data)?;
+            }
+            Ok(output)
+        }
+        Encoding::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::new(level));
+            encoder.write_all(data)?;
+            Ok(encoder.finish()?)
+        }
+    }
+}
+
+fn compress(data: &[u8], encoding: Encoding) -> Result<Vec<u8>> {
+    let level = match encoding {
+        Encoding::Brotli => ON_THE_FLY_BROTLI_QUALITY,
+        Encoding::Gzip => flate2::Compression::default().level(),
+    };
+    compress_with_level(data, encoding, level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::fs;
+    use std::time::Duration;
+    use warp::http::Response;
+    use warp::test::request;
+
+    // A directory of files to serve, removed when dropped.
+    fn test_root(name: &str) -> TempDir {
+        TempDir::new("static-files", name)
+    }
+
+    // The files of `root`, without live reload.
+    fn serve(root: &TempDir) -> BoxedFilter<(Box<dyn Reply>,)> {
+        files(root.path().to_path_buf(), false)
+    }
+
+    fn set_modified(path: &Path, modified: SystemTime) {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    fn header<'a>(response: &'a Response<Bytes>, name: &str) -> &'a str {
+        response.headers()[name].to_str().unwrap()
+    }
+
+    #[test]
+    fn test_negotiate_encoding() {
+        assert_eq!(negotiate_encoding(""), None);
+        assert_eq!(negotiate_encoding("identity"), None);
+        assert_eq!(negotiate_encoding("gzip"), Some(Encoding::Gzip));
+        assert_eq!(negotiate_encoding("x-gzip"), Some(Encoding::Gzip));
+        assert_eq!(negotiate_encoding("BR"), Some(Encoding::Brotli));
+        // Brotli wins a tie, whatever the order.
+        assert_eq!(
+            negotiate_encoding("gzip, deflate, br"),
+            Some(Encoding::Brotli)
+        );
+        assert_eq!(negotiate_encoding("*"), Some(Encoding::Brotli));
+        // Higher q-values win.
+        assert_eq!(
+            negotiate_encoding("br;q=0.5, gzip;q=0.8"),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(
+            negotiate_encoding("gzip; q=0.2, *;q=0.9"),
+            Some(Encoding::Brotli)
+        );
+        // q=0 means "not acceptable".
+        assert_eq!(negotiate_encoding("br;q=0, gzip"), Some(Encoding::Gzip));
+        assert_eq!(negotiate_encoding("br;q=0, gzip;q=0.0"), None);
+    }
+
+    #[test]
+    fn test_is_hashed_asset() {
+        for hashed in [
+            "app.3f9a1c2b7d4e5f60.js",
+            "pkg/app_bg-3f9a1c2b.wasm",
+            "style.DEADBEEF.css",
+        ] {
+            assert!(is_hashed_asset(Path::new(hashed)), "{}", hashed);
+        }
+        for unhashed in [
+            "app.js",
+            "app_bg.wasm",
+            "app.3f9a1c2.js",
+            "app.3f9a1c2z.js",
+            "index.html",
+            "my-component.js",
+            "3f9a1c2b7d4e5f60.js",
+        ] {
+            assert!(!is_hashed_asset(Path::new(unhashed)), "{}", unhashed);
+        }
+    }
+
+    #[test]
+    fn test_content_type_pins_wasm() {
+        assert_eq!(content_type(Path::new("app_bg.wasm")), "application/wasm");
+        assert_eq!(
+            content_type(Path::new("app.mjs")),
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(content_type(Path::new("logo.png")), "image/png");
+        assert_eq!(
+            content_type(Path::new("LICENSE")),
+            "application/octet-stream"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_serves_wasm_as_application_wasm() {
+        let root = test_root("wasm");
+        root.write("pkg/app_bg.wasm", b"\0asm\x01\0\0\0");
+
+        let response = request()
+            .path("/pkg/app_bg.wasm")
+            .reply(&serve(&root))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(header(&response, "content-type"), "application/wasm");
+        assert_eq!(response.body().as_ref(), b"\0asm\x01\0\0\0");
+    }
+
+    #[tokio::test]
+    async fn test_matching_if_none_match_is_not_modified() {
+        let root = test_root("etag");
+        root.write("index.html", b"<h1>Hi</h1>");
+        let files = serve(&root);
+
+        let response = request().path("/").reply(&files).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = header(&response, "etag").to_string();
+
+        let response = request()
+            .path("/index.html")
+            .header("if-none-match", &etag)
+            .reply(&files)
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(response.body().is_empty());
+        assert_eq!(header(&response, "etag"), etag);
+
+        let response = request()
+            .path("/index.html")
+            .header("if-none-match", "\"something-else\"")
+            .reply(&files)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_stale_precompressed_variants_are_not_served() {
+        let root = test_root("stale");
+        let original = root.write("app.js", b"let x = 1;\n".repeat(200));
+        let brotli = root.write("app.js.br", b"old brotli");
+        let gzip = root.write("app.js.gz", b"old gzip");
+        let now = SystemTime::now();
+        set_modified(&brotli, now - Duration::from_secs(60));
+        set_modified(&gzip, now - Duration::from_secs(60));
+        set_modified(&original, now);
+        let files = serve(&root);
+
+        // The original was rebuilt after the variants, so it is compressed anew.
+        let response = request()
+            .path("/app.js")
+            .header("accept-encoding", "br")
+            .reply(&files)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(header(&response, "content-encoding"), "br");
+        assert_ne!(response.body().as_ref(), b"old brotli");
+        let mut decompressed = Vec::new();
+        brotli::BrotliDecompress(&mut response.body().as_ref(), &mut decompressed).unwrap();
+        assert_eq!(decompressed, fs::read(&original).unwrap());
+
+        // A fresh variant is served as it is.
+        set_modified(&gzip, now + Duration::from_secs(60));
+        let response = request()
+            .path("/app.js")
+            .header("accept-encoding", "gzip")
+            .reply(&files)
+            .await;
+        assert_eq!(header(&response, "content-encoding"), "gzip");
+        assert_eq!(response.body().as_ref(), b"old gzip");
+    }
+
+    #[tokio::test]
+    async fn test_range_requests() {
+        let root = test_root("range");
+        root.write("video.bin", b"0123456789");
+        let files = serve(&root);
+        let range = |value: &'static str| {
+            request()
+                .path("/video.bin")
+                .header("range", value)
+                .header("accept-encoding", "br")
+        };
+
+        let response = request().path("/video.bin").reply(&files).await;
+        assert_eq!(header(&response, "accept-ranges"), "bytes");
+
+        for (value, body, content_range) in [
+            ("bytes=2-4", "234", "bytes 2-4/10"),
+            ("bytes=7-", "789", "bytes 7-9/10"),
+            ("bytes=-3", "789", "bytes 7-9/10"),
+            ("bytes=8-100", "89", "bytes 8-9/10"),
+            ("bytes=0-0, 5-6", "0", "bytes 0-0/10"),
+        ] {
+            let response = range(value).reply(&files).await;
+            assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT, "{}", value);
+            assert_eq!(response.body().as_ref(), body.as_bytes(), "{}", value);
+            assert_eq!(header(&response, "content-range"), content_range);
+            assert_eq!(header(&response, "content-length"), body.len().to_string());
+            assert!(response.headers().get("content-encoding").is_none());
+        }
+
+        for value in ["bytes=10-", "bytes=5-2", "bytes=-0"] {
+            let response = range(value).reply(&files).await;
+            assert_eq!(
+                response.status(),
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                "{}",
+                value
+            );
+            assert_eq!(header(&response, "content-range"), "bytes */10");
+        }
+
+        // A range that cannot be parsed is ignored.
+        let response = range("bytes=a-b").reply(&files).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().as_ref(), b"0123456789");
+    }
+
+    #[tokio::test]
+    async fn test_if_range_for_another_version_serves_the_whole_file() {
+        let root = test_root("if-range");
+        root.write("video.bin", b"0123456789");
+        let files = serve(&root);
+        let etag = header(&request().path("/video.bin").reply(&files).await, "etag").to_string();
+
+        let response = request()
+            .path("/video.bin")
+            .header("range", "bytes=0-1")
+            .header("if-range", &etag)
+            .reply(&files)
+            .await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.body().as_ref(), b"01");
+
+        let response = request()
+            .path("/video.bin")
+            .header("range", "bytes=0-1")
+            .header("if-range", "\"an-older-version\"")
+            .reply(&files)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().as_ref(), b"0123456789");
+    }
+
+    #[tokio::test]
+    async fn test_missing_files_are_not_found() {
+        let root = test_root("missing");
+        root.write("index.html", b"");
+        let files = serve(&root);
+
+        for path in ["/missing.js", "/index.html/child", "/../secret"] {
+            assert!(!request().path(path).matches(&files).await, "{}", path);
+            let response = request().path(path).reply(&files).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_files_are_server_errors() {
+        let root = test_root("unreadable");
+        // A directory where `sub/index.html` should be a file cannot be read.
+        fs::create_dir_all(root.path().join("sub/index.html")).unwrap();
+
+        let response = request().path("/sub/").reply(&serve(&root)).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
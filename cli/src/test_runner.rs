@@ -1,28 +1,134 @@
-use crate::process_runner::{run_command, CommandConfig};
+use crate::build::{check_wasm_bindgen_cli, BuildBackend, CrateMetadata, WASM_TARGET};
+use crate::coverage::CoverageOptions;
+use crate::fuzz::FuzzOptions;
+use crate::orchestrator::{FailurePolicy, Job};
 use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
-use std::path::Path;
-
-pub fn run_base_tests() -> Result<()> {
-    println!("\nRunning base tests...");
-    let cmd_config = CommandConfig {
-        program_name: "cargo",
-        args: &["test", "-p", "base"],
-        current_dir: None,
-        env_vars: None,
-        inherit_stdio: true,
-    };
-    run_command(cmd_config).context("Failed to run base tests")?;
-    println!("base tests passed successfully!");
-    Ok(())
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Runs the app tests in Node instead of a browser, in place of a browser name.
+pub const NODE: &str = "node";
+
+/// How `cli test` runs the app tests and schedules the test suites.
+#[derive(Debug, Clone)]
+pub struct TestOptions {
+    pub browsers: Vec<String>,
+    pub headless: bool,
+    pub backend: BuildBackend,
+    pub failure_policy: FailurePolicy,
+    /// Test suites running at once; `None` runs them all together.
+    pub jobs: Option<usize>,
+    /// Measure the coverage of the base tests.
+    pub coverage: Option<CoverageOptions>,
+    /// Fuzz the base crate instead of running the tests.
+    pub fuzz: Option<FuzzOptions>,
+    /// Run the app tests in Node instead of the browsers. Suites configured
+    /// with `run_in_browser` are skipped.
+    pub node: bool,
+    /// The WebDriver for the one browser the app tests run in.
+    pub driver_path: Option<PathBuf>,
+    /// WebDriver capabilities the browser sessions start with.
+    pub webdriver_json: Option<PathBuf>,
+    /// Command-line arguments added to the browsers' capabilities.
+    pub browser_args: Vec<String>,
+    /// Where the capabilities of each browser are written for the runner.
+    pub webdriver_dir: PathBuf,
+    /// How long each app test may run; wasm-bindgen-test-runner allows 20s.
+    pub timeout: Option<Duration>,
+    /// Run only the tests whose names contain this.
+    pub filter: Option<String>,
+    /// How many times a failed suite runs again.
+    pub retries: u32,
+    /// Write the test results to this file as JUnit XML.
+    pub junit: Option<PathBuf>,
 }
 
-pub fn run_app_tests(app_path: &Path, browser: &str, headless: bool) -> Result<()> {
-    println!(
-        "\nRunning app WASM tests with {} (headless: {})",
-        browser, headless
-    );
+/// The base tests; `env_vars` can make them collect coverage.
+pub fn base_tests_job(
+    base_path: &Path,
+    env_vars: HashMap<String, String>,
+    filter: Option<&str>,
+) -> Job {
+    let mut args = vec!["test".to_string()];
+    args.extend(filter.map(String::from));
+    Job {
+        name: "base tests".to_string(),
+        program: "cargo".to_string(),
+        args,
+        current_dir: Some(base_path.to_path_buf()),
+        env_vars,
+        cargo_diagnostics: true,
+        retries: 0,
+        keep_output: false,
+    }
+}
+
+/// The app tests in each browser, or once in Node with `options.node`.
+pub fn app_tests_jobs(app_path: &Path, options: &TestOptions) -> Result<Vec<Job>> {
+    if options.node {
+        return Ok(vec![app_tests_job(app_path, NODE, options)?]);
+    }
+    if options.driver_path.is_some() && options.browsers.len() > 1 {
+        bail!(
+            "--driver-path needs a single browser, but the app tests run in {}. Pick one with --browser.",
+            options.browsers.join(", ")
+        );
+    }
+    options
+        .browsers
+        .iter()
+        .map(|browser| app_tests_job(app_path, browser, options))
+        .collect()
+}
 
+/// The app tests in `browser`, or in Node for [`NODE`]. Checks the tools
+/// they need up front, so a missing one is reported before anything starts.
+pub fn app_tests_job(app_path: &Path, browser: &str, options: &TestOptions) -> Result<Job> {
+    let browser = browser.to_lowercase();
+    let driver_path = match &options.driver_path {
+        Some(path) => Some(
+            path.canonicalize()
+                .with_context(|| format!("WebDriver '{}' not found", path.display()))?,
+        ),
+        None => None,
+    };
+    if browser == NODE {
+        check_node()?;
+    }
+
+    let mut env_vars = HashMap::from([("RUST_LOG".to_string(), "warn".to_string())]);
+    if let Some(timeout) = options.timeout {
+        env_vars.insert(
+            "WASM_BINDGEN_TEST_TIMEOUT".to_string(),
+            timeout.as_secs().max(1).to_string(),
+        );
+    }
+    if let Some(capabilities) = webdriver_capabilities(&browser, options)? {
+        env_vars.insert(
+            "WASM_BINDGEN_TEST_WEBDRIVER_JSON".to_string(),
+            capabilities.display().to_string(),
+        );
+    }
+
+    let mut job = if options.backend == BuildBackend::Cargo {
+        app_tests_job_with_cargo(app_path, &browser, options.headless, driver_path, env_vars)?
+    } else {
+        app_tests_job_with_wasm_pack(app_path, &browser, options.headless, driver_path, env_vars)?
+    };
+    job.args.extend(options.filter.clone());
+    Ok(job)
+}
+
+fn app_tests_job_with_wasm_pack(
+    app_path: &Path,
+    browser: &str,
+    headless: bool,
+    driver_path: Option<PathBuf>,
+    env_vars: HashMap<String, String>,
+) -> Result<Job> {
     if std::process::Command::new("wasm-pack")
         .arg("--version")
         .output()
@@ -31,30 +137,160 @@ pub fn run_app_tests(app_path: &Path, browser: &str, headless: bool) -> Result<(
         bail!("wasm-pack not found. Please install it.");
     }
 
-    let mut args = vec!["test"];
-    match browser.to_lowercase().as_str() {
-        "chrome" => args.push("--chrome"),
-        "firefox" => args.push("--firefox"),
-        "safari" => args.push("--safari"),
+    let (flag, driver_flag) = match browser {
+        "chrome" => ("--chrome", "--chromedriver"),
+        "firefox" => ("--firefox", "--geckodriver"),
+        "safari" => ("--safari", "--safaridriver"),
+        NODE => ("--node", ""),
         _ => bail!("Unsupported browser: {}", browser),
     };
-
+    let mut args = vec!["test".to_string(), flag.to_string()];
+    if let Some(driver_path) = driver_path {
+        args.push(driver_flag.to_string());
+        args.push(driver_path.display().to_string());
+    }
     if headless {
-        args.push("--headless");
-    }
-
-    let cmd_config = CommandConfig {
-        program_name: "wasm-pack",
-        args: &args,
-        current_dir: Some(app_path),
-        env_vars: Some(HashMap::from([(
-            "RUST_LOG".to_string(),
-            "warn".to_string(),
-        )])),
-        inherit_stdio: true,
-    };
+        args.push("--headless".to_string());
+    }
+    // wasm-pack takes the crate path first; what follows goes to `cargo test`.
+    args.push(".".to_string());
+
+    Ok(Job {
+        name: format!("app tests ({})", browser),
+        program: "wasm-pack".to_string(),
+        args,
+        current_dir: Some(app_path.to_path_buf()),
+        env_vars,
+        cargo_diagnostics: false,
+        retries: 0,
+        keep_output: false,
+    })
+}
 
-    run_command(cmd_config).context("Failed to run app tests")?;
-    println!("app tests with {} passed successfully!", browser);
+/// Runs the app tests the way `wasm-pack test` does: `cargo test` for the
+/// WASM target with `wasm-bindgen-test-runner` as the runner, which drives
+/// the browser through the WebDriver named in the environment, or Node.
+fn app_tests_job_with_cargo(
+    app_path: &Path,
+    browser: &str,
+    headless: bool,
+    driver_path: Option<PathBuf>,
+    mut env_vars: HashMap<String, String>,
+) -> Result<Job> {
+    let metadata = CrateMetadata::read(app_path)?;
+    check_wasm_bindgen_cli("wasm-bindgen-test-runner", &metadata.workspace_root)?;
+
+    env_vars.insert(
+        "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER".to_string(),
+        "wasm-bindgen-test-runner".to_string(),
+    );
+    if browser == NODE {
+        env_vars.insert("WASM_BINDGEN_TEST_ONLY_NODE".to_string(), "1".to_string());
+    } else {
+        let (driver_var, driver) = match browser {
+            "chrome" => ("CHROMEDRIVER", "chromedriver"),
+            "firefox" => ("GECKODRIVER", "geckodriver"),
+            "safari" => ("SAFARIDRIVER", "safaridriver"),
+            _ => bail!("Unsupported browser: {}", browser),
+        };
+        match driver_path {
+            Some(path) => {
+                env_vars.insert(driver_var.to_string(), path.display().to_string());
+            }
+            None if std::env::var_os(driver_var).is_none() => {
+                env_vars.insert(driver_var.to_string(), driver.to_string());
+            }
+            None => {}
+        }
+        env_vars.insert("WASM_BINDGEN_TEST_ONLY_WEB".to_string(), "1".to_string());
+        if !headless {
+            env_vars.insert("NO_HEADLESS".to_string(), "1".to_string());
+        }
+    }
+
+    Ok(Job {
+        name: format!("app tests ({})", browser),
+        program: "cargo".to_string(),
+        args: vec![
+            "test".to_string(),
+            "--target".to_string(),
+            WASM_TARGET.to_string(),
+        ],
+        current_dir: Some(app_path.to_path_buf()),
+        env_vars,
+        cargo_diagnostics: true,
+        retries: 0,
+        keep_output: false,
+    })
+}
+
+fn check_node() -> Result<()> {
+    if std::process::Command::new("node")
+        .arg("--version")
+        .output()
+        .is_err()
+    {
+        bail!("node not found. Install Node.js to run the app tests with --node.");
+    }
     Ok(())
 }
+
+/// Writes the capabilities `browser` starts with, `--webdriver-json` plus the
+/// `--browser-arg`s, and returns the file. `None` without either option.
+fn webdriver_capabilities(browser: &str, options: &TestOptions) -> Result<Option<PathBuf>> {
+    if options.webdriver_json.is_none() && options.browser_args.is_empty() {
+        return Ok(None);
+    }
+    let browser_options = match browser {
+        "chrome" => "goog:chromeOptions",
+        "firefox" => "moz:firefoxOptions",
+        _ => bail!(
+            "--webdriver-json and --browser-arg only work with chrome and firefox; the {} WebDriver ignores capabilities",
+            browser
+        ),
+    };
+
+    let mut capabilities = match &options.webdriver_json {
+        Some(path) => {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read '{}'", path.display()))?;
+            match serde_json::from_str(&text) {
+                Ok(Value::Object(capabilities)) => capabilities,
+                Ok(_) => bail!(
+                    "'{}' must hold a JSON object of WebDriver capabilities",
+                    path.display()
+                ),
+                Err(e) => bail!("Failed to parse '{}': {}", path.display(), e),
+            }
+        }
+        None => Map::new(),
+    };
+    if !options.browser_args.is_empty() {
+        let Value::Object(browser_capabilities) = capabilities
+            .entry(browser_options)
+            .or_insert_with(|| Value::Object(Map::new()))
+        else {
+            bail!("The capability '{}' must be a JSON object", browser_options);
+        };
+        let Value::Array(args) = browser_capabilities
+            .entry("args")
+            .or_insert_with(|| Value::Array(Vec::new()))
+        else {
+            bail!(
+                "The capability '{}.args' must be a JSON array",
+                browser_options
+            );
+        };
+        args.extend(options.browser_args.iter().cloned().map(Value::String));
+    }
+
+    fs::create_dir_all(&options.webdriver_dir)
+        .with_context(|| format!("Failed to create '{}'", options.webdriver_dir.display()))?;
+    let path = options.webdriver_dir.join(format!("{}.json", browser));
+    fs::write(
+        &path,
+        serde_json::to_string_pretty(&Value::Object(capabilities))?,
+    )
+    .with_context(|| format!("Failed to write '{}'", path.display()))?;
+    Ok(Some(path))
+}
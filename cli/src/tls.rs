@@ -0,0 +1,353 @@
+use crate::report::progress;
+use anyhow::{bail, Context, Result};
+use local_ip_address::local_ip;
+use rustls::pki_types::PrivateKeyDer;
+use rustls::ServerConfig;
+use rustls_pemfile::Item;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CERT_FILE: &str = "cert.pem";
+const KEY_FILE: &str = "key.pem";
+// The names the cached certificate was issued for, one per line.
+const NAMES_FILE: &str = "names.txt";
+
+/// The PEM certificate and private key the server presents over HTTPS.
+#[derive(Debug, Clone)]
+pub struct TlsFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl TlsFiles {
+    /// Checks that the files hold a certificate chain and a private key the
+    /// server accepts, the way it reads them. The server panics on files it
+    /// cannot use, so they are checked before it starts.
+    fn validate(&self) -> Result<()> {
+        let cert_pem = fs::read(&self.cert)
+            .with_context(|| format!("Failed to read '{}'", self.cert.display()))?;
+        let certs = rustls_pemfile::certs(&mut cert_pem.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Failed to parse certificate '{}'", self.cert.display()))?;
+        if certs.is_empty() {
+            bail!(
+                "Certificate file '{}' contains no PEM certificate.",
+                self.cert.display()
+            );
+        }
+
+        let key_pem = fs::read(&self.key)
+            .with_context(|| format!("Failed to read '{}'", self.key.display()))?;
+        let mut key: Option<PrivateKeyDer> = None;
+        for item in rustls_pemfile::read_all(&mut key_pem.as_slice()) {
+            let item = item
+                .with_context(|| format!("Failed to parse private key '{}'", self.key.display()))?;
+            key = Some(match item {
+                Item::Pkcs1Key(key) => key.into(),
+                Item::Pkcs8Key(key) => key.into(),
+                Item::Sec1Key(key) => key.into(),
+                _ => bail!(
+                    "Private key file '{}' must contain only the private key.",
+                    self.key.display()
+                ),
+            });
+        }
+        let Some(key) = key else {
+            bail!(
+                "Private key file '{}' contains no PEM private key (PKCS#1, PKCS#8 or SEC1).",
+                self.key.display()
+            );
+        };
+
+        ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .with_context(|| {
+                format!(
+                    "Private key '{}' cannot be used with certificate '{}'",
+                    self.key.display(),
+                    self.cert.display()
+                )
+            })?;
+        Ok(())
+    }
+}
+
+/// Decides whether and how to serve over TLS. Explicit `--cert`/`--key` files
+/// are used as given once they parse; otherwise `--https` uses a self-signed
+/// certificate cached in `cache_dir`, generated again whenever the names it
+/// must cover change (e.g. a new LAN IP).
+pub fn resolve_tls(
+    https: bool,
+    cert: Option<PathBuf>,
+    key: Option<PathBuf>,
+    host: &str,
+    cache_dir: &Path,
+) -> Result<Option<TlsFiles>> {
+    match (cert, key) {
+        (Some(cert), Some(key)) => {
+            for (path, what) in [(&cert, "Certificate"), (&key, "Private key")] {
+                if !path.is_file() {
+                    bail!("{} file '{}' does not exist.", what, path.display());
+                }
+            }
+            let files = TlsFiles { cert, key };
+            files.validate()?;
+            Ok(Some(files))
+        }
+        (None, None) if https => self_signed(&certificate_names(host), cache_dir).map(Some),
+        (None, None) => Ok(None),
+        _ => bail!("--cert and --key must be given together."),
+    }
+}
+
+/// The names a self-signed development certificate must be valid for, sorted
+/// so the cached list compares equal whatever order they were found in.
+fn certificate_names(host: &str) -> Vec<String> {
+    let mut names = vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ];
+    if let Ok(ip) = local_ip() {
+        names.push(ip.to_string());
+    }
+    if host != "0.0.0.0" && host != "::" {
+        names.push(host.to_string());
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn self_signed(names: &[String], cache_dir: &Path) -> Result<TlsFiles> {
+    let files = TlsFiles {
+        cert: cache_dir.join(CERT_FILE),
+        key: cache_dir.join(KEY_FILE),
+    };
+    let names_path = cache_dir.join(NAMES_FILE);
+    let wanted_names = names.join("\n");
+
+    let cached_names = fs::read_to_string(&names_path).unwrap_or_default();
+    if cached_names == wanted_names && files.validate().is_ok() {
+        progress!(
+            "Using cached self-signed certificate from '{}'",
+            cache_dir.display()
+        );
+        return Ok(files);
+    }
+
+    progress!(
+        "Generating a self-signed certificate for {}...",
+        names.join(", ")
+    );
+    let certified = rcgen::generate_simple_self_signed(names.to_vec())
+        .context("Failed to generate a self-signed certificate")?;
+    fs::create_dir_all(cache_dir).with_context(|| {
+        format!(
+            "Failed to create certificate directory '{}'",
+            cache_dir.display()
+        )
+    })?;
+    fs::write(&files.cert, certified.cert.pem())
+        .with_context(|| format!("Failed to write '{}'", files.cert.display()))?;
+    write_private(&files.key, &certified.key_pair.serialize_pem())
+        .with_context(|| format!("Failed to write '{}'", files.key.display()))?;
+    fs::write(&names_path, wanted_names)
+        .with_context(|| format!("Failed to write '{}'", names_path.display()))?;
+    progress!(
+        "Browsers will warn about this certificate until you trust '{}'.",
+        files.cert.display()
+    );
+    Ok(files)
+}
+
+/// Writes `contents` to a file only its owner can read, where that exists.
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        // The mode only applies to new files; an older key may be readable by others.
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(contents.as_bytes())
+    }
+    #[cfg(not(unix))]
+    fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use rustls::client::danger::ServerCertVerifier;
+    use rustls::client::WebPkiServerVerifier;
+    use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use rustls::RootCertStore;
+    use std::sync::Arc;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    // Whether a client trusting the certificate accepts it for `name`.
+    fn is_valid_for(cert_pem: &str, name: &str) -> bool {
+        let cert: CertificateDer = rustls_pemfile::certs(&mut cert_pem.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap();
+        let mut roots = RootCertStore::empty();
+        roots.add(cert.clone()).unwrap();
+        let verifier = WebPkiServerVerifier::builder(Arc::new(roots))
+            .build()
+            .unwrap();
+        verifier
+            .verify_server_cert(
+                &cert,
+                &[],
+                &ServerName::try_from(name.to_string()).unwrap(),
+                &[],
+                UnixTime::now(),
+            )
+            .is_ok()
+    }
+
+    #[test]
+    fn test_certificate_names_are_sorted_without_duplicates() {
+        for host in [
+            "localhost",
+            "127.0.0.1",
+            "::1",
+            "0.0.0.0",
+            "::",
+            "dev.example",
+        ] {
+            let names = certificate_names(host);
+            for name in ["localhost", "127.0.0.1", "::1"] {
+                assert!(names.iter().any(|n| n == name), "{:?}", names);
+            }
+            let mut sorted = names.clone();
+            sorted.sort();
+            sorted.dedup();
+            assert_eq!(names, sorted, "Names for host {}", host);
+            assert!(!names.iter().any(|n| n == "0.0.0.0" || n == "::"));
+        }
+        assert!(certificate_names("dev.example")
+            .iter()
+            .any(|n| n == "dev.example"));
+    }
+
+    #[test]
+    fn test_self_signed_certificate_covers_its_names() {
+        let cache = TempDir::new("tls", "san");
+        let files = self_signed(
+            &names(&["127.0.0.1", "dev.example", "localhost"]),
+            cache.path(),
+        )
+        .unwrap();
+        files.validate().unwrap();
+
+        let cert = fs::read_to_string(&files.cert).unwrap();
+        for name in ["localhost", "127.0.0.1", "dev.example"] {
+            assert!(is_valid_for(&cert, name), "{} should be covered", name);
+        }
+        for name in ["example.com", "10.0.0.1", "::1"] {
+            assert!(!is_valid_for(&cert, name), "{} should not be covered", name);
+        }
+        assert_eq!(
+            fs::read_to_string(cache.path().join(NAMES_FILE)).unwrap(),
+            "127.0.0.1\ndev.example\nlocalhost"
+        );
+    }
+
+    #[test]
+    fn test_self_signed_certificate_is_reused_until_the_names_change() {
+        let cache = TempDir::new("tls", "reuse");
+        let files = self_signed(&names(&["localhost"]), cache.path()).unwrap();
+        let cert = fs::read_to_string(&files.cert).unwrap();
+
+        let files = self_signed(&names(&["localhost"]), cache.path()).unwrap();
+        assert_eq!(fs::read_to_string(&files.cert).unwrap(), cert);
+
+        let files = self_signed(&names(&["192.168.1.20", "localhost"]), cache.path()).unwrap();
+        let new_cert = fs::read_to_string(&files.cert).unwrap();
+        assert_ne!(new_cert, cert);
+        assert!(is_valid_for(&new_cert, "192.168.1.20"));
+
+        // A damaged cache is replaced rather than handed to the server.
+        fs::write(&files.key, "not a key").unwrap();
+        let files = self_signed(&names(&["192.168.1.20", "localhost"]), cache.path()).unwrap();
+        files.validate().unwrap();
+        assert_ne!(fs::read_to_string(&files.cert).unwrap(), new_cert);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_self_signed_private_key_is_only_readable_by_its_owner() {
+        use std::os::unix::fs::PermissionsExt;
+        let cache = TempDir::new("tls", "mode");
+        // An older key that anyone could read gets its permissions fixed.
+        fs::write(cache.path().join(KEY_FILE), "old").unwrap();
+        fs::set_permissions(
+            cache.path().join(KEY_FILE),
+            fs::Permissions::from_mode(0o644),
+        )
+        .unwrap();
+
+        let files = self_signed(&names(&["localhost"]), cache.path()).unwrap();
+        let mode = fs::metadata(&files.key).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_resolve_tls_rejects_files_the_server_cannot_use() {
+        let cache = TempDir::new("tls", "invalid");
+        let generated = self_signed(&names(&["localhost"]), cache.path()).unwrap();
+        let resolve = |cert: &Path, key: &Path| {
+            resolve_tls(
+                false,
+                Some(cert.to_path_buf()),
+                Some(key.to_path_buf()),
+                "127.0.0.1",
+                cache.path(),
+            )
+        };
+
+        let files = resolve(&generated.cert, &generated.key).unwrap().unwrap();
+        assert_eq!(files.cert, generated.cert);
+
+        let garbage = cache.path().join("garbage.pem");
+        fs::write(&garbage, "not PEM at all").unwrap();
+        let error = resolve(&garbage, &generated.key).unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("contains no PEM certificate"),
+            "{:#}",
+            error
+        );
+        let error = resolve(&generated.cert, &garbage).unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("contains no PEM private key"),
+            "{:#}",
+            error
+        );
+        // A certificate passed as the key.
+        let error = resolve(&generated.cert, &generated.cert).unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("must contain only the private key"),
+            "{:#}",
+            error
+        );
+        let error = resolve(&generated.cert, &cache.path().join("missing.pem")).unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("does not exist"),
+            "{:#}",
+            error
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use crate::report::progress;
+use anyhow::{Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::Duration;
+
+/// How long the watcher waits for further events before acting on a batch.
+/// Editors and `cargo fmt` often write several files in quick succession.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// A directory to watch and what kind of sources it contains.
+#[derive(Debug, Clone)]
+pub struct WatchTarget {
+    pub path: PathBuf,
+    pub kind: SourceKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    /// Rust sources; a change requires rebuilding the WASM package.
+    Rust,
+    /// Files served as-is; a change only needs the browser to reload.
+    Static,
+}
+
+/// Paths and file names the watcher never reports.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    /// Directories whose contents are ignored, e.g. the build output directory.
+    pub dirs: Vec<PathBuf>,
+}
+
+impl IgnoreRules {
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        if self.dirs.iter().any(|dir| path.starts_with(dir)) {
+            return true;
+        }
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return true;
+        };
+        // Hidden files, editor swap/backup files and Vim's write test file.
+        name.starts_with('.')
+            || name.ends_with('~')
+            || name.ends_with(".swp")
+            || name.ends_with(".swx")
+            || name.ends_with(".tmp")
+            || name == "4913"
+    }
+}
+
+/// The files that changed in one debounced batch.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeSet {
+    pub rust: BTreeSet<PathBuf>,
+    pub static_files: BTreeSet<PathBuf>,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.rust.is_empty() && self.static_files.is_empty()
+    }
+
+    /// Adds `path` as a change to the innermost of `targets` that contains it.
+    /// Ignored paths and paths outside every target are dropped.
+    fn add(&mut self, path: PathBuf, targets: &[WatchTarget], ignore: &IgnoreRules) {
+        if ignore.is_ignored(&path) {
+            return;
+        }
+        let Some(kind) = targets
+            .iter()
+            .filter(|t| path.starts_with(&t.path))
+            .max_by_key(|t| t.path.components().count())
+            .map(|t| t.kind)
+        else {
+            return;
+        };
+        match kind {
+            SourceKind::Rust => self.rust.insert(path),
+            SourceKind::Static => self.static_files.insert(path),
+        };
+    }
+}
+
+/// Watches `targets` recursively and calls `on_change` with each debounced batch
+/// of relevant changes. Blocks for as long as the watcher is running.
+/// Events that arrive while `on_change` runs (e.g. during a build) form the next batch.
+pub fn watch<F>(
+    targets: &[WatchTarget],
+    ignore: &IgnoreRules,
+    debounce: Duration,
+    mut on_change: F,
+) -> Result<()>
+where
+    F: FnMut(&ChangeSet),
+{
+    let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to create file watcher")?;
+    for target in targets {
+        watcher
+            .watch(&target.path, RecursiveMode::Recursive)
+            .with_context(|| format!("Failed to watch '{}'", target.path.display()))?;
+        progress!("Watching {}", target.path.display());
+    }
+
+    loop {
+        // Block until something happens, then keep collecting until it goes quiet.
+        let mut events = vec![rx.recv().context("File watcher stopped")?];
+        loop {
+            match rx.recv_timeout(debounce) {
+                Ok(event) => events.push(event),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+
+        let mut changes = ChangeSet::default();
+        for event in events {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    eprintln!("File watcher error: {}", e);
+                    continue;
+                }
+            };
+            if !matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                continue;
+            }
+            for path in event.paths {
+                changes.add(path, targets, ignore);
+            }
+        }
+
+        if !changes.is_empty() {
+            on_change(&changes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets() -> Vec<WatchTarget> {
+        vec![
+            WatchTarget {
+                path: PathBuf::from("/ws/app/src"),
+                kind: SourceKind::Rust,
+            },
+            WatchTarget {
+                path: PathBuf::from("/ws/static"),
+                kind: SourceKind::Static,
+            },
+            // Rust sources inside the static directory belong to the inner target.
+            WatchTarget {
+                path: PathBuf::from("/ws/static/worker/src"),
+                kind: SourceKind::Rust,
+            },
+        ]
+    }
+
+    fn ignore() -> IgnoreRules {
+        IgnoreRules {
+            dirs: vec![PathBuf::from("/ws/static/pkg")],
+        }
+    }
+
+    #[test]
+    fn test_ignored_dirs_cover_everything_beneath_them() {
+        let ignore = ignore();
+        assert!(ignore.is_ignored(Path::new("/ws/static/pkg")));
+        assert!(ignore.is_ignored(Path::new("/ws/static/pkg/app_bg.wasm")));
+        assert!(ignore.is_ignored(Path::new("/ws/static/pkg/snippets/a/b.js")));
+        // Like a `pkg/` line in .gitignore, the rule matches whole path components.
+        assert!(!ignore.is_ignored(Path::new("/ws/static/pkg-old/app.js")));
+        assert!(!ignore.is_ignored(Path::new("/ws/static/index.html")));
+    }
+
+    #[test]
+    fn test_hidden_and_editor_temp_files_are_ignored() {
+        let ignore = IgnoreRules::default();
+        for name in [
+            ".index.html.swp",
+            "index.html.swp",
+            "index.html.swx",
+            "index.html~",
+            "lib.rs.tmp",
+            ".#lib.rs",
+            ".DS_Store",
+            "4913",
+        ] {
+            assert!(
+                ignore.is_ignored(&Path::new("/ws/app/src").join(name)),
+                "{} should be ignored",
+                name
+            );
+        }
+        for name in ["lib.rs", "index.html", "4913.rs", "swp.js", "tmp"] {
+            assert!(
+                !ignore.is_ignored(&Path::new("/ws/app/src").join(name)),
+                "{} should not be ignored",
+                name
+            );
+        }
+        assert!(
+            ignore.is_ignored(Path::new("/")),
+            "a path without a file name"
+        );
+    }
+
+    #[test]
+    fn test_changes_are_classified_by_the_innermost_target() {
+        let (targets, ignore) = (targets(), ignore());
+        let mut changes = ChangeSet::default();
+        for path in [
+            "/ws/app/src/lib.rs",
+            "/ws/static/index.html",
+            "/ws/static/worker/src/main.rs",
+            "/ws/static/pkg/app.js",
+            "/ws/static/.index.html.swp",
+            "/ws/base/Cargo.toml",
+        ] {
+            changes.add(PathBuf::from(path), &targets, &ignore);
+        }
+
+        assert_eq!(
+            changes.rust,
+            BTreeSet::from([
+                PathBuf::from("/ws/app/src/lib.rs"),
+                PathBuf::from("/ws/static/worker/src/main.rs"),
+            ])
+        );
+        assert_eq!(
+            changes.static_files,
+            BTreeSet::from([PathBuf::from("/ws/static/index.html")])
+        );
+    }
+
+    #[test]
+    fn test_change_set_is_empty_when_every_change_is_ignored() {
+        let (targets, ignore) = (targets(), ignore());
+        let mut changes = ChangeSet::default();
+        changes.add(PathBuf::from("/ws/static/pkg/app.js"), &targets, &ignore);
+        changes.add(PathBuf::from("/ws/app/src/lib.rs~"), &targets, &ignore);
+        changes.add(PathBuf::from("/elsewhere/lib.rs"), &targets, &ignore);
+        assert!(changes.is_empty());
+
+        // The same file changing twice in one batch is one change.
+        changes.add(PathBuf::from("/ws/app/src/lib.rs"), &targets, &ignore);
+        changes.add(PathBuf::from("/ws/app/src/lib.rs"), &targets, &ignore);
+        assert!(!changes.is_empty());
+        assert_eq!(changes.rust.len(), 1);
+    }
+}
@@ -0,0 +1 @@
+module_or_path = new URL('app_bg.cd5d4935a48c0672.wasm', import.meta.url);
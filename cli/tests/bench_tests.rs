@@ -0,0 +1,208 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::TempWorkspace;
+
+use std::fs;
+use std::process::Output;
+
+// A workspace whose `cargo bench` reports the median times listed in
+// `times`, one "group value nanoseconds" per line, the way criterion stores
+// them in the Cargo target directory.
+fn workspace(name: &str) -> TempWorkspace {
+    let workspace = TempWorkspace::new("bench", name);
+    fs::create_dir_all(workspace.path().join("base")).unwrap();
+    workspace.fake_tool(
+        "cargo",
+        "while read group value ns; do\n\
+           dir=\"${CARGO_TARGET_DIR:-$ROOT/target}/criterion/$group/$value/new\"\n\
+           mkdir -p \"$dir\"\n\
+           echo \"{\\\"full_id\\\":\\\"$group/$value\\\"}\" > \"$dir/benchmark.json\"\n\
+           echo \"{\\\"median\\\":{\\\"point_estimate\\\":$ns}}\" > \"$dir/estimates.json\"\n\
+           echo \"$group/$value time: $ns ns\"\n\
+         done < \"$ROOT/times\"",
+    );
+    workspace
+}
+
+fn set_times(workspace: &TempWorkspace, times: &str) {
+    workspace.write("times", times);
+}
+
+fn run(workspace: &TempWorkspace, args: &[&str]) -> Output {
+    workspace.cli(&["bench"]).args(args).output().unwrap()
+}
+
+#[test]
+fn test_bench_fails_on_regressions_against_the_saved_baseline() {
+    let workspace = workspace("regression");
+    set_times(&workspace, "shape_at 1000 1000\nserialize 1000 50000\n");
+    let output = run(&workspace, &["--save-baseline", "main"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(workspace
+        .path()
+        .join("target/bench-baselines/main.json")
+        .is_file());
+
+    // 8% slower is within the default 10%, 30% slower is not.
+    set_times(&workspace, "shape_at 1000 1080\nserialize 1000 65000\n");
+    let output = run(&workspace, &[]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("+8.0%"), "{}", stdout);
+    assert!(stdout.contains("+30.0% !"), "{}", stdout);
+    assert!(
+        stderr.contains(
+            "1 benchmark is slower than baseline 'main' by more than 10%: serialize/1000 (+30.0%)"
+        ),
+        "{}",
+        stderr
+    );
+
+    let output = run(&workspace, &["--max-regression", "50"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_bench_ignores_results_of_benchmarks_that_did_not_run() {
+    let workspace = workspace("stale");
+    set_times(&workspace, "shape_at 1000 1000\n");
+    let output = run(&workspace, &["--save-baseline", "old"]);
+    assert!(output.status.success());
+
+    set_times(&workspace, "shape_at 10000 9000\n");
+    let output = run(&workspace, &["--baseline", "old", "--save-baseline", "new"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let saved =
+        fs::read_to_string(workspace.path().join("target/bench-baselines/new.json")).unwrap();
+    assert!(saved.contains("shape_at/10000"), "{}", saved);
+    assert!(!saved.contains("shape_at/1000\""), "{}", saved);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("new"), "{}", stdout);
+}
+
+#[test]
+fn test_bench_rejects_baseline_names_that_are_not_file_names() {
+    let workspace = workspace("name");
+    set_times(&workspace, "shape_at 1000 1000\n");
+    let output = run(&workspace, &["--save-baseline", "../main"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid value '../main'"), "{}", stderr);
+}
+
+#[test]
+fn test_bench_browser_serves_the_harness_and_merges_its_results() {
+    use std::os::unix::fs::PermissionsExt;
+    let workspace = workspace("browser");
+    set_times(&workspace, "shape_at 1000 1000\n");
+    let root = workspace.path();
+    for (path, contents) in [
+        ("static/index.html", "<h1>App</h1>"),
+        ("static/bench.html", "<h1>Benchmarks</h1>"),
+        ("static/pkg/app.js", "export default function init() {}"),
+        ("static/pkg/app_bg.wasm", "\0asm"),
+        ("bench/browser-bench.mjs", ""),
+    ] {
+        fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+        fs::write(root.join(path), contents).unwrap();
+    }
+    // A `node` that fetches the page it was pointed at and reports one result.
+    let node = root.join("bin").join("node");
+    fs::write(
+        &node,
+        format!(
+            "#!/bin/sh\n\
+             curl -sf \"$2\" > \"{root}/page\" || exit 1\n\
+             echo '{{\"results\":[{{\"id\":\"browser/render_frame/1000\",\"time_ns\":2500000,\"iterations\":40}}]}}'\n",
+            root = root.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&node, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = run(
+        &workspace,
+        &["--browser", "--skip-build", "--save-baseline", "main"],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("page")).unwrap(),
+        "<h1>Benchmarks</h1>"
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[ ok ] browser benchmarks"), "{}", stdout);
+    let saved = fs::read_to_string(root.join("target/bench-baselines/main.json")).unwrap();
+    assert!(
+        saved.contains("\"browser/render_frame/1000\": 2500000"),
+        "{}",
+        saved
+    );
+    assert!(saved.contains("\"shape_at/1000\": 1000"), "{}", saved);
+}
+
+#[test]
+fn test_bench_keeps_the_baseline_when_the_run_regressed() {
+    let workspace = workspace("keep");
+    set_times(&workspace, "shape_at 1000 1000\n");
+    let output = run(&workspace, &["--save-baseline", "main"]);
+    assert!(output.status.success());
+    let baseline = workspace.read("target/bench-baselines/main.json");
+
+    set_times(&workspace, "shape_at 1000 2000\n");
+    let output = run(&workspace, &["--save-baseline", "main"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Not saving the results as baseline 'main'"),
+        "{}",
+        stdout
+    );
+    assert_eq!(workspace.read("target/bench-baselines/main.json"), baseline);
+
+    // Once the regression is accepted, the run can replace the baseline.
+    let output = run(
+        &workspace,
+        &["--max-regression", "150", "--save-baseline", "main"],
+    );
+    assert!(output.status.success());
+    assert!(workspace
+        .read("target/bench-baselines/main.json")
+        .contains("\"shape_at/1000\": 2000"));
+}
+
+#[test]
+fn test_bench_reads_criterion_results_from_the_cargo_target_dir() {
+    let workspace = workspace("target-dir");
+    set_times(&workspace, "shape_at 1000 1000\n");
+    let target_dir = workspace.path().join("build-output");
+    let output = workspace
+        .cli(&["bench", "--save-baseline", "main"])
+        .env("CARGO_TARGET_DIR", &target_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(target_dir.join("bench-baselines/main.json").is_file());
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::TempWorkspace;
+use std::fs;
+
+// A workspace with an empty cdylib app crate that locks wasm-bindgen 0.2.100.
+fn workspace(name: &str) -> TempWorkspace {
+    TempWorkspace::with_files(
+        "build",
+        name,
+        &[
+            ("Cargo.toml", "[workspace]\nmembers = [\"app\"]\n"),
+            (
+                "app/Cargo.toml",
+                "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                 [lib]\ncrate-type = [\"cdylib\", \"rlib\"]\n\n[features]\nwee_alloc = []\n",
+            ),
+            ("app/src/lib.rs", ""),
+            (
+                "Cargo.lock",
+                "version = 3\n\n[[package]]\nname = \"app\"\nversion = \"0.1.0\"\n\n\
+                 [[package]]\nname = \"wasm-bindgen\"\nversion = \"0.2.100\"\n",
+            ),
+            ("static/index.html", "<html></html>"),
+        ],
+    )
+}
+
+// Puts a fake `wasm-bindgen` that reports `version` first on PATH.
+#[cfg(unix)]
+fn fake_wasm_bindgen(workspace: &TempWorkspace, version: &str) {
+    workspace.fake_tool(
+        "wasm-bindgen",
+        &format!("echo \"wasm-bindgen {}\"", version),
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cargo_backend_rejects_mismatched_wasm_bindgen() {
+    let workspace = workspace("mismatch");
+    fake_wasm_bindgen(&workspace, "0.2.90");
+
+    let output = workspace.run_cli(&["build", "--backend", "cargo"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr
+            .contains("wasm-bindgen 0.2.90 is installed, but Cargo.lock uses wasm-bindgen 0.2.100"),
+        "Unexpected error: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("cargo install -f wasm-bindgen-cli --version 0.2.100"),
+        "Unexpected error: {}",
+        stderr
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_backend_can_be_chosen_in_project_config() {
+    let workspace = workspace("config");
+    fs::write(
+        workspace.path().join("project.toml"),
+        "[build]\nbackend = \"cargo\"\n",
+    )
+    .unwrap();
+    fake_wasm_bindgen(&workspace, "0.2.90");
+
+    let output = workspace.run_cli(&["build"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Cargo.lock uses wasm-bindgen 0.2.100"),
+        "The cargo backend should have run: {}",
+        stderr
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_json_output_reports_the_failed_stage_and_error_location() {
+    let workspace = workspace("json");
+    fs::write(
+        workspace.path().join("app/src/lib.rs"),
+        "pub fn broken() -> u32 {\n    \"x\"\n}\n",
+    )
+    .unwrap();
+    fake_wasm_bindgen(&workspace, "0.2.100");
+
+    let output = workspace.run_cli(&["--json", "build", "--backend", "cargo"]);
+    assert!(!output.status.success());
+    // stdout carries nothing but JSON events, ending with the summary.
+    let events: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("stdout line is not JSON ({}): {}", e, line))
+        })
+        .collect();
+    let summary = events.last().expect("No JSON events");
+    assert_eq!(summary["event"], "summary");
+    assert_eq!(summary["command"], "build");
+    assert_eq!(summary["success"], false);
+    assert_eq!(summary["failed_stage"], "build app (cargo)");
+    let error = &summary["errors"][0];
+    assert_eq!(error["message"], "mismatched types");
+    assert!(
+        error["location"]
+            .as_str()
+            .unwrap()
+            .starts_with("app/src/lib.rs:2:"),
+        "Unexpected location: {}",
+        error
+    );
+}
@@ -0,0 +1,150 @@
+//! Fixtures shared by the CLI integration tests. Each test file uses only
+//! some of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+/// The CLI binary with backtraces turned off, so errors read as users see them.
+pub fn cli() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_cli"));
+    command
+        .env("RUST_BACKTRACE", "0")
+        .env("RUST_LIB_BACKTRACE", "0")
+        .env_remove("CARGO_TARGET_DIR");
+    command
+}
+
+/// A throwaway Cargo workspace in the temp directory, removed when dropped.
+/// Fake tools written with [`TempWorkspace::fake_tool`] go to its `bin`,
+/// which comes first on the PATH of the CLI it runs.
+pub struct TempWorkspace(PathBuf);
+
+impl TempWorkspace {
+    /// An empty workspace named after the test `kind` and `name`, so
+    /// parallel tests never share one.
+    pub fn new(kind: &str, name: &str) -> Self {
+        let root =
+            std::env::temp_dir().join(format!("cli-{}-test-{}-{}", kind, std::process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("bin")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[workspace]\nmembers = []\n").unwrap();
+        Self(root.canonicalize().unwrap())
+    }
+
+    /// A workspace with `files`, given as path and contents. A `Cargo.toml`
+    /// among them replaces the empty one.
+    pub fn with_files(kind: &str, name: &str, files: &[(&str, &str)]) -> Self {
+        let workspace = Self::new(kind, name);
+        for (path, contents) in files {
+            workspace.write(path, contents);
+        }
+        workspace
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Writes `contents` to `path` in the workspace, creating its directories.
+    pub fn write(&self, path: &str, contents: &str) {
+        let path = self.0.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    pub fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.0.join(path))
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e))
+    }
+
+    /// Writes a shell script named `name` to `bin`; `$ROOT` in `body` is the workspace.
+    #[cfg(unix)]
+    pub fn fake_tool(&self, name: &str, body: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        let script = self.0.join("bin").join(name);
+        let mut body = body.replace("$ROOT", self.0.to_str().unwrap());
+        if name == "cargo" {
+            // The CLI asks Cargo for its target directory before running anything.
+            body = format!(
+                "if [ \"$1\" = \"metadata\" ]; then\n\
+                   echo \"{{\\\"target_directory\\\":\\\"${{CARGO_TARGET_DIR:-{}/target}}\\\"}}\"; exit 0\n\
+                 fi\n{}",
+                self.0.display(),
+                body
+            );
+        }
+        fs::write(&script, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    /// `bin` followed by the PATH the tests run with.
+    pub fn path_var(&self) -> String {
+        format!(
+            "{}:{}",
+            self.0.join("bin").display(),
+            std::env::var("PATH").unwrap_or_default()
+        )
+    }
+
+    /// The CLI pointed at this workspace with `--workspace`, then `args`.
+    pub fn cli(&self, args: &[&str]) -> Command {
+        let mut command = cli();
+        command
+            .arg("--workspace")
+            .arg(&self.0)
+            .args(args)
+            .env("PATH", self.path_var());
+        command
+    }
+
+    pub fn run_cli(&self, args: &[&str]) -> Output {
+        self.cli(args).output().unwrap()
+    }
+}
+
+impl Drop for TempWorkspace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A CLI server process, killed when a test finishes, even if it panics.
+pub struct ServerProcess(Child);
+
+impl ServerProcess {
+    /// Starts `command` and waits until it listens on `port`.
+    pub fn start(mut command: Command, port: u16) -> Self {
+        let process = Self(
+            command
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .expect("CLI should start"),
+        );
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(Instant::now() < deadline, "CLI server did not start");
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        process
+    }
+}
+
+impl Drop for ServerProcess {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+pub fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
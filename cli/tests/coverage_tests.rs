@@ -0,0 +1,99 @@
+mod common;
+
+use common::TempWorkspace;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+// A workspace with a small base crate, half of it tested.
+fn workspace(name: &str) -> TempWorkspace {
+    TempWorkspace::with_files(
+        "coverage",
+        name,
+        &[
+            ("Cargo.toml", "[workspace]\nmembers = [\"base\"]\n"),
+            (
+                "base/Cargo.toml",
+                "[package]\nname = \"base\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+            ),
+            (
+                "base/src/lib.rs",
+                "pub fn tested(x: u32) -> u32 {\n    x + 1\n}\n\n\
+                 pub fn untested(x: u32) -> u32 {\n    x * 2\n}\n",
+            ),
+            (
+                "base/tests/lib_tests.rs",
+                "#[test]\nfn test_tested() {\n    assert_eq!(base::tested(1), 2);\n}\n",
+            ),
+        ],
+    )
+}
+
+fn run(workspace: &TempWorkspace, args: &[&str]) -> Output {
+    workspace.cli(&["test"]).args(args).output().unwrap()
+}
+
+// Whether rustup's llvm-tools component is installed for the host.
+fn llvm_tools_installed() -> bool {
+    let sysroot = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .unwrap();
+    let rustlib = PathBuf::from(String::from_utf8_lossy(&sysroot.stdout).trim())
+        .join("lib")
+        .join("rustlib");
+    fs::read_dir(rustlib)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .any(|entry| entry.path().join("bin").join("llvm-profdata").is_file())
+}
+
+#[test]
+fn test_coverage_writes_lcov_and_html_and_enforces_the_minimum() {
+    if !llvm_tools_installed() {
+        eprintln!("Skipping: rustup component llvm-tools is not installed");
+        return;
+    }
+    let workspace = workspace("report");
+
+    let output = run(
+        &workspace,
+        &["--base", "--coverage", "--min-coverage", "90"],
+    );
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("[ ok ] base tests"), "{}", stdout);
+    assert!(stdout.contains("[FAIL] coverage report"), "{}", stdout);
+    assert!(
+        stderr.contains("Line coverage of 50.0% is below the minimum of 90%"),
+        "{}",
+        stderr
+    );
+
+    let coverage_dir = workspace.path().join("target").join("coverage");
+    let lcov = fs::read_to_string(coverage_dir.join("lcov.info")).unwrap();
+    assert!(lcov.contains("lib.rs"), "{}", lcov);
+    assert!(lcov.contains("LF:6") && lcov.contains("LH:3"), "{}", lcov);
+    assert!(coverage_dir.join("html").join("index.html").is_file());
+
+    let output = run(
+        &workspace,
+        &["--base", "--coverage", "--min-coverage", "50"],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_coverage_cannot_be_limited_to_the_app() {
+    let workspace = workspace("app");
+    let output = run(&workspace, &["--app", "--coverage"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot be used with"), "{}", stderr);
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use common::TempWorkspace;
+use serde_json::Value;
+use std::fs;
+use std::process::Output;
+
+// A workspace with a locked wasm-bindgen version.
+fn workspace(name: &str) -> TempWorkspace {
+    TempWorkspace::with_files(
+        "doctor",
+        name,
+        &[(
+            "Cargo.lock",
+            "version = 3\n\n[[package]]\nname = \"wasm-bindgen\"\nversion = \"0.2.100\"\n",
+        )],
+    )
+}
+
+// Adds a fake tool to the workspace's `bin`, which is all the doctor sees on PATH.
+#[cfg(unix)]
+fn fake_versioned_tool(workspace: &TempWorkspace, name: &str, version_line: &str) {
+    workspace.fake_tool(name, &format!("echo \"{}\"", version_line));
+}
+
+fn doctor_json(workspace: &TempWorkspace) -> (Output, Value) {
+    let output = workspace
+        .cli(&["doctor", "--json"])
+        .env("PATH", workspace.path().join("bin"))
+        .output()
+        .unwrap();
+    // Every line of stdout is a JSON event; the report is the `doctor` one.
+    let events: Vec<Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("stdout line is not JSON ({}): {}", e, line))
+        })
+        .collect();
+    let report = events
+        .iter()
+        .find(|event| event["event"] == "doctor")
+        .cloned()
+        .unwrap_or_else(|| panic!("No doctor event in {:?}", events));
+    (output, report)
+}
+
+fn check<'a>(report: &'a Value, name: &str) -> &'a Value {
+    report["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["name"] == name)
+        .unwrap_or_else(|| panic!("No '{}' check in {}", name, report))
+}
+
+#[test]
+fn test_doctor_reports_missing_tools_as_json() {
+    let workspace = workspace("missing");
+    let (output, report) = doctor_json(&workspace);
+
+    assert!(!output.status.success(), "Missing rustc should fail");
+    assert_eq!(check(&report, "rustc")["status"], "fail");
+    assert!(check(&report, "rustc")["fix"]
+        .as_str()
+        .unwrap()
+        .contains("rustup"));
+    // Only the selected backend's tools are required.
+    assert_eq!(check(&report, "wasm-pack")["status"], "fail");
+    assert_eq!(check(&report, "wasm-bindgen")["status"], "warn");
+    assert_eq!(check(&report, "wasm-opt")["status"], "warn");
+    assert_eq!(
+        report["counts"]["fail"].as_u64().unwrap()
+            + report["counts"]["warn"].as_u64().unwrap()
+            + report["counts"]["pass"].as_u64().unwrap(),
+        report["checks"].as_array().unwrap().len() as u64
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_doctor_detects_wasm_bindgen_version_mismatch() {
+    let workspace = workspace("mismatch");
+    fs::write(
+        workspace.path().join("project.toml"),
+        "[build]\nbackend = \"cargo\"\n",
+    )
+    .unwrap();
+    fake_versioned_tool(&workspace, "wasm-bindgen", "wasm-bindgen 0.2.90");
+    fake_versioned_tool(
+        &workspace,
+        "wasm-bindgen-test-runner",
+        "wasm-bindgen-test-runner 0.2.100",
+    );
+    fake_versioned_tool(&workspace, "wasm-opt", "wasm-opt version 118 (version_118)");
+
+    let (_, report) = doctor_json(&workspace);
+    let wasm_bindgen = check(&report, "wasm-bindgen");
+    assert_eq!(wasm_bindgen["status"], "fail");
+    assert!(wasm_bindgen["message"]
+        .as_str()
+        .unwrap()
+        .contains("Cargo.lock uses wasm-bindgen 0.2.100"));
+    assert_eq!(
+        wasm_bindgen["fix"],
+        "cargo install -f wasm-bindgen-cli --version 0.2.100"
+    );
+    assert_eq!(check(&report, "wasm-bindgen-test-runner")["status"], "pass");
+    assert_eq!(check(&report, "wasm-opt")["status"], "pass");
+    assert_eq!(check(&report, "wasm-pack")["status"], "warn");
+}
+
+#[test]
+fn test_doctor_reports_invalid_config_instead_of_stopping() {
+    let workspace = workspace("bad-config");
+    fs::write(
+        workspace.path().join("project.toml"),
+        "[build]\nbackend = 3\n",
+    )
+    .unwrap();
+
+    let (output, report) = doctor_json(&workspace);
+    assert!(!output.status.success());
+    let config = check(&report, "project configuration");
+    assert_eq!(config["status"], "fail");
+    assert!(config["message"].as_str().unwrap().contains("project.toml"));
+    assert!(report["checks"].as_array().unwrap().len() > 1);
+}
@@ -0,0 +1,84 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::TempWorkspace;
+
+use std::fs;
+use std::process::Output;
+
+fn workspace(name: &str) -> TempWorkspace {
+    TempWorkspace::with_files(
+        "e2e",
+        name,
+        &[
+            ("static/index.html", "<h1>E2E</h1>"),
+            ("static/pkg/app.js", "export default function init() {}"),
+            ("static/pkg/app_bg.wasm", "\0asm"),
+        ],
+    )
+}
+
+// A `pnpm` that records its arguments and the page at the URL it was
+// given, then exits with `exit_code`.
+fn fake_pnpm(workspace: &TempWorkspace, exit_code: i32) {
+    workspace.fake_tool(
+        "pnpm",
+        &format!(
+            "echo \"$@\" > \"$ROOT/args\"\n\
+             echo \"$E2E_BASE_URL\" > \"$ROOT/base_url\"\n\
+             curl -sf \"$E2E_BASE_URL/\" > \"$ROOT/page\"\n\
+             exit {}",
+            exit_code
+        ),
+    );
+}
+
+fn run(workspace: &TempWorkspace, args: &[&str]) -> Output {
+    workspace.cli(&["e2e"]).args(args).output().unwrap()
+}
+
+#[test]
+fn test_e2e_serves_the_site_to_playwright_on_a_free_port() {
+    let workspace = workspace("serve");
+    fake_pnpm(&workspace, 0);
+
+    let output = run(&workspace, &["--skip-build", "--project", "firefox"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let base_url = workspace.read("base_url");
+    assert!(
+        base_url.starts_with("http://127.0.0.1:") && !base_url.trim().ends_with(":8080"),
+        "Unexpected base URL: {}",
+        base_url
+    );
+    assert_eq!(workspace.read("page"), "<h1>E2E</h1>");
+    assert_eq!(workspace.read("args").trim(), "test:e2e --project firefox");
+}
+
+#[test]
+fn test_e2e_exits_with_playwrights_exit_code() {
+    let workspace = workspace("exit-code");
+    fake_pnpm(&workspace, 3);
+
+    let output = run(&workspace, &["--skip-build"]);
+    assert_eq!(output.status.code(), Some(3));
+    // The server was up while Playwright ran.
+    assert_eq!(workspace.read("page"), "<h1>E2E</h1>");
+}
+
+#[test]
+fn test_e2e_skip_build_requires_build_output() {
+    let workspace = workspace("no-build");
+    fake_pnpm(&workspace, 0);
+    fs::remove_file(workspace.path().join("static/pkg/app_bg.wasm")).unwrap();
+
+    let output = run(&workspace, &["--skip-build"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("'app_bg.wasm' not found"), "{}", stderr);
+    assert!(!workspace.path().join("args").exists());
+}
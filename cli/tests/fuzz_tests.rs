@@ -0,0 +1,136 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::TempWorkspace;
+
+use std::fs;
+use std::process::Output;
+
+// A workspace with two fuzz targets.
+fn workspace(name: &str) -> TempWorkspace {
+    TempWorkspace::with_files(
+        "fuzz",
+        name,
+        &[
+            ("base/fuzz/fuzz_targets/parse_json.rs", ""),
+            ("base/fuzz/fuzz_targets/parse_color.rs", ""),
+        ],
+    )
+}
+
+// A `cargo` with cargo-fuzz that records each run and crashes on `crashing_target`.
+fn fake_cargo(workspace: &TempWorkspace, crashing_target: &str) {
+    workspace.fake_tool(
+        "cargo",
+        &format!(
+            "if [ \"$3\" = \"--version\" ]; then echo cargo-fuzz 0.13.0; exit 0; fi\n\
+             echo \"$PWD $@\" >> \"$ROOT/runs\"\n\
+             if [ \"$4\" = \"{}\" ]; then echo 'deadly signal'; exit 77; fi\n\
+             echo \"Done 100 runs\"",
+            crashing_target
+        ),
+    );
+}
+
+fn run(workspace: &TempWorkspace, args: &[&str]) -> Output {
+    workspace.cli(&["test"]).args(args).output().unwrap()
+}
+
+fn runs(workspace: &TempWorkspace) -> Vec<String> {
+    let mut runs: Vec<String> = fs::read_to_string(workspace.path().join("runs"))
+        .unwrap_or_default()
+        .lines()
+        .map(String::from)
+        .collect();
+    runs.sort();
+    runs
+}
+
+#[test]
+fn test_fuzz_runs_each_target_for_the_given_time() {
+    let workspace = workspace("time");
+    fake_cargo(&workspace, "none");
+
+    let output = run(
+        &workspace,
+        &[
+            "--fuzz",
+            "parse_json",
+            "--fuzz",
+            "parse_color",
+            "--time",
+            "2m",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let base = workspace.path().join("base");
+    assert_eq!(
+        runs(&workspace),
+        [
+            format!(
+                "{} +nightly fuzz run parse_color -- -max_total_time=120",
+                base.display()
+            ),
+            format!(
+                "{} +nightly fuzz run parse_json -- -max_total_time=120",
+                base.display()
+            ),
+        ]
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout
+            .lines()
+            .any(|line| line.starts_with("[fuzz parse_json ]") && line.ends_with("Done 100 runs")),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_fuzz_fails_when_a_target_crashes() {
+    let workspace = workspace("crash");
+    fake_cargo(&workspace, "parse_color");
+
+    let output = run(
+        &workspace,
+        &[
+            "--fuzz",
+            "parse_json",
+            "--fuzz",
+            "parse_color",
+            "--keep-going",
+            "--time",
+            "5",
+        ],
+    );
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("[ ok ] fuzz parse_json"), "{}", stdout);
+    assert!(stdout.contains("[FAIL] fuzz parse_color"), "{}", stdout);
+    assert!(stderr.contains("Failed: fuzz parse_color"), "{}", stderr);
+}
+
+#[test]
+fn test_fuzz_rejects_unknown_targets() {
+    let workspace = workspace("unknown");
+    fake_cargo(&workspace, "none");
+
+    let output = run(&workspace, &["--fuzz", "parse_xml"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "Unknown fuzz target 'parse_xml'. Available targets: parse_color, parse_json"
+        ),
+        "{}",
+        stderr
+    );
+    assert!(runs(&workspace).is_empty());
+}
@@ -0,0 +1,99 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{free_port, ServerProcess, TempWorkspace};
+use hyper::{Client, StatusCode};
+use warp::Filter;
+
+// A site whose `wasm-pack` always fails, so `dev --watch` serves it with a
+// broken build.
+fn workspace(name: &str) -> TempWorkspace {
+    let workspace = TempWorkspace::with_files(
+        "live-reload",
+        name,
+        &[
+            (
+                "static/index.html",
+                "<html><body><h1>Live</h1></body></html>",
+            ),
+            ("app/src/lib.rs", ""),
+            ("base/src/lib.rs", ""),
+        ],
+    );
+    workspace.fake_tool("wasm-pack", "echo \"error: no build here\" >&2\nexit 1");
+    workspace.fake_tool("cargo", "exit 1");
+    workspace
+}
+
+fn start(workspace: &TempWorkspace, args: &[&str]) -> (ServerProcess, u16) {
+    let port = free_port();
+    let mut command = workspace.cli(args);
+    command.args(["--host", "127.0.0.1", "--port", &port.to_string()]);
+    (ServerProcess::start(command, port), port)
+}
+
+async fn get(port: u16, path: &str) -> (StatusCode, String) {
+    let response = Client::new()
+        .get(
+            format!("http://127.0.0.1:{}{}", port, path)
+                .parse()
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (status, String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[tokio::test]
+async fn test_watch_mode_serves_the_client_and_injects_it() {
+    let workspace = workspace("watch");
+    let (_server, port) = start(&workspace, &["dev", "--watch"]);
+
+    let (status, script) = get(port, "/__livereload.js").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(script.contains("__livereload"), "{}", script);
+
+    let (status, page) = get(port, "/").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        page,
+        "<html><body><h1>Live</h1><script src=\"/__livereload.js\"></script>\n</body></html>"
+    );
+}
+
+#[tokio::test]
+async fn test_without_watch_mode_there_is_no_client() {
+    let workspace = workspace("serve");
+    let (_server, port) = start(&workspace, &["serve"]);
+
+    let (status, _) = get(port, "/__livereload.js").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, page) = get(port, "/").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page, "<html><body><h1>Live</h1></body></html>");
+}
+
+#[tokio::test]
+async fn test_a_root_proxy_rule_does_not_take_over_the_live_reload_endpoints() {
+    let upstream =
+        warp::path::full().map(|path: warp::path::FullPath| format!("upstream {}", path.as_str()));
+    let (upstream, server) = warp::serve(upstream).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    let workspace = workspace("root-proxy");
+    let rule = format!("/=http://{}", upstream);
+    let (_server, port) = start(&workspace, &["dev", "--watch", "--proxy", &rule]);
+
+    let (status, script) = get(port, "/__livereload.js").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!script.starts_with("upstream"), "{}", script);
+    assert!(script.contains("__livereload"), "{}", script);
+
+    let (status, body) = get(port, "/api/items").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "upstream /api/items");
+}
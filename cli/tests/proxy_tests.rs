@@ -0,0 +1,240 @@
+mod common;
+
+use common::{cli, free_port, ServerProcess, TempWorkspace};
+use futures_util::{SinkExt, StreamExt};
+use hyper::{Body, Client, Method, Request, StatusCode};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+use tokio_tungstenite::tungstenite::Message;
+use warp::Filter;
+
+// Starts a stub upstream that echoes each request back as
+// "METHOD /path?query body", with the X-Forwarded headers it received as
+// X-Seen-Forwarded-*, and echoes WebSocket messages on /ws.
+fn start_stub_upstream() -> SocketAddr {
+    let websocket = warp::path("ws").and(warp::ws()).map(|ws: warp::ws::Ws| {
+        ws.on_upgrade(|socket| async move {
+            let (tx, rx) = socket.split();
+            let _ = rx.forward(tx).await;
+        })
+    });
+    let echo = warp::method()
+        .and(warp::path::full())
+        .and(
+            warp::query::raw()
+                .map(|q: String| format!("?{}", q))
+                .or(warp::any().map(String::new))
+                .unify(),
+        )
+        .and(warp::header::optional::<String>("x-forwarded-host"))
+        .and(warp::header::optional::<String>("x-forwarded-proto"))
+        .and(warp::body::bytes())
+        .map(
+            |method: Method,
+             path: warp::path::FullPath,
+             query: String,
+             forwarded_host: Option<String>,
+             forwarded_proto: Option<String>,
+             body: hyper::body::Bytes| {
+                let reply = warp::reply::with_header(
+                    format!(
+                        "{} {}{} {}",
+                        method,
+                        path.as_str(),
+                        query,
+                        String::from_utf8_lossy(&body)
+                    ),
+                    "x-seen-forwarded-host",
+                    forwarded_host.unwrap_or_default(),
+                );
+                warp::reply::with_header(
+                    reply,
+                    "x-seen-forwarded-proto",
+                    forwarded_proto.unwrap_or_default(),
+                )
+            },
+        );
+    let (addr, server) = warp::serve(websocket.or(echo)).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    addr
+}
+
+fn start_cli_server(proxy_rules: &[String]) -> (ServerProcess, u16) {
+    let port = free_port();
+    let mut command = cli();
+    command.args(["serve", "--host", "127.0.0.1", "--port", &port.to_string()]);
+    for rule in proxy_rules {
+        command.args(["--proxy", rule]);
+    }
+    (ServerProcess::start(command, port), port)
+}
+
+async fn request(method: Method, url: String, body: &str) -> (StatusCode, String) {
+    let request = Request::builder()
+        .method(method)
+        .uri(url)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = Client::new().request(request).await.unwrap();
+    let status = response.status();
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (status, String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[tokio::test]
+async fn test_proxy_rewrites_prefix_when_target_has_path() {
+    let upstream = start_stub_upstream();
+    let (_server, port) = start_cli_server(&[format!("/api=http://{}/", upstream)]);
+
+    let (status, body) = request(
+        Method::GET,
+        format!("http://127.0.0.1:{}/api/users?page=2", port),
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "GET /users?page=2 ");
+}
+
+#[tokio::test]
+async fn test_proxy_keeps_path_when_target_has_none() {
+    let upstream = start_stub_upstream();
+    let (_server, port) = start_cli_server(&[format!("/api=http://{}", upstream)]);
+
+    let (status, body) = request(
+        Method::POST,
+        format!("http://127.0.0.1:{}/api/items", port),
+        "{\"name\":\"circle\"}",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "POST /api/items {\"name\":\"circle\"}");
+}
+
+#[tokio::test]
+async fn test_proxy_sets_forwarded_headers() {
+    let upstream = start_stub_upstream();
+    let (_server, port) = start_cli_server(&[format!("/api=http://{}", upstream)]);
+
+    let response = Client::new()
+        .get(format!("http://127.0.0.1:{}/api", port).parse().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()["x-seen-forwarded-host"],
+        format!("127.0.0.1:{}", port).as_str()
+    );
+    assert_eq!(response.headers()["x-seen-forwarded-proto"], "http");
+}
+
+#[tokio::test]
+async fn test_proxy_forwards_https_as_the_scheme() {
+    let upstream = start_stub_upstream();
+    let workspace = TempWorkspace::with_files("proxy", "https", &[("static/index.html", "")]);
+    let port = free_port();
+    let mut command = workspace.cli(&["serve", "--https", "--host", "127.0.0.1"]);
+    command.args(["--port", &port.to_string()]);
+    command.args(["--proxy", &format!("/api=http://{}", upstream)]);
+    let _server = ServerProcess::start(command, port);
+
+    // Trust the self-signed certificate the server generated.
+    let cert_pem = workspace.read("target/dev-certs/cert.pem");
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut cert_pem.as_bytes()) {
+        roots.add(cert.unwrap()).unwrap();
+    }
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let stream = TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("127.0.0.1").unwrap(), stream)
+        .await
+        .expect("The TLS handshake should succeed");
+
+    let (mut sender, connection) = hyper::client::conn::handshake(stream).await.unwrap();
+    tokio::spawn(connection);
+    let request = Request::get("/api/users")
+        .header("host", format!("127.0.0.1:{}", port))
+        .body(Body::empty())
+        .unwrap();
+    let response = sender.send_request(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-seen-forwarded-proto"], "https");
+}
+
+#[tokio::test]
+async fn test_proxy_matches_whole_segments_and_longest_prefix() {
+    let upstream = start_stub_upstream();
+    let (_server, port) = start_cli_server(&[
+        format!("/api=http://{}/v1", upstream),
+        format!("/api/admin=http://{}/admin", upstream),
+    ]);
+
+    let (_, body) = request(
+        Method::GET,
+        format!("http://127.0.0.1:{}/api/admin/stats", port),
+        "",
+    )
+    .await;
+    assert_eq!(body, "GET /admin/stats ");
+
+    let (status, _) = request(Method::GET, format!("http://127.0.0.1:{}/apiary", port), "").await;
+    assert_eq!(
+        status,
+        StatusCode::NOT_FOUND,
+        "/apiary is not under /api and should not be proxied"
+    );
+}
+
+#[tokio::test]
+async fn test_proxy_still_serves_static_files() {
+    let upstream = start_stub_upstream();
+    let (_server, port) = start_cli_server(&[format!("/api=http://{}", upstream)]);
+
+    let (status, body) = request(
+        Method::GET,
+        format!("http://127.0.0.1:{}/index.html", port),
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("<html"));
+}
+
+#[tokio::test]
+async fn test_proxy_reports_unreachable_upstream() {
+    let closed_port = free_port();
+    let (_server, port) = start_cli_server(&[format!("/api=http://127.0.0.1:{}", closed_port)]);
+
+    let (status, _) = request(
+        Method::GET,
+        format!("http://127.0.0.1:{}/api/users", port),
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn test_proxy_relays_websockets() {
+    let upstream = start_stub_upstream();
+    let (_server, port) = start_cli_server(&[format!("/socket=ws://{}/ws", upstream)]);
+
+    let (mut socket, _) =
+        tokio_tungstenite::connect_async(format!("ws://127.0.0.1:{}/socket", port))
+            .await
+            .expect("WebSocket through the proxy should connect");
+    socket.send(Message::text("hello")).await.unwrap();
+    let reply = tokio::time::timeout(Duration::from_secs(5), socket.next())
+        .await
+        .expect("The echo should arrive")
+        .unwrap()
+        .unwrap();
+    assert_eq!(reply, Message::text("hello"));
+}
@@ -0,0 +1,267 @@
+mod common;
+
+use common::TempWorkspace;
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+// A workspace with a static site and a prebuilt package.
+fn workspace(name: &str) -> TempWorkspace {
+    let style = format!(
+        "body {{ margin: 0; }}\n{}",
+        ".shape { fill: red; }\n".repeat(100)
+    );
+    TempWorkspace::with_files(
+        "release",
+        name,
+        &[
+            (
+                "static/index.html",
+                "<link rel=\"stylesheet\" href=\"css/style.css\">\n\
+                 <script type=\"module\">import { run_app } from './main.js';</script>\n",
+            ),
+            (
+                "static/main.js",
+                "import { initializeApp } from './js/appManager.js';\n",
+            ),
+            (
+                "static/js/appManager.js",
+                "import init from '../pkg/app.js';\n\
+                 const next = await import(`../pkg/app.js?v=${version}`);\n\
+                 const url = 'https://example.com/app.js';\n",
+            ),
+            ("static/css/style.css", &style),
+            ("static/pkg/app.js", "stale dev build"),
+            ("static/pkg/old_module.js", "stale dev build"),
+            (
+                "target/release-pkg/app.js",
+                "module_or_path = new URL('app_bg.wasm', import.meta.url);\n",
+            ),
+            ("target/release-pkg/app_bg.wasm", "\0asm"),
+            ("target/release-pkg/app.d.ts", "export {};"),
+            ("target/release-pkg/package.json", "{}"),
+            // Release the prebuilt package, as if the WASM build wrote it there.
+            (
+                "project.toml",
+                "[paths]\nout-dir = \"target/release-pkg\"\n",
+            ),
+        ],
+    )
+}
+
+fn release(workspace: &TempWorkspace) -> Output {
+    workspace.run_cli(&["release", "--skip-build"])
+}
+
+fn manifest(dist: &Path) -> serde_json::Value {
+    serde_json::from_str(&fs::read_to_string(dist.join(".release-manifest.json")).unwrap()).unwrap()
+}
+
+fn hashed_name(manifest: &serde_json::Value, original: &str) -> String {
+    let hashed = manifest["assets"][original]
+        .as_str()
+        .unwrap_or_else(|| panic!("{} was not hashed", original));
+    hashed.rsplit('/').next().unwrap().to_string()
+}
+
+#[test]
+fn test_release_hashes_assets_and_rewrites_references() {
+    let workspace = workspace("hash");
+    let output = release(&workspace);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let dist = workspace.path().join("dist");
+    let manifest = manifest(&dist);
+    let main = hashed_name(&manifest, "main.js");
+    let app_manager = hashed_name(&manifest, "js/appManager.js");
+    let app = hashed_name(&manifest, "pkg/app.js");
+    let wasm = hashed_name(&manifest, "pkg/app_bg.wasm");
+    let style = hashed_name(&manifest, "css/style.css");
+
+    let index = fs::read_to_string(dist.join("index.html")).unwrap();
+    assert!(
+        index.contains(&format!("href=\"css/{}\"", style)),
+        "{}",
+        index
+    );
+    assert!(index.contains(&format!("'./{}'", main)), "{}", index);
+    let main_js = fs::read_to_string(dist.join(&main)).unwrap();
+    assert!(main_js.contains(&format!("'./js/{}'", app_manager)));
+    let app_manager_js = fs::read_to_string(dist.join("js").join(&app_manager)).unwrap();
+    assert!(app_manager_js.contains(&format!("'../pkg/{}'", app)));
+    assert!(app_manager_js.contains(&format!("`../pkg/{}?v=${{version}}`", app)));
+    assert!(app_manager_js.contains("'https://example.com/app.js'"));
+    let app_js = fs::read_to_string(dist.join("pkg").join(&app)).unwrap();
+    assert!(app_js.contains(&format!("'{}'", wasm)));
+
+    assert!(!dist.join("main.js").exists());
+    assert!(!dist.join("pkg").join("old_module.js").exists());
+    assert!(!dist.join("pkg").join("package.json").exists());
+    assert!(!dist.join("pkg").join("app.d.ts").exists());
+    assert!(
+        !fs::read_to_string(dist.join("pkg").join(&app))
+            .unwrap()
+            .contains("stale"),
+        "The package should come from the build, not the static directory"
+    );
+}
+
+#[test]
+fn test_release_precompresses_large_text_files() {
+    let workspace = workspace("compress");
+    assert!(release(&workspace).status.success());
+
+    let dist = workspace.path().join("dist");
+    let manifest = manifest(&dist);
+    let style = format!("css/{}", hashed_name(&manifest, "css/style.css"));
+    assert!(dist.join(format!("{}.br", style)).is_file());
+    assert!(dist.join(format!("{}.gz", style)).is_file());
+    let entry = &manifest["files"][&style];
+    assert!(entry["brotli"].as_u64().unwrap() < entry["size"].as_u64().unwrap());
+
+    // Small files are not worth compressing.
+    assert!(!dist.join("index.html.gz").exists());
+}
+
+#[test]
+fn test_release_hashes_are_stable_and_follow_content() {
+    let workspace = workspace("stable");
+    assert!(release(&workspace).status.success());
+    let first = manifest(&workspace.path().join("dist"));
+
+    assert!(release(&workspace).status.success());
+    let second = manifest(&workspace.path().join("dist"));
+    assert_eq!(first["assets"], second["assets"]);
+
+    // A change to the WASM file changes the hash of everything that leads to it.
+    fs::write(
+        workspace.path().join("target/release-pkg/app_bg.wasm"),
+        "\0asm changed",
+    )
+    .unwrap();
+    assert!(release(&workspace).status.success());
+    let third = manifest(&workspace.path().join("dist"));
+    for asset in [
+        "pkg/app_bg.wasm",
+        "pkg/app.js",
+        "js/appManager.js",
+        "main.js",
+    ] {
+        assert_ne!(first["assets"][asset], third["assets"][asset], "{}", asset);
+    }
+    assert_eq!(
+        first["assets"]["css/style.css"],
+        third["assets"]["css/style.css"]
+    );
+}
+
+#[test]
+fn test_release_refuses_to_replace_an_unrelated_directory() {
+    let workspace = workspace("unrelated");
+    fs::create_dir_all(workspace.path().join("dist")).unwrap();
+    fs::write(workspace.path().join("dist").join("notes.txt"), "keep me").unwrap();
+
+    let output = release(&workspace);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("was not created by `cli release`"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(workspace.path().join("dist").join("notes.txt").is_file());
+}
+
+#[test]
+fn test_release_checks_the_size_budget_of_a_prebuilt_package() {
+    let workspace = workspace("budget");
+    let output = workspace.run_cli(&["release", "--skip-build", "--size-budget", "2B"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("exceeds the size budget"));
+    assert!(!workspace.path().join("dist").exists());
+
+    let output = workspace.run_cli(&["release", "--skip-build", "--size-budget", "1KB"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("% used"));
+}
+
+#[test]
+fn test_release_leaves_out_the_benchmark_harness() {
+    let workspace = workspace("bench");
+    workspace.write(
+        "static/bench.html",
+        "<script type=\"module\">import { runBenchmarks } from './js/bench.js';</script>\n",
+    );
+    workspace.write("static/js/bench.js", "import init from '../pkg/app.js';\n");
+    let output = release(&workspace);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let dist = workspace.path().join("dist");
+    assert!(!dist.join("bench.html").exists());
+    let manifest = manifest(&dist);
+    assert!(manifest["files"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .all(|file| !file.contains("bench")));
+    hashed_name(&manifest, "js/appManager.js");
+}
+
+#[test]
+fn test_release_keeps_a_web_app_manifest_apart_from_its_own() {
+    let workspace = workspace("web-manifest");
+    let web_manifest = "{\"name\": \"Drawing App\", \"start_url\": \"/\"}";
+    workspace.write("static/manifest.json", web_manifest);
+    let output = release(&workspace);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(workspace.read("dist/manifest.json"), web_manifest);
+    hashed_name(&manifest(&workspace.path().join("dist")), "main.js");
+
+    // A web app manifest does not make a directory look like a release.
+    workspace.write("site/manifest.json", web_manifest);
+    let site = workspace.path().join("site");
+    let output = workspace.run_cli(&[
+        "release",
+        "--skip-build",
+        "--out-dir",
+        site.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("was not created by `cli release`"));
+    assert_eq!(workspace.read("site/manifest.json"), web_manifest);
+}
+
+#[test]
+fn test_release_refuses_an_out_dir_inside_the_static_directory() {
+    let workspace = workspace("nested");
+    for out_dir in ["static/dist", "static/../static/dist", "static"] {
+        let out_dir = workspace.path().join(out_dir);
+        let out_dir = out_dir.to_str().unwrap();
+        let output = workspace.run_cli(&["release", "--skip-build", "--out-dir", out_dir]);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("is inside the static directory"),
+            "{}: {}",
+            out_dir,
+            stderr
+        );
+    }
+    assert!(!workspace.path().join("static/dist").exists());
+    assert!(workspace.path().join("static/index.html").is_file());
+}
@@ -0,0 +1,215 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{free_port, ServerProcess, TempWorkspace};
+use hyper::header::HeaderMap;
+use hyper::{Body, Client, Method, Request, StatusCode};
+
+const INDEX: &str = "<html><body><h1>App</h1></body></html>";
+
+// A site with a stylesheet and a 404 page; its `wasm-pack` always fails, so
+// `dev --watch` serves it with a broken build.
+fn workspace(name: &str) -> TempWorkspace {
+    let workspace = TempWorkspace::with_files(
+        "server",
+        name,
+        &[
+            ("static/index.html", INDEX),
+            ("static/css/style.css", "h1 { color: red; }"),
+            ("static/404.html", "<h1>Lost</h1>"),
+            ("custom-404.html", "<h1>Custom lost</h1>"),
+            ("app/src/lib.rs", ""),
+            ("base/src/lib.rs", ""),
+        ],
+    );
+    workspace.fake_tool("wasm-pack", "exit 1");
+    workspace.fake_tool("cargo", "exit 1");
+    workspace
+}
+
+fn start(workspace: &TempWorkspace, args: &[&str]) -> (ServerProcess, u16) {
+    let port = free_port();
+    let mut command = workspace.cli(args);
+    command.args(["--host", "127.0.0.1", "--port", &port.to_string()]);
+    (ServerProcess::start(command, port), port)
+}
+
+async fn request(method: Method, port: u16, path: &str) -> (StatusCode, HeaderMap, String) {
+    let request = Request::builder()
+        .method(method)
+        .uri(format!("http://127.0.0.1:{}{}", port, path))
+        .body(Body::empty())
+        .unwrap();
+    let response = Client::new().request(request).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (
+        status,
+        headers,
+        String::from_utf8_lossy(&bytes).into_owned(),
+    )
+}
+
+#[tokio::test]
+async fn test_spa_serves_index_for_client_side_routes() {
+    let workspace = workspace("spa");
+    let (_server, port) = start(&workspace, &["serve", "--spa"]);
+
+    let (status, _, body) = request(Method::GET, port, "/settings/profile").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, INDEX);
+
+    let (status, headers, body) = request(Method::HEAD, port, "/settings").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(headers["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    assert!(body.is_empty());
+
+    // Real files still win, and missing assets are not pages.
+    let (_, _, body) = request(Method::GET, port, "/css/style.css").await;
+    assert_eq!(body, "h1 { color: red; }");
+    let (status, _, _) = request(Method::GET, port, "/css/missing.css").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_spa_in_watch_mode_injects_the_client_and_answers_head() {
+    let workspace = workspace("spa-watch");
+    let (_server, port) = start(&workspace, &["dev", "--watch", "--spa"]);
+
+    let (status, _, body) = request(Method::GET, port, "/settings").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        body.contains("<script src=\"/__livereload.js\"></script>"),
+        "{}",
+        body
+    );
+
+    let (status, headers, body) = request(Method::HEAD, port, "/settings").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["cache-control"], "no-store");
+    assert!(body.is_empty());
+
+    let (status, _, _) = request(Method::HEAD, port, "/").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_without_spa_unknown_routes_are_not_found() {
+    let workspace = workspace("no-spa");
+    let (_server, port) = start(&workspace, &["serve"]);
+
+    for method in [Method::GET, Method::HEAD] {
+        let (status, _, _) = request(method, port, "/settings").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
+
+#[tokio::test]
+async fn test_headers_are_added_to_every_response() {
+    let workspace = workspace("headers");
+    let (_server, port) = start(
+        &workspace,
+        &[
+            "serve",
+            "--header",
+            "X-Frame-Options: DENY",
+            "--header",
+            "Cache-Control: no-store",
+        ],
+    );
+
+    for path in ["/", "/css/style.css", "/missing"] {
+        let (_, headers, _) = request(Method::GET, port, path).await;
+        assert_eq!(headers["x-frame-options"], "DENY", "{}", path);
+        // User headers replace the server's own.
+        assert_eq!(headers["cache-control"], "no-store", "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn test_cross_origin_isolation_headers_can_be_overridden() {
+    let workspace = workspace("coop-coep");
+    let (_server, port) = start(
+        &workspace,
+        &[
+            "serve",
+            "--cross-origin-isolated",
+            "--header",
+            "Cross-Origin-Embedder-Policy: credentialless",
+        ],
+    );
+
+    for path in ["/", "/missing"] {
+        let (_, headers, _) = request(Method::GET, port, path).await;
+        assert_eq!(headers["cross-origin-opener-policy"], "same-origin");
+        assert_eq!(headers["cross-origin-embedder-policy"], "credentialless");
+    }
+}
+
+#[tokio::test]
+async fn test_cross_origin_isolation_is_off_by_default() {
+    let workspace = workspace("no-coop-coep");
+    let (_server, port) = start(&workspace, &["serve"]);
+    let (_, headers, _) = request(Method::GET, port, "/").await;
+    assert!(headers.get("cross-origin-opener-policy").is_none());
+    assert!(headers.get("cross-origin-embedder-policy").is_none());
+}
+
+#[tokio::test]
+async fn test_not_found_page() {
+    let workspace = workspace("404");
+
+    // 404.html in the static directory is the default.
+    let (server, port) = start(&workspace, &["serve"]);
+    let (status, headers, body) = request(Method::GET, port, "/missing").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(headers["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    assert_eq!(body, "<h1>Lost</h1>");
+    drop(server);
+
+    let page = workspace.path().join("custom-404.html");
+    let (_server, port) = start(
+        &workspace,
+        &["serve", "--not-found-page", page.to_str().unwrap()],
+    );
+    let (status, _, body) = request(Method::GET, port, "/missing.js").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body, "<h1>Custom lost</h1>");
+}
+
+#[test]
+fn test_missing_not_found_page_is_an_error() {
+    let workspace = workspace("404-missing");
+    let output = workspace.run_cli(&["serve", "--not-found-page", "/nonexistent/404.html"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("404 page '/nonexistent/404.html' does not exist."),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_https_certificate_is_cached_in_the_cargo_target_dir() {
+    let workspace = workspace("target-dir");
+    let target_dir = workspace.path().join("build-output");
+    let port = free_port();
+    let mut command = workspace.cli(&["serve", "--https", "--host", "127.0.0.1"]);
+    command
+        .args(["--port", &port.to_string()])
+        .env("CARGO_TARGET_DIR", &target_dir);
+    let server = ServerProcess::start(command, port);
+    drop(server);
+
+    assert!(target_dir.join("dev-certs/cert.pem").is_file());
+    assert!(!workspace.path().join("target/dev-certs").exists());
+}
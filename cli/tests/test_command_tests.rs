@@ -0,0 +1,467 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::TempWorkspace;
+
+use std::fs;
+use std::process::{Child, Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn workspace(name: &str) -> TempWorkspace {
+    let workspace = TempWorkspace::new("test-cmd", name);
+    for dir in ["app", "base", "pids"] {
+        fs::create_dir_all(workspace.path().join(dir)).unwrap();
+    }
+    workspace
+}
+
+// A wasm-pack whose tests start a long-running helper, like a WebDriver,
+// and wait for it. The helper's pid goes to `pids/<browser flag>`.
+fn hanging_wasm_pack(workspace: &TempWorkspace) {
+    workspace.fake_tool(
+        "wasm-pack",
+        "if [ \"$1\" = \"--version\" ]; then echo \"wasm-pack 0.13.1\"; exit 0; fi\n\
+         sleep 60 &\n\
+         echo $! > \"$ROOT/pids/$2.tmp\" && mv \"$ROOT/pids/$2.tmp\" \"$ROOT/pids/$2\"\n\
+         wait",
+    );
+}
+
+fn test_command(workspace: &TempWorkspace, args: &[&str]) -> Command {
+    let mut command = workspace.cli(&["test"]);
+    command.args(args);
+    command
+}
+
+fn run(workspace: &TempWorkspace, args: &[&str]) -> Output {
+    test_command(workspace, args).output().unwrap()
+}
+
+fn helper_pid(workspace: &TempWorkspace, browser_flag: &str) -> Option<String> {
+    fs::read_to_string(workspace.path().join("pids").join(browser_flag))
+        .ok()
+        .map(|pid| pid.trim().to_string())
+}
+
+fn is_running(pid: &str) -> bool {
+    let output = Command::new("ps")
+        .args(["-o", "stat=", "-p", pid])
+        .output()
+        .unwrap();
+    let stat = String::from_utf8_lossy(&output.stdout);
+    // Killed orphans can linger as zombies until something reaps them.
+    !stat.trim().is_empty() && !stat.trim().starts_with('Z')
+}
+
+fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+fn wait_for_exit(mut child: Child, timeout: Duration) -> Output {
+    if !wait_until(timeout, || child.try_wait().unwrap().is_some()) {
+        let _ = child.kill();
+        panic!("cli test did not exit within {:?}", timeout);
+    }
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_suites_run_in_parallel_with_prefixed_output() {
+    let workspace = workspace("parallel");
+    // Each browser waits for the other to start, which only works in parallel.
+    workspace.fake_tool(
+        "wasm-pack",
+        "if [ \"$1\" = \"--version\" ]; then echo \"wasm-pack 0.13.1\"; exit 0; fi\n\
+         touch \"$ROOT/pids/started$2\"\n\
+         other=--firefox; [ \"$2\" = \"--firefox\" ] && other=--chrome\n\
+         for i in $(seq 100); do\n\
+           if [ -e \"$ROOT/pids/started$other\" ]; then echo \"saw $other\"; exit 0; fi\n\
+           sleep 0.1\n\
+         done\n\
+         exit 1",
+    );
+    workspace.fake_tool("cargo", "echo \"test result: ok\"");
+
+    let output = run(&workspace, &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "Suites should run in parallel: {}{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        stdout.contains("[app tests (chrome) ] saw --firefox"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("[app tests (firefox)] saw --chrome"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("[base tests         ] test result: ok"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_keep_going_runs_every_suite_and_reports_all_failures() {
+    let workspace = workspace("keep-going");
+    workspace.fake_tool(
+        "wasm-pack",
+        "if [ \"$1\" = \"--version\" ]; then echo \"wasm-pack 0.13.1\"; exit 0; fi\n\
+         sleep 1\n\
+         [ \"$2\" = \"--chrome\" ]",
+    );
+    workspace.fake_tool("cargo", "exit 101");
+
+    let output = run(&workspace, &["--keep-going"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("[ ok ] app tests (chrome)"), "{}", stdout);
+    assert!(stdout.contains("[FAIL] app tests (firefox)"), "{}", stdout);
+    assert!(stdout.contains("[FAIL] base tests"), "{}", stdout);
+    assert!(
+        stderr.contains("Failed: base tests, app tests (firefox)"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_fail_fast_stops_the_other_suites_and_their_children() {
+    let workspace = workspace("fail-fast");
+    hanging_wasm_pack(&workspace);
+    // Fails once both browsers have started their helpers.
+    workspace.fake_tool(
+        "cargo",
+        "while [ ! -e \"$ROOT/pids/--chrome\" ] || [ ! -e \"$ROOT/pids/--firefox\" ]; do sleep 0.1; done\n\
+         exit 101",
+    );
+
+    let child = test_command(&workspace, &[])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let output = wait_for_exit(child, Duration::from_secs(30));
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[FAIL] base tests"), "{}", stdout);
+    assert!(stdout.contains("[stop] app tests (chrome)"), "{}", stdout);
+    assert!(stdout.contains("Failed stage: base tests"), "{}", stdout);
+    for browser_flag in ["--chrome", "--firefox"] {
+        let pid = helper_pid(&workspace, browser_flag).unwrap();
+        assert!(
+            wait_until(Duration::from_secs(5), || !is_running(&pid)),
+            "The helper of {} is still running",
+            browser_flag
+        );
+    }
+}
+
+#[test]
+fn test_helpers_left_behind_by_a_finished_suite_are_stopped() {
+    let workspace = workspace("leftover");
+    // Passes at once, leaving its helper running with the output still open.
+    workspace.fake_tool(
+        "wasm-pack",
+        "if [ \"$1\" = \"--version\" ]; then echo \"wasm-pack 0.13.1\"; exit 0; fi\n\
+         sleep 60 &\n\
+         echo $! > \"$ROOT/pids/$2.tmp\" && mv \"$ROOT/pids/$2.tmp\" \"$ROOT/pids/$2\"",
+    );
+    workspace.fake_tool("cargo", "echo \"test result: ok\"");
+
+    let child = test_command(&workspace, &["--browser", "chrome"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let output = wait_for_exit(child, Duration::from_secs(30));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("[ ok ] app tests (chrome)"), "{}", stdout);
+    let pid = helper_pid(&workspace, "--chrome").unwrap();
+    assert!(
+        wait_until(Duration::from_secs(5), || !is_running(&pid)),
+        "The helper is still running"
+    );
+}
+
+#[test]
+fn test_interrupt_stops_every_suite_and_their_children() {
+    let workspace = workspace("interrupt");
+    hanging_wasm_pack(&workspace);
+
+    let child = test_command(&workspace, &["--app"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let pids_written = wait_until(Duration::from_secs(20), || {
+        helper_pid(&workspace, "--chrome").is_some()
+            && helper_pid(&workspace, "--firefox").is_some()
+    });
+    assert!(pids_written, "The app test suites did not start");
+
+    let status = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let output = wait_for_exit(child, Duration::from_secs(20));
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Interrupted"), "{}", stderr);
+    for browser_flag in ["--chrome", "--firefox"] {
+        let pid = helper_pid(&workspace, browser_flag).unwrap();
+        assert!(
+            wait_until(Duration::from_secs(5), || !is_running(&pid)),
+            "The helper of {} is still running",
+            browser_flag
+        );
+    }
+}
+
+#[test]
+fn test_junit_reports_each_test_of_every_suite() {
+    let workspace = workspace("junit");
+    // libtest's output, with a failure and what it printed.
+    workspace.fake_tool(
+        "cargo",
+        "echo 'running 3 tests'\n\
+         echo 'test state::adds ... ok'\n\
+         echo 'test state::removes ... FAILED'\n\
+         echo 'test state::slow ... ignored, too slow'\n\
+         echo\n\
+         echo 'failures:'\n\
+         echo\n\
+         echo '---- state::removes stdout ----'\n\
+         echo 'left: 1 < right: 2 & more'\n\
+         echo\n\
+         echo 'failures:'\n\
+         echo '    state::removes'\n\
+         exit 101",
+    );
+    // wasm-bindgen-test's output.
+    workspace.fake_tool(
+        "wasm-pack",
+        "if [ \"$1\" = \"--version\" ]; then echo \"wasm-pack 0.13.1\"; exit 0; fi\n\
+         echo 'running 1 test'\n\
+         echo 'test app_wasm_tests::mounts ... ok'",
+    );
+
+    let junit = workspace.path().join("results/junit.xml");
+    let output = run(
+        &workspace,
+        &[
+            "--browser",
+            "chrome",
+            "--keep-going",
+            "--junit",
+            junit.to_str().unwrap(),
+        ],
+    );
+    assert!(!output.status.success());
+    let xml = fs::read_to_string(&junit).unwrap();
+    assert!(
+        xml.contains(
+            "<testsuites name=\"cli test\" tests=\"4\" failures=\"1\" errors=\"0\" skipped=\"1\""
+        ),
+        "{}",
+        xml
+    );
+    assert!(
+        xml.contains("<testsuite name=\"base tests\" tests=\"3\" failures=\"1\""),
+        "{}",
+        xml
+    );
+    assert!(
+        xml.contains("<testcase name=\"state::adds\" classname=\"base tests\"/>"),
+        "{}",
+        xml
+    );
+    assert!(
+        xml.contains(
+            "<testcase name=\"state::removes\" classname=\"base tests\">\n      \
+             <failure message=\"test failed\">left: 1 &lt; right: 2 &amp; more</failure>"
+        ),
+        "{}",
+        xml
+    );
+    assert!(
+        xml.contains("<testcase name=\"state::slow\" classname=\"base tests\">\n      <skipped/>"),
+        "{}",
+        xml
+    );
+    assert!(
+        xml.contains(
+            "<testcase name=\"app_wasm_tests::mounts\" classname=\"app tests (chrome)\"/>"
+        ),
+        "{}",
+        xml
+    );
+}
+
+#[test]
+fn test_retries_run_a_failed_suite_again() {
+    let workspace = workspace("retries");
+    // Fails on the first run only, like a flaky suite.
+    workspace.fake_tool(
+        "cargo",
+        "if [ ! -e \"$ROOT/pids/ran\" ]; then touch \"$ROOT/pids/ran\"; echo 'test flaky ... FAILED'; exit 101; fi\n\
+         echo 'test flaky ... ok'",
+    );
+
+    let junit = workspace.path().join("junit.xml");
+    let output = run(&workspace, &["--base", "--junit", junit.to_str().unwrap()]);
+    assert!(!output.status.success());
+
+    fs::remove_file(workspace.path().join("pids/ran")).unwrap();
+    let output = run(
+        &workspace,
+        &[
+            "--base",
+            "--retries",
+            "2",
+            "--junit",
+            junit.to_str().unwrap(),
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        stdout.contains("[base tests] failed with exit status: 101, retrying (attempt 2 of 3)"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("on attempt 2"), "{}", stdout);
+    assert!(stdout.contains("[ ok ] base tests"), "{}", stdout);
+    assert!(!stdout.contains("[FAIL] base tests"), "{}", stdout);
+    let xml = fs::read_to_string(&junit).unwrap();
+    assert!(xml.contains("failures=\"0\""), "{}", xml);
+    assert!(
+        xml.contains("<property name=\"attempts\" value=\"2\"/>"),
+        "{}",
+        xml
+    );
+}
+
+#[test]
+fn test_app_test_options_reach_wasm_pack() {
+    let workspace = workspace("options");
+    // Records its arguments, the test timeout and the capabilities it was given.
+    workspace.fake_tool(
+        "wasm-pack",
+        "if [ \"$1\" = \"--version\" ]; then echo \"wasm-pack 0.13.1\"; exit 0; fi\n\
+         echo \"$@\" > \"$ROOT/args\"\n\
+         echo \"$WASM_BINDGEN_TEST_TIMEOUT\" > \"$ROOT/timeout\"\n\
+         if [ -n \"$WASM_BINDGEN_TEST_WEBDRIVER_JSON\" ]; then cat \"$WASM_BINDGEN_TEST_WEBDRIVER_JSON\" > \"$ROOT/capabilities\"; fi",
+    );
+    let read = |file: &str| fs::read_to_string(workspace.path().join(file)).unwrap();
+
+    let output = run(
+        &workspace,
+        &[
+            "--app",
+            "--node",
+            "--filter",
+            "drawing_state",
+            "--timeout",
+            "2m",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(read("args").trim(), "test --node . drawing_state");
+    assert_eq!(read("timeout").trim(), "120");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[ ok ] app tests (node)"), "{}", stdout);
+
+    workspace.fake_tool("chromedriver", "exit 0");
+    fs::write(
+        workspace.path().join("webdriver.json"),
+        r#"{"goog:chromeOptions": {"args": ["window-size=800,600"]}, "acceptInsecureCerts": true}"#,
+    )
+    .unwrap();
+    let driver = workspace.path().join("bin/chromedriver");
+    let webdriver_json = workspace.path().join("webdriver.json");
+    let output = run(
+        &workspace,
+        &[
+            "--app",
+            "--browser",
+            "chrome",
+            "--headless",
+            "--driver-path",
+            driver.to_str().unwrap(),
+            "--webdriver-json",
+            webdriver_json.to_str().unwrap(),
+            "--browser-arg=--lang=de",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        read("args").trim(),
+        format!(
+            "test --chrome --chromedriver {} --headless .",
+            driver.canonicalize().unwrap().display()
+        )
+    );
+    let capabilities: serde_json::Value = serde_json::from_str(&read("capabilities")).unwrap();
+    assert_eq!(
+        capabilities,
+        serde_json::json!({
+            "goog:chromeOptions": {"args": ["window-size=800,600", "--lang=de"]},
+            "acceptInsecureCerts": true,
+        })
+    );
+}
+
+#[test]
+fn test_driver_path_needs_a_single_browser() {
+    let workspace = workspace("driver-path");
+    workspace.fake_tool("wasm-pack", "exit 0");
+
+    let output = run(
+        &workspace,
+        &["--app", "--headless", "--driver-path", "chromedriver"],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--driver-path needs a single browser"),
+        "{}",
+        stderr
+    );
+
+    let output = run(&workspace, &["--app", "--driver-path", "chromedriver"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--headless"), "{}", stderr);
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use common::{cli, TempWorkspace};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Output, Stdio};
+
+// A workspace with a static directory and nested directories to start from.
+fn workspace(name: &str) -> TempWorkspace {
+    let workspace =
+        TempWorkspace::with_files("workspace", name, &[("static/index.html", "<html></html>")]);
+    fs::create_dir_all(workspace.path().join("nested").join("deeper")).unwrap();
+    workspace
+}
+
+// Starts `cli serve` and returns the workspace root it reports, then stops it.
+fn detected_root(current_dir: &Path, extra_args: &[&str]) -> String {
+    let mut child = cli()
+        .args(extra_args)
+        .args(["serve", "--host", "127.0.0.1", "--port", "0"])
+        .current_dir(current_dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut first_line = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut first_line)
+        .unwrap();
+    let _ = child.kill();
+    let _ = child.wait();
+    first_line
+        .trim()
+        .strip_prefix("Workspace root detected as: ")
+        .unwrap_or_else(|| panic!("Unexpected output: {}", first_line))
+        .to_string()
+}
+
+// Runs the CLI in `current_dir`, so it has to find the workspace itself.
+fn run_cli(current_dir: &Path, args: &[&str]) -> Output {
+    cli().args(args).current_dir(current_dir).output().unwrap()
+}
+
+#[test]
+fn test_workspace_is_found_from_a_nested_directory() {
+    let workspace = workspace("nested");
+    let root = detected_root(&workspace.path().join("nested").join("deeper"), &[]);
+    assert_eq!(root, workspace.path().display().to_string());
+}
+
+#[test]
+fn test_workspace_flag_overrides_discovery() {
+    let workspace = workspace("override");
+    let root = detected_root(
+        &std::env::temp_dir(),
+        &["--workspace", workspace.path().to_str().unwrap()],
+    );
+    assert_eq!(root, workspace.path().display().to_string());
+}
+
+#[test]
+fn test_workspace_flag_requires_a_manifest() {
+    let workspace = workspace("no-manifest");
+    let output = run_cli(
+        workspace.path(),
+        &[
+            "--workspace",
+            workspace.path().join("static").to_str().unwrap(),
+            "build",
+        ],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("No Cargo.toml found"),
+        "Unexpected error: {}",
+        stderr
+    );
+}
+
+#[test]
+fn test_invalid_config_reports_the_offending_key() {
+    let workspace = workspace("bad-config");
+    fs::write(
+        workspace.path().join("project.toml"),
+        "[server]\nprot = 9000\n",
+    )
+    .unwrap();
+
+    let output = run_cli(workspace.path(), &["serve"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("project.toml"),
+        "Unexpected error: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("unknown field `prot`"),
+        "Unexpected error: {}",
+        stderr
+    );
+}
+
+#[test]
+fn test_config_cannot_live_in_two_places() {
+    let workspace = workspace("two-configs");
+    fs::write(workspace.path().join("project.toml"), "").unwrap();
+    fs::write(
+        workspace.path().join("Cargo.toml"),
+        "[workspace]\nmembers = []\n\n[workspace.metadata.cli.server]\nport = 9000\n",
+    )
+    .unwrap();
+
+    let output = run_cli(workspace.path(), &["serve"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Found both"),
+        "Unexpected error: {}",
+        stderr
+    );
+}
+
+#[test]
+fn test_invalid_size_budget_is_rejected() {
+    let workspace = workspace("bad-budget");
+    fs::write(
+        workspace.path().join("project.toml"),
+        "[build]\nwasm-opt = \"z\"\nsize-budget = \"500 parsecs\"\n",
+    )
+    .unwrap();
+
+    let output = run_cli(workspace.path(), &["serve"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("invalid size '500 parsecs'"),
+        "Unexpected error: {}",
+        stderr
+    );
+}
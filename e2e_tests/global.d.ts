@@ -12,6 +12,10 @@ declare global {
      * This type is imported from the wasm-pack generated `app.d.ts` file.
      */
     appInstance?: AppHandle;
+    /** Set by `static/bench.html` once the browser benchmarks have run. */
+    benchResults?: { id: string; time_ns: number; iterations: number }[];
+    /** Set by `static/bench.html` when the browser benchmarks failed. */
+    benchError?: string;
   }
 }
 
@@ -1,6 +1,10 @@
 // @ts-check
 import { defineConfig, devices } from '@playwright/test';
 
+// `cli e2e` builds the app, serves it on a free port and passes the address
+// here. Without it, Playwright starts the dev server itself.
+const baseURL = process.env.E2E_BASE_URL;
+
 export default defineConfig({
   testDir: './e2e_tests',
   fullyParallel: true,
@@ -8,7 +12,7 @@ export default defineConfig({
   retries: process.env.CI ? 2 : 0,
   reporter: 'html',
   use: {
-    baseURL: 'http://127.0.0.1:8080',
+    baseURL: baseURL ?? 'http://127.0.0.1:8080',
     trace: 'on-first-retry',
   },
   projects: [
@@ -25,7 +29,7 @@ export default defineConfig({
       use: { ...devices['Desktop Safari'], hasTouch: true },
     }] : []),
   ],
-  webServer: {
+  webServer: baseURL ? undefined : {
     command: 'cargo run --bin cli -- dev --port 8080 --host 127.0.0.1 --wasm-debug',
     url: 'http://127.0.0.1:8080',
     reuseExistingServer: !process.env.CI,
@@ -27,3 +27,33 @@ export async function initializeApp() {
         return null;
     }
 }
+
+/**
+ * Replaces a running app with a freshly built WASM package while keeping its
+ * drawing. Used by the dev server's live reload after a rebuild.
+ * @param {import('../pkg/app.js').AppHandle} current The running app, destroyed on success.
+ * @param {string} version Appended to the package URLs to bypass module and HTTP caches.
+ * @returns {Promise<import('../pkg/app.js').AppHandle|null>} The new app, or null if the
+ *   state could not be carried over and the page should be reloaded instead.
+ */
+export async function hotReloadApp(current, version) {
+    const state = current.getDrawingState();
+
+    // Load the new package before tearing anything down, so a failed fetch
+    // leaves the current app running.
+    const pkg = await import(`../pkg/app.js?v=${version}`);
+    await pkg.default({ module_or_path: new URL(`../pkg/app_bg.wasm?v=${version}`, import.meta.url) });
+
+    current.destroy();
+    const next = pkg.mount_app();
+    try {
+        next.initializeRenderer();
+        next.loadDrawingState(state);
+    } catch (e) {
+        console.warn("Could not restore the drawing into the new WASM package:", e);
+        next.destroy();
+        return null;
+    }
+    next.start();
+    return next;
+}
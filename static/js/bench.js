@@ -0,0 +1,107 @@
+// static/js/bench.js
+// @ts-check
+import init, { mount_app } from '../pkg/app.js';
+
+/**
+ * @typedef {Object} BenchResult
+ * @property {string} id Name of the benchmark, e.g. `browser/render_frame/1000`.
+ * @property {number} time_ns Median time of one call, in nanoseconds.
+ * @property {number} iterations How many calls were measured.
+ */
+
+/** The drawing sizes the rendering and state transfer are measured at. */
+const SIZES = [1000, 10000];
+
+/** How long each benchmark collects samples for, in milliseconds. */
+const MEASURE_MS = 1000;
+
+/** Each sample runs enough calls to take at least this long, as timers are coarse. */
+const MIN_SAMPLE_MS = 5;
+
+/**
+ * A drawing of `count` shapes spread over the canvas, the same on every run.
+ * @param {number} count
+ */
+function drawing(count) {
+    let seed = 42;
+    const random = () => {
+        seed = (seed * 1664525 + 1013904223) % 4294967296;
+        return seed / 4294967296;
+    };
+    const shapes = [];
+    for (let i = 0; i < count; i++) {
+        shapes.push({
+            x: random() * 800,
+            y: random() * 600,
+            radius: 10 + random() * 40,
+            color: `rgb(${Math.floor(random() * 256)}, ${Math.floor(random() * 256)}, ${Math.floor(random() * 256)})`,
+            opacity: 1,
+        });
+    }
+    return { shapes };
+}
+
+/**
+ * Times `fn` and returns the median time of one call.
+ * @param {string} id
+ * @param {() => unknown} fn
+ * @returns {BenchResult}
+ */
+function measure(id, fn) {
+    // Warm up, and find how many calls make a sample long enough to time.
+    let batch = 1;
+    for (;;) {
+        const start = performance.now();
+        for (let i = 0; i < batch; i++) fn();
+        if (performance.now() - start >= MIN_SAMPLE_MS) break;
+        batch *= 2;
+    }
+
+    /** @type {number[]} */
+    const samples = [];
+    const end = performance.now() + MEASURE_MS;
+    while (performance.now() < end || samples.length < 5) {
+        const start = performance.now();
+        for (let i = 0; i < batch; i++) fn();
+        samples.push((performance.now() - start) / batch);
+    }
+    samples.sort((a, b) => a - b);
+    const median = samples[Math.floor(samples.length / 2)];
+    return { id, time_ns: median * 1e6, iterations: samples.length * batch };
+}
+
+/**
+ * Mounts the app without its render loop and measures drawing a frame,
+ * reading the state into JavaScript and the cost of a bare wasm↔JS call.
+ * @param {(result: BenchResult) => void} [onResult] Called as each benchmark finishes.
+ * @returns {Promise<BenchResult[]>}
+ */
+export async function runBenchmarks(onResult = () => {}) {
+    await init();
+    const app = mount_app();
+    app.initializeRenderer();
+
+    /** @type {BenchResult[]} */
+    const results = [];
+    /** @param {BenchResult} result */
+    const record = (result) => {
+        results.push(result);
+        onResult(result);
+    };
+    // Let the page paint between benchmarks, so progress shows.
+    const yieldToBrowser = () => new Promise((resolve) => setTimeout(resolve, 0));
+
+    try {
+        record(measure('browser/call_overhead/isRunning', () => app.isRunning()));
+        for (const count of SIZES) {
+            app.loadDrawingState(drawing(count));
+            await yieldToBrowser();
+            record(measure(`browser/render_frame/${count}`, () => app.renderFrame()));
+            await yieldToBrowser();
+            record(measure(`browser/get_drawing_state/${count}`, () => app.getDrawingState()));
+        }
+    } finally {
+        app.destroy();
+    }
+    return results;
+}